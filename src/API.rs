@@ -1,68 +1,911 @@
-use reqwest::StatusCode;
-use crate::API::objects::VapiHost;
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+use std::thread;
+use std::time::{Duration, Instant};
+use serde::de::DeserializeOwned;
+use crate::API::objects::{VapiBatchResult, VapiCluster, VapiDeploymentResult, VapiDiskSummary, VapiHost, VapiMoRef, VapiObjectTags, VapiPowerInfo, VapiRetrieveResult, VapiSnapshotInfo, VapiTaskInfo};
 mod api_impl;
+mod config;
+mod error;
+mod filter;
 mod objects;
+mod spec;
+mod tls;
 
+pub use objects::{VapiCategory, VapiTag, VapiLibrary, VapiLibraryItem, VapiDatacenter, VapiCdrom, VapiDatastore, VapiDisk, VapiFolder, VapiNetwork, VapiResourcePool, VapiNic, VapiSnapshot, VapiVmInfo};
 
-pub fn new_api(hostname : String) -> api_impl::VcenterApi {
-    return api_impl::VcenterApi::new(hostname);
+pub use api_impl::VcenterApi;
+pub use config::{ApiVersion, ConnectionConfig};
+pub use error::ApiError;
+pub use filter::{ClusterFilter, DatastoreFilter, FolderFilter, HostFilter, NetworkFilter, ResourcePoolFilter, VmFilter};
+use filter::{ListFilter, LIST_CHUNK, POWER_STATES};
+pub use spec::{CloneSpec, CpuUpdate, CreateSpec, DeploySpec, DiskProvisioning, DiskSpec, IsoImage, MemoryUpdate};
+pub use tls::{same_fingerprint, CertificateCheck, TlsMode};
+
+//biggest vmdk vsphere allows, 62 TiB
+pub const MAX_DISK_GIB: u64 = 62 * 1024;
+//most vcpus and memory (24 TiB) of a vsphere 8 vm
+pub const MAX_CPU_COUNT: u32 = 768;
+pub const MAX_MEMORY_MIB: u64 = 24 * 1024 * 1024;
+
+//bytes of a disk size, sizes above the vmdk limit are refused before they reach vcenter
+fn gib_bytes(gib : u64) -> Result<u64, ApiError> {
+    if gib > MAX_DISK_GIB {
+        return Err(ApiError::Failed(format!("disks are at most {} GiB", MAX_DISK_GIB)));
+    }
+    gib.checked_mul(1024 * 1024 * 1024).ok_or_else(|| ApiError::Failed(format!("{} GiB is too big", gib)))
+}
+
+//every /rest answer wraps the payload in {"value": ...}, /api answers are the payload itself
+fn parse_value<T: DeserializeOwned>(api: &api_impl::VcenterApi, body: &str) -> Result<T, ApiError> {
+    let v: serde_json::Value = serde_json::from_str(body)?;
+    let value = match api.version() {
+        ApiVersion::Rest => v
+            .get("value")
+            .cloned()
+            .ok_or_else(|| ApiError::UnexpectedJson("missing \"value\" field".to_string()))?,
+        ApiVersion::Api => v,
+    };
+    let value = serde_json::from_value(value)?;
+    Ok(value)
+}
+
+pub fn check_certificate(hostname : &str, config : &ConnectionConfig) -> Result<CertificateCheck, ApiError> {
+    tls::check_certificate(hostname, &config.tls, config.connect_timeout)
+}
+
+pub fn known_host(path : &Path, hostname : &str) -> Option<String> {
+    tls::load_known_hosts(path).remove(hostname)
+}
+
+pub fn trust_host(path : &Path, hostname : &str, fingerprint : &str) -> Result<(), ApiError> {
+    tls::save_known_host(path, hostname, fingerprint)
+}
+
+pub fn new_api(hostname : String, config : ConnectionConfig) -> Result<api_impl::VcenterApi, ApiError> {
+    api_impl::VcenterApi::new(hostname, config)
+}
+
+//send the filter in chunks and merge the answers, an object found twice is kept once
+fn list_chunked<F, T>(api : &api_impl::VcenterApi, filter : &F, request : impl Fn(&F) -> Result<String, ApiError>, id : impl Fn(&T) -> &String) -> Result<Vec<T>, ApiError>
+where
+    F: ListFilter,
+    T: DeserializeOwned,
+{
+    let mut objects: Vec<T> = Vec::new();
+    let mut seen = HashSet::new();
+    for chunk in filter.chunks(LIST_CHUNK) {
+        let found: Vec<T> = parse_value(api, &request(&chunk)?)?;
+        found.into_iter().for_each(|object| {
+            if seen.insert(id(&object).clone()) {
+                objects.push(object);
+            }
+        });
+    }
+    Ok(objects)
+}
+
+//hosts matching the filter, long lists of values are sent in several requests
+pub fn list_hosts(api : api_impl::VcenterApi, filter : &HostFilter) -> Result<Vec<VapiHost>, ApiError> {
+    list_chunked(&api, filter, |chunk| api_impl::VcenterApi::list_hosts(&api, chunk), |host: &VapiHost| &host.host)
+}
+
+//vms matching the filter, a query over the result cap of vcenter is split by host and then by power state
+pub fn list_vms(api : api_impl::VcenterApi, filter : &VmFilter) -> Result<Vec<objects::VapiVm>, ApiError> {
+    let mut vms: Vec<objects::VapiVm> = Vec::new();
+    let mut seen = HashSet::new();
+    for chunk in filter.chunks(LIST_CHUNK) {
+        let found = match api_impl::VcenterApi::list_vms(&api, &chunk) {
+            Ok(found) => parse_value(&api, &found)?,
+            Err(e) if e.is_result_limit() => match split_vm_filter(api.clone(), &chunk)? {
+                Some(parts) => {
+                    let mut found = Vec::new();
+                    for part in parts {
+                        found.extend(list_vms(api.clone(), &part)?);
+                    }
+                    found
+                }
+                None => return Err(e),
+            },
+            Err(e) => return Err(e),
+        };
+        found.into_iter().for_each(|vm| {
+            if seen.insert(vm.vm.clone()) {
+                vms.push(vm);
+            }
+        });
+    }
+    Ok(vms)
+}
+
+//narrower filters covering the same vms, None when it can not be split any more
+fn split_vm_filter(api : api_impl::VcenterApi, filter : &VmFilter) -> Result<Option<Vec<VmFilter>>, ApiError> {
+    let with = |change: &dyn Fn(&mut VmFilter)| {
+        let mut part = filter.clone();
+        change(&mut part);
+        part
+    };
+    if filter.hosts.is_empty() {
+        let hosts = list_hosts(api, &HostFilter {
+            datacenters: filter.datacenters.clone(),
+            clusters: filter.clusters.clone(),
+            ..HostFilter::default()
+        })?;
+        return Ok(Some(hosts.iter().map(|host| with(&|part| part.hosts = vec![host.host.clone()])).collect()));
+    }
+    if filter.hosts.len() > 1 {
+        return Ok(Some(filter.hosts.iter().map(|host| with(&|part| part.hosts = vec![host.clone()])).collect()));
+    }
+    if filter.power_states.len() != 1 {
+        let states: Vec<String> = if filter.power_states.is_empty() {
+            POWER_STATES.iter().map(|state| state.to_string()).collect()
+        } else {
+            filter.power_states.clone()
+        };
+        return Ok(Some(states.iter().map(|state| with(&|part| part.power_states = vec![state.clone()])).collect()));
+    }
+    Ok(None)
+}
+
+pub fn get_datacenters(api : api_impl::VcenterApi) -> Result<Vec<VapiDatacenter>, ApiError> {
+    let datacenters = api_impl::VcenterApi::list_datacenters(&api, &[])?;
+    parse_value(&api, &datacenters)
+}
+
+//id of the datacenter with this name
+pub fn get_datacenter(api : api_impl::VcenterApi, name : String) -> Result<VapiDatacenter, ApiError> {
+    let datacenters = api_impl::VcenterApi::list_datacenters(&api, std::slice::from_ref(&name))?;
+    let datacenters: Vec<VapiDatacenter> = parse_value(&api, &datacenters)?;
+    datacenters.into_iter().next().ok_or_else(|| ApiError::NotFound(format!("Datacenter {}", name)))
+}
+
+pub fn list_clusters(api : api_impl::VcenterApi, filter : &ClusterFilter) -> Result<Vec<VapiCluster>, ApiError> {
+    list_chunked(&api, filter, |chunk| api_impl::VcenterApi::list_clusters(&api, chunk), |cluster: &VapiCluster| &cluster.cluster)
+}
+
+//clusters of the datacenter with this id, every cluster of vcenter when None
+pub fn get_clusters(api : api_impl::VcenterApi, datacenter : Option<String>) -> Result<Vec<VapiCluster>, ApiError> {
+    list_clusters(api, &ClusterFilter { datacenters: datacenter.into_iter().collect(), ..ClusterFilter::default() })
+}
+
+pub fn get_cluster_hosts(api : api_impl::VcenterApi, cluster : String) -> Result<Vec<VapiHost>, ApiError> {
+    list_hosts(api, &HostFilter { clusters: vec![cluster], ..HostFilter::default() })
+}
+
+pub fn list_datastores(api : api_impl::VcenterApi, filter : &DatastoreFilter) -> Result<Vec<VapiDatastore>, ApiError> {
+    list_chunked(&api, filter, |chunk| api_impl::VcenterApi::list_datastores(&api, chunk), |datastore: &VapiDatastore| &datastore.datastore)
+}
+
+//datastores of the datacenter with this id, every datastore of vcenter when None
+pub fn get_datastores(api : api_impl::VcenterApi, datacenter : Option<String>) -> Result<Vec<VapiDatastore>, ApiError> {
+    list_datastores(api, &DatastoreFilter { datacenters: datacenter.into_iter().collect(), ..DatastoreFilter::default() })
+}
+
+//ids of the vms with disks or files on the datastore
+pub fn get_datastore_vms(api : api_impl::VcenterApi, datastore : String) -> Result<Vec<String>, ApiError> {
+    let vms = api_impl::VcenterApi::get_datastore_vms(&api, datastore)?;
+    //vi/json answers are never wrapped in {"value": ...}
+    let vms: Vec<VapiMoRef> = serde_json::from_str(&vms)?;
+    Ok(vms.into_iter().map(|vm| vm.value).collect())
+}
+
+pub fn list_networks(api : api_impl::VcenterApi, filter : &NetworkFilter) -> Result<Vec<VapiNetwork>, ApiError> {
+    list_chunked(&api, filter, |chunk| api_impl::VcenterApi::list_networks(&api, chunk), |network: &VapiNetwork| &network.network)
+}
+
+//networks and port groups of the datacenter with this id, every network of vcenter when None
+pub fn get_networks(api : api_impl::VcenterApi, datacenter : Option<String>) -> Result<Vec<VapiNetwork>, ApiError> {
+    list_networks(api, &NetworkFilter { datacenters: datacenter.into_iter().collect(), ..NetworkFilter::default() })
 }
 
-pub fn get_all_hosts(api : api_impl::VcenterApi,session : String) -> Vec<VapiHost>{
-    let hosts = api_impl::VcenterApi::get_all_hosts(&api,session);
-    let v2: serde_json::Value = serde_json::from_str(&hosts).unwrap();
-    let host_list: Vec<VapiHost> = serde_json::from_value(v2["value"].clone()).unwrap();
-    return host_list;
+//ids of the vms with a nic on the network
+pub fn get_network_vms(api : api_impl::VcenterApi, network : String) -> Result<Vec<String>, ApiError> {
+    let vms = api_impl::VcenterApi::get_network_vms(&api, network)?;
+    //vi/json answers are never wrapped in {"value": ...}
+    let vms: Vec<VapiMoRef> = serde_json::from_str(&vms)?;
+    Ok(vms.into_iter().map(|vm| vm.value).collect())
 }
 
-pub fn get_host(api : api_impl::VcenterApi,session : String, name : String) -> VapiHost{
-    let host = api_impl::VcenterApi::get_host(&api,session, name);
-    let v2: serde_json::Value = serde_json::from_str(&host).unwrap();
-    let host: Vec<VapiHost> = serde_json::from_value(v2["value"].clone()).unwrap();
-    return host[0].clone();
+pub fn connect_nic(api : api_impl::VcenterApi, vm : String, nic : String) -> Result<(), ApiError> {
+    api_impl::VcenterApi::nic_connection(&api, vm, nic, "connect")
 }
 
-pub fn get_all_vms(api : api_impl::VcenterApi,session : String) -> Vec<objects::VapiVm> {
-    let vms = api_impl::VcenterApi::get_all_vms(&api,session);
-    let v2: serde_json::Value = serde_json::from_str(&vms).unwrap();
-    let vm_list: Vec<objects::VapiVm> = serde_json::from_value(v2["value"].clone()).unwrap();
-    return vm_list;
+pub fn disconnect_nic(api : api_impl::VcenterApi, vm : String, nic : String) -> Result<(), ApiError> {
+    api_impl::VcenterApi::nic_connection(&api, vm, nic, "disconnect")
 }
 
-pub fn get_vms_from_host(api : api_impl::VcenterApi,session: String, host: String) -> Vec<objects::VapiVm> {
-    let sessiontmp = session.clone();
-    let host = api_impl::VcenterApi::get_host(&api,session, host);
-    let hostjson: serde_json::Value = serde_json::from_str(&host).unwrap();
-    let host: Vec<VapiHost> = serde_json::from_value(hostjson["value"].clone()).unwrap();
+//whether the nic is connected when the vm powers on
+pub fn set_nic_start_connected(api : api_impl::VcenterApi, vm : String, nic : String, start_connected : bool) -> Result<(), ApiError> {
+    api_impl::VcenterApi::update_nic(&api, vm, nic, serde_json::json!({ "start_connected": start_connected }))
+}
+
+//the backing type is the type of the network: STANDARD_PORTGROUP, DISTRIBUTED_PORTGROUP or OPAQUE_NETWORK
+pub fn move_nic(api : api_impl::VcenterApi, vm : String, nic : String, network : &VapiNetwork) -> Result<(), ApiError> {
+    let spec = serde_json::json!({
+        "backing": { "type": network.network_type, "network": network.network },
+    });
+    api_impl::VcenterApi::update_nic(&api, vm, nic, spec)
+}
+
+pub fn list_folders(api : api_impl::VcenterApi, filter : &FolderFilter) -> Result<Vec<VapiFolder>, ApiError> {
+    list_chunked(&api, filter, |chunk| api_impl::VcenterApi::list_folders(&api, chunk), |folder: &VapiFolder| &folder.folder)
+}
+
+//vm folders of the datacenter with this id, every vm folder of vcenter when None
+pub fn get_vm_folders(api : api_impl::VcenterApi, datacenter : Option<String>) -> Result<Vec<VapiFolder>, ApiError> {
+    list_folders(api, &FolderFilter {
+        types: vec!["VIRTUAL_MACHINE".to_string()],
+        datacenters: datacenter.into_iter().collect(),
+        ..FolderFilter::default()
+    })
+}
+
+pub fn list_resource_pools(api : api_impl::VcenterApi, filter : &ResourcePoolFilter) -> Result<Vec<VapiResourcePool>, ApiError> {
+    list_chunked(&api, filter, |chunk| api_impl::VcenterApi::list_resource_pools(&api, chunk), |pool: &VapiResourcePool| &pool.resource_pool)
+}
+
+//resource pools of the datacenter with this id, every resource pool of vcenter when None
+pub fn get_resource_pools(api : api_impl::VcenterApi, datacenter : Option<String>) -> Result<Vec<VapiResourcePool>, ApiError> {
+    list_resource_pools(api, &ResourcePoolFilter { datacenters: datacenter.into_iter().collect(), ..ResourcePoolFilter::default() })
+}
+
+//hosts of the datacenter with this id, every host of vcenter when None
+pub fn get_all_hosts(api : api_impl::VcenterApi, datacenter : Option<String>) -> Result<Vec<VapiHost>, ApiError> {
+    list_hosts(api, &HostFilter { datacenters: datacenter.into_iter().collect(), ..HostFilter::default() })
+}
+
+#[allow(dead_code)]
+pub fn get_host(api : api_impl::VcenterApi, name : String) -> Result<VapiHost, ApiError> {
+    let host = list_hosts(api, &HostFilter { names: vec![name.clone()], ..HostFilter::default() })?;
+    host.into_iter().next().ok_or_else(|| ApiError::NotFound(format!("Host {}", name)))
+}
+
+#[allow(dead_code)]
+pub fn get_all_vms(api : api_impl::VcenterApi) -> Result<Vec<objects::VapiVm>, ApiError> {
+    list_vms(api, &VmFilter::default())
+}
+
+pub fn get_vm_details(api : api_impl::VcenterApi, vm : String) -> Result<VapiVmInfo, ApiError> {
+    let vm = api_impl::VcenterApi::get_vm(&api, vm)?;
+    let mut vm: VapiVmInfo = parse_value(&api, &vm)?;
+    vm.nics.iter_mut().for_each(|(key, nic)| nic.nic = key.clone());
+    vm.disks.iter_mut().for_each(|(key, disk)| disk.disk = key.clone());
+    vm.cdroms.iter_mut().for_each(|(key, cdrom)| cdrom.cdrom = key.clone());
+    Ok(vm)
+}
+
+//content library isos are stored in contentlib-... folders of the library datastore
+pub fn is_library_iso(cdrom : &VapiCdrom) -> bool {
+    cdrom.backing.iso_file.as_ref().is_some_and(|file| file.contains("contentlib-"))
+}
+
+//a datastore iso goes in the first cd-rom of the vm, a library iso on a new one
+//the cd-rom is disconnected while its backing changes and connected again on a powered on vm
+pub fn mount_iso(api : api_impl::VcenterApi, vm : String, cdroms : &[VapiCdrom], image : &IsoImage, powered_on : bool) -> Result<(), ApiError> {
+    let file = match image {
+        IsoImage::Library(item) => {
+            api_impl::VcenterApi::mount_library_iso(&api, item.clone(), vm)?;
+            return Ok(());
+        }
+        IsoImage::Datastore(file) => file,
+    };
+    let cdrom = cdroms.first().ok_or_else(|| ApiError::NotFound("CD-ROM device".to_string()))?;
+    if cdrom.state == "CONNECTED" {
+        api_impl::VcenterApi::cdrom_connection(&api, vm.clone(), cdrom.cdrom.clone(), "disconnect")?;
+    }
+    let spec = serde_json::json!({
+        "backing": { "type": "ISO_FILE", "iso_file": file },
+        "start_connected": true,
+    });
+    api_impl::VcenterApi::update_cdrom(&api, vm.clone(), cdrom.cdrom.clone(), spec)?;
+    if powered_on {
+        api_impl::VcenterApi::cdrom_connection(&api, vm, cdrom.cdrom.clone(), "connect")?;
+    }
+    Ok(())
+}
+
+//every iso of the vm is ejected, the cd-roms made for library isos are removed
+pub fn eject_iso(api : api_impl::VcenterApi, vm : String, cdroms : &[VapiCdrom]) -> Result<(), ApiError> {
+    for cdrom in cdroms.iter().filter(|cdrom| cdrom.backing.backing_type == "ISO_FILE") {
+        if is_library_iso(cdrom) {
+            api_impl::VcenterApi::unmount_library_iso(&api, vm.clone(), cdrom.cdrom.clone())?;
+            continue;
+        }
+        if cdrom.state == "CONNECTED" {
+            api_impl::VcenterApi::cdrom_connection(&api, vm.clone(), cdrom.cdrom.clone(), "disconnect")?;
+        }
+        let spec = serde_json::json!({
+            "backing": { "type": "CLIENT_DEVICE", "device_access_type": "EMULATION" },
+            "start_connected": false,
+        });
+        api_impl::VcenterApi::update_cdrom(&api, vm.clone(), cdrom.cdrom.clone(), spec)?;
+    }
+    Ok(())
+}
+
+//virtual disks of the vm with their size and vmdk file
+pub fn get_vm_disks(api : api_impl::VcenterApi, vm : String) -> Result<Vec<VapiDisk>, ApiError> {
+    let disks = api_impl::VcenterApi::list_disks(&api, vm.clone())?;
+    let disks: Vec<VapiDiskSummary> = parse_value(&api, &disks)?;
+    let mut found = Vec::new();
+    for summary in disks {
+        let disk = api_impl::VcenterApi::get_disk(&api, vm.clone(), summary.disk.clone())?;
+        let mut disk: VapiDisk = parse_value(&api, &disk)?;
+        disk.disk = summary.disk;
+        found.push(disk);
+    }
+    Ok(found)
+}
+
+//devices of the vm as vi/json sends them, a disk has the same key as its id in vcenter/vm
+fn get_vm_devices(api : &api_impl::VcenterApi, vm : &str) -> Result<Vec<serde_json::Value>, ApiError> {
+    let config = api_impl::VcenterApi::get_vm_config(api, vm.to_string())?;
+    //vi/json answers are never wrapped in {"value": ...}
+    let config: serde_json::Value = serde_json::from_str(&config)?;
+    config["hardware"]["device"]
+        .as_array()
+        .cloned()
+        .ok_or_else(|| ApiError::UnexpectedJson("missing \"hardware.device\" field".to_string()))
+}
+
+//send a device change to the vm and wait for vcenter to apply it
+fn reconfigure_device(api : &api_impl::VcenterApi, vm : &str, change : serde_json::Value) -> Result<(), ApiError> {
+    let spec = serde_json::json!({
+        "_typeName": "VirtualMachineConfigSpec",
+        "deviceChange": [change],
+    });
+    let task = api_impl::VcenterApi::reconfigure_vm(api, vm.to_string(), spec)?;
+    let task: VapiMoRef = serde_json::from_str(&task)?;
+    wait_for_task(api, &task.value, api.provision_timeout())
+}
+
+//poll a vi/json task until it ends, its error message is kept when it fails
+fn wait_for_task(api : &api_impl::VcenterApi, task : &str, timeout : Duration) -> Result<(), ApiError> {
+    let start = Instant::now();
+    loop {
+        let info = api_impl::VcenterApi::get_task_info(api, task.to_string())?;
+        let info: VapiTaskInfo = serde_json::from_str(&info)?;
+        match info.state.as_str() {
+            "success" => return Ok(()),
+            "error" => {
+                let message = info.error.map(|fault| fault.localized_message).unwrap_or_default();
+                return Err(ApiError::Failed(message));
+            }
+            _ => {}
+        }
+        if start.elapsed() >= timeout {
+            return Err(ApiError::Failed(format!("task {} still running after {} seconds", task, timeout.as_secs())));
+        }
+        thread::sleep(Duration::from_secs(1));
+    }
+}
+
+//free unit of the controller, scsi controllers keep unit 7 for themselves
+fn free_unit(devices : &[serde_json::Value], controller : &serde_json::Value) -> Option<u64> {
+    let key = controller["key"].as_i64();
+    let kind = controller["_typeName"].as_str().unwrap_or_default();
+    let units = if kind.contains("NVME") { 15 } else if kind.contains("AHCI") { 30 } else { 16 };
+    let used: Vec<u64> = devices.iter()
+        .filter(|device| device["controllerKey"].as_i64() == key)
+        .filter_map(|device| device["unitNumber"].as_u64())
+        .collect();
+    (0..units).find(|unit| !used.contains(unit) && (units != 16 || *unit != 7))
+}
 
+//the disk goes on the controller of the first disk of the vm, or on its first disk controller
+pub fn add_disk(api : api_impl::VcenterApi, vm : String, spec : &DiskSpec) -> Result<(), ApiError> {
+    const CONTROLLERS: [&str; 6] = [
+        "ParaVirtualSCSIController",
+        "VirtualLsiLogicSASController",
+        "VirtualLsiLogicController",
+        "VirtualBusLogicController",
+        "VirtualNVMEController",
+        "VirtualAHCIController",
+    ];
+    let devices = get_vm_devices(&api, &vm)?;
+    let controller_key = devices.iter()
+        .find(|device| device["_typeName"] == "VirtualDisk")
+        .and_then(|disk| disk["controllerKey"].as_i64());
+    let controller = devices.iter()
+        .find(|device| match controller_key {
+            Some(key) => device["key"].as_i64() == Some(key),
+            None => CONTROLLERS.contains(&device["_typeName"].as_str().unwrap_or_default()),
+        })
+        .ok_or_else(|| ApiError::Failed("the vm has no disk controller".to_string()))?;
+    let unit = free_unit(&devices, controller)
+        .ok_or_else(|| ApiError::Failed("the disk controller has no free unit".to_string()))?;
 
+    let bytes = gib_bytes(spec.capacity_gib)?;
+    let change = serde_json::json!({
+        "_typeName": "VirtualDeviceConfigSpec",
+        "operation": "add",
+        "fileOperation": "create",
+        "device": {
+            "_typeName": "VirtualDisk",
+            "key": -1,
+            "controllerKey": controller["key"],
+            "unitNumber": unit,
+            "capacityInKB": bytes / 1024,
+            "capacityInBytes": bytes,
+            "backing": {
+                "_typeName": "VirtualDiskFlatVer2BackingInfo",
+                "fileName": format!("[{}]", spec.datastore),
+                "diskMode": "persistent",
+                "thinProvisioned": spec.provisioning == DiskProvisioning::Thin,
+                "eagerlyScrub": spec.provisioning == DiskProvisioning::EagerZeroedThick,
+            },
+        },
+    });
+    reconfigure_device(&api, &vm, change)
+}
 
-    let vms = api_impl::VcenterApi::get_vms_from_host(&api,sessiontmp, host[0].host.clone());
-    let v2: serde_json::Value = serde_json::from_str(&vms).unwrap();
-    let vm_list: Vec<objects::VapiVm> = serde_json::from_value(v2["value"].clone()).unwrap();
-    return vm_list;
+//disks can only grow, vcenter refuses to grow a disk of a vm with snapshots
+pub fn grow_disk(api : api_impl::VcenterApi, vm : String, disk : String, capacity_gib : u64) -> Result<(), ApiError> {
+    let devices = get_vm_devices(&api, &vm)?;
+    let mut device = devices.into_iter()
+        .find(|device| device["_typeName"] == "VirtualDisk" && device["key"].as_i64().map(|key| key.to_string()) == Some(disk.clone()))
+        .ok_or_else(|| ApiError::NotFound(format!("Disk {}", disk)))?;
+    let bytes = gib_bytes(capacity_gib)?;
+    if device["capacityInBytes"].as_u64().is_some_and(|current| current >= bytes) {
+        return Err(ApiError::Failed("the new size has to be bigger than the current one".to_string()));
+    }
+    device["capacityInKB"] = serde_json::json!(bytes / 1024);
+    device["capacityInBytes"] = serde_json::json!(bytes);
+    let change = serde_json::json!({
+        "_typeName": "VirtualDeviceConfigSpec",
+        "operation": "edit",
+        "device": device,
+    });
+    reconfigure_device(&api, &vm, change)
 }
 
-pub fn shutdown_vm(api : api_impl::VcenterApi,session : String, vm : String) -> StatusCode  {
-    return api_impl::VcenterApi::shutdown_vm(&api,session, vm);
+//the vmdk file is kept on the datastore
+pub fn remove_disk(api : api_impl::VcenterApi, vm : String, disk : String) -> Result<(), ApiError> {
+    api_impl::VcenterApi::delete_disk(&api, vm, disk)
 }
 
-pub fn poweron_vm(api : api_impl::VcenterApi,session : String, vm : String) -> StatusCode  {
-    return api_impl::VcenterApi::start_vm(&api,session, vm);
+//vms of the host with this id
+pub fn get_vms_from_host(api : api_impl::VcenterApi, host: String) -> Result<Vec<objects::VapiVm>, ApiError> {
+    list_vms(api, &VmFilter { hosts: vec![host], ..VmFilter::default() })
 }
 
-pub fn reboot_vm(api : api_impl::VcenterApi,session : String, vm : String) -> StatusCode  {
-    return api_impl::VcenterApi::reboot_vm(&api,session, vm);
+pub fn connect_host(api : api_impl::VcenterApi, host : String) -> Result<(), ApiError> {
+    api_impl::VcenterApi::connect_host(&api, host)
 }
 
-pub fn authenticate(api : api_impl::VcenterApi,username : String,password : String, host : String) -> String {
-    let session = api_impl::VcenterApi::get_session(&api
-                                                    ,username
-                                                  , password
-                                                  , host);
-    let v: serde_json::Value = serde_json::from_str(&session).unwrap();
+pub fn disconnect_host(api : api_impl::VcenterApi, host : String) -> Result<(), ApiError> {
+    api_impl::VcenterApi::disconnect_host(&api, host)
+}
 
-    return v["value"].as_str().unwrap().to_string()
+//maintenance mode of the hosts in a single property collector call instead of one call per host
+pub fn get_hosts_maintenance(api : api_impl::VcenterApi, hosts : &[String]) -> Result<HashMap<String, bool>, ApiError> {
+    let mut maintenance = HashMap::new();
+    if hosts.is_empty() {
+        return Ok(maintenance);
+    }
+    let objects : Vec<serde_json::Value> = hosts.iter().map(|host| serde_json::json!({
+        "_typeName": "ObjectSpec",
+        "obj": { "_typeName": "ManagedObjectReference", "type": "HostSystem", "value": host },
+    })).collect();
+    let spec = serde_json::json!({
+        "_typeName": "PropertyFilterSpec",
+        "propSet": [{ "_typeName": "PropertySpec", "type": "HostSystem", "pathSet": ["runtime.inMaintenanceMode"] }],
+        "objectSet": objects,
+    });
+    let mut page = api_impl::VcenterApi::retrieve_properties(&api, spec)?;
+    //vi/json answers are never wrapped in {"value": ...}, an empty answer means nothing was found
+    while !page.trim().is_empty() {
+        let Some(result) = serde_json::from_str::<Option<VapiRetrieveResult>>(&page)? else {
+            break;
+        };
+        result.objects.into_iter().for_each(|object| {
+            let value = object.prop_set.iter().find(|property| property.name == "runtime.inMaintenanceMode").and_then(|property| property.val["_value"].as_bool());
+            if let Some(value) = value {
+                maintenance.insert(object.obj.value, value);
+            }
+        });
+        match result.token {
+            Some(token) => page = api_impl::VcenterApi::continue_retrieve_properties(&api, token)?,
+            None => break,
+        }
+    }
+    Ok(maintenance)
 }
 
+//start a vi/json task on the host and wait for it, so the host state is only changed once vcenter did it
+fn run_host_task(api : &api_impl::VcenterApi, host : String, method : &str, body : serde_json::Value) -> Result<(), ApiError> {
+    let task = api_impl::VcenterApi::host_task(api, host, method, body)?;
+    let task: VapiMoRef = serde_json::from_str(&task)?;
+    wait_for_task(api, &task.value, api.provision_timeout())
+}
+
+//the vms of the host have to be powered off or moved before it enters maintenance mode
+pub fn enter_maintenance(api : api_impl::VcenterApi, host : String) -> Result<(), ApiError> {
+    run_host_task(&api, host, "EnterMaintenanceMode_Task", serde_json::json!({ "timeout": 0 }))
+}
 
+pub fn exit_maintenance(api : api_impl::VcenterApi, host : String) -> Result<(), ApiError> {
+    run_host_task(&api, host, "ExitMaintenanceMode_Task", serde_json::json!({ "timeout": 0 }))
+}
+
+//without force the host has to be in maintenance mode
+pub fn reboot_host(api : api_impl::VcenterApi, host : String) -> Result<(), ApiError> {
+    run_host_task(&api, host, "RebootHost_Task", serde_json::json!({ "force": false }))
+}
+
+pub fn shutdown_host(api : api_impl::VcenterApi, host : String) -> Result<(), ApiError> {
+    run_host_task(&api, host, "ShutdownHost_Task", serde_json::json!({ "force": false }))
+}
+
+//every tag category of vcenter, the list call only gives the ids
+//only the categories missing from known are fetched, a renamed category keeps its old name
+pub fn get_categories(api : api_impl::VcenterApi, known : &[VapiCategory]) -> Result<Vec<VapiCategory>, ApiError> {
+    let ids: Vec<String> = parse_value(&api, &api_impl::VcenterApi::list_categories(&api)?)?;
+    let mut categories = Vec::new();
+    for id in ids {
+        match known.iter().find(|category| category.id == id) {
+            Some(category) => categories.push(category.clone()),
+            None => categories.push(parse_value(&api, &api_impl::VcenterApi::get_category(&api, id)?)?),
+        }
+    }
+    Ok(categories)
+}
+
+//every tag of vcenter, the list call only gives the ids
+//only the tags missing from known are fetched, as the categories
+pub fn get_tags(api : api_impl::VcenterApi, known : &[VapiTag]) -> Result<Vec<VapiTag>, ApiError> {
+    let ids: Vec<String> = parse_value(&api, &api_impl::VcenterApi::list_tags(&api)?)?;
+    let mut tags = Vec::new();
+    for id in ids {
+        match known.iter().find(|tag| tag.id == id) {
+            Some(tag) => tags.push(tag.clone()),
+            None => tags.push(parse_value(&api, &api_impl::VcenterApi::get_tag(&api, id)?)?),
+        }
+    }
+    Ok(tags)
+}
+
+fn vm_object_ids(vms : &[String]) -> serde_json::Value {
+    vms.iter().map(|vm| serde_json::json!({ "type": "VirtualMachine", "id": vm })).collect()
+}
+
+//ids of the tags attached to each vm, a vm without tags is in the map with an empty list
+pub fn get_vm_tags(api : api_impl::VcenterApi, vms : &[String]) -> Result<HashMap<String, Vec<String>>, ApiError> {
+    let mut tags: HashMap<String, Vec<String>> = vms.iter().map(|vm| (vm.clone(), Vec::new())).collect();
+    for chunk in vms.chunks(LIST_CHUNK) {
+        let attached = api_impl::VcenterApi::list_attached_tags(&api, vm_object_ids(chunk))?;
+        let attached: Vec<VapiObjectTags> = parse_value(&api, &attached)?;
+        attached.into_iter().for_each(|object| {
+            tags.insert(object.object_id.id, object.tag_ids);
+        });
+    }
+    Ok(tags)
+}
+
+//vcenter answers 200 with success false when the tag could not be changed on some vms
+fn check_batch(api : &api_impl::VcenterApi, body : &str) -> Result<(), ApiError> {
+    let result: VapiBatchResult = parse_value(api, body)?;
+    if result.success {
+        return Ok(());
+    }
+    let messages: Vec<String> = result.error_messages.into_iter().map(|message| message.default_message).collect();
+    Err(ApiError::Partial(messages.join(" ")))
+}
+
+pub fn attach_tag(api : api_impl::VcenterApi, tag : String, vms : &[String]) -> Result<(), ApiError> {
+    for chunk in vms.chunks(LIST_CHUNK) {
+        let result = api_impl::VcenterApi::attach_tag(&api, tag.clone(), vm_object_ids(chunk))?;
+        check_batch(&api, &result)?;
+    }
+    Ok(())
+}
+
+pub fn detach_tag(api : api_impl::VcenterApi, tag : String, vms : &[String]) -> Result<(), ApiError> {
+    for chunk in vms.chunks(LIST_CHUNK) {
+        let result = api_impl::VcenterApi::detach_tag(&api, tag.clone(), vm_object_ids(chunk))?;
+        check_batch(&api, &result)?;
+    }
+    Ok(())
+}
+
+//every content library of vcenter, the list call only gives the ids
+pub fn get_libraries(api : api_impl::VcenterApi) -> Result<Vec<VapiLibrary>, ApiError> {
+    let ids: Vec<String> = parse_value(&api, &api_impl::VcenterApi::list_libraries(&api)?)?;
+    let mut libraries = Vec::new();
+    for id in ids {
+        libraries.push(parse_value(&api, &api_impl::VcenterApi::get_library(&api, id)?)?);
+    }
+    Ok(libraries)
+}
+
+//items of the library: ovf and vm templates, isos and other files
+pub fn get_library_items(api : api_impl::VcenterApi, library : String) -> Result<Vec<VapiLibraryItem>, ApiError> {
+    let ids: Vec<String> = parse_value(&api, &api_impl::VcenterApi::list_library_items(&api, library)?)?;
+    let mut items = Vec::new();
+    for id in ids {
+        items.push(parse_value(&api, &api_impl::VcenterApi::get_library_item(&api, id)?)?);
+    }
+    Ok(items)
+}
+
+//root resource pool of the cluster, or of the host when it is standalone, vcenter always names it Resources
+fn root_resource_pool(api : api_impl::VcenterApi, spec : &DeploySpec) -> Result<String, ApiError> {
+    let filter = match &spec.cluster {
+        Some(cluster) => ResourcePoolFilter { clusters: vec![cluster.clone()], ..ResourcePoolFilter::default() },
+        None => ResourcePoolFilter { hosts: spec.host.clone().into_iter().collect(), ..ResourcePoolFilter::default() },
+    };
+    let pools = list_resource_pools(api, &ResourcePoolFilter { names: vec!["Resources".to_string()], ..filter })?;
+    pools.into_iter().next().map(|pool| pool.resource_pool).ok_or_else(|| ApiError::NotFound("Root resource pool".to_string()))
+}
+
+//default_message of every message found in a vcenter error
+fn error_messages(value : &serde_json::Value, messages : &mut Vec<String>) {
+    match value {
+        serde_json::Value::Object(map) => map.iter().for_each(|(key, value)| match (key.as_str(), value) {
+            ("default_message", serde_json::Value::String(message)) => messages.push(message.clone()),
+            _ => error_messages(value, messages),
+        }),
+        serde_json::Value::Array(values) => values.iter().for_each(|value| error_messages(value, messages)),
+        _ => {}
+    }
+}
+
+//deploy an ovf or vm template item and return the id of the new vm, it answers once the vm is created
+pub fn deploy_library_item(api : api_impl::VcenterApi, item : &VapiLibraryItem, spec : &DeploySpec) -> Result<String, ApiError> {
+    match item.item_type.as_str() {
+        "ovf" => {
+            let mut target = serde_json::json!({
+                "resource_pool_id": root_resource_pool(api.clone(), spec)?,
+                "folder_id": spec.folder,
+            });
+            if let Some(host) = &spec.host {
+                target["host_id"] = serde_json::json!(host);
+            }
+            let body = serde_json::json!({
+                "target": target,
+                "deployment_spec": {
+                    "name": spec.name,
+                    "accept_all_EULA": true,
+                    "default_datastore_id": spec.datastore,
+                },
+            });
+            let result = api_impl::VcenterApi::deploy_ovf(&api, item.id.clone(), body)?;
+            let result: VapiDeploymentResult = parse_value(&api, &result)?;
+            match (result.succeeded, result.resource_id) {
+                (true, Some(vm)) => Ok(vm.id),
+                _ => {
+                    let mut messages = Vec::new();
+                    if let Some(error) = &result.error {
+                        error_messages(error, &mut messages);
+                    }
+                    Err(ApiError::Failed(messages.join(" ")))
+                }
+            }
+        }
+        "vm-template" => {
+            let mut placement = serde_json::json!({ "folder": spec.folder });
+            if let Some(cluster) = &spec.cluster {
+                placement["cluster"] = serde_json::json!(cluster);
+            }
+            if let Some(host) = &spec.host {
+                placement["host"] = serde_json::json!(host);
+            }
+            let body = serde_json::json!({
+                "name": spec.name,
+                "placement": placement,
+                "vm_home_storage": { "datastore": spec.datastore },
+                "disk_storage": { "datastore": spec.datastore },
+                "powered_on": false,
+            });
+            let vm = api_impl::VcenterApi::deploy_vm_template(&api, item.id.clone(), body)?;
+            parse_value(&api, &vm)
+        }
+        other => Err(ApiError::Failed(format!("{} items can not be deployed", other))),
+    }
+}
+
+//create the vm and return its id, the nic is a vmxnet3 connected at power on
+pub fn create_vm(api : api_impl::VcenterApi, spec : &CreateSpec) -> Result<String, ApiError> {
+    let mut body = serde_json::json!({
+        "name": spec.name,
+        "guest_OS": spec.guest_os,
+        "placement": {
+            "folder": spec.folder,
+            "host": spec.host,
+            "datastore": spec.datastore,
+        },
+        "cpu": { "count": spec.cpu_count },
+        "memory": { "size_MiB": spec.memory_mib },
+        "disks": [{ "new_vmdk": { "capacity": gib_bytes(spec.disk_gib)? } }],
+    });
+    if let Some(network) = &spec.network {
+        //the backing type is the type of the network: STANDARD_PORTGROUP, DISTRIBUTED_PORTGROUP or OPAQUE_NETWORK
+        body["nics"] = serde_json::json!([{
+            "type": "VMXNET3",
+            "start_connected": true,
+            "backing": { "type": network.network_type, "network": network.network },
+        }]);
+    }
+    let vm = api_impl::VcenterApi::create_vm(&api, body)?;
+    parse_value(&api, &vm)
+}
+
+//clone the vm and return the id of the copy, it answers once the disks are copied
+pub fn clone_vm(api : api_impl::VcenterApi, spec : &CloneSpec) -> Result<String, ApiError> {
+    let body = serde_json::json!({
+        "name": spec.name,
+        "source": spec.source,
+        "power_on": spec.power_on,
+    });
+    let vm = api_impl::VcenterApi::clone_vm(&api, body)?;
+    parse_value(&api, &vm)
+}
+
+//the null fields of an update are dropped so vcenter keeps their value
+fn without_nulls(mut body : serde_json::Value) -> serde_json::Value {
+    if let Some(fields) = body.as_object_mut() {
+        fields.retain(|_, value| !value.is_null());
+    }
+    body
+}
+
+pub fn update_cpu(api : api_impl::VcenterApi, vm : String, update : &CpuUpdate) -> Result<(), ApiError> {
+    let body = serde_json::json!({
+        "count": update.count,
+        "cores_per_socket": update.cores_per_socket,
+        "hot_add_enabled": update.hot_add_enabled,
+        "hot_remove_enabled": update.hot_remove_enabled,
+    });
+    api_impl::VcenterApi::update_cpu(&api, vm, without_nulls(body))
+}
+
+pub fn update_memory(api : api_impl::VcenterApi, vm : String, update : &MemoryUpdate) -> Result<(), ApiError> {
+    let body = serde_json::json!({
+        "size_MiB": update.size_mib,
+        "hot_add_enabled": update.hot_add_enabled,
+    });
+    api_impl::VcenterApi::update_memory(&api, vm, without_nulls(body))
+}
+
+pub fn delete_vm(api : api_impl::VcenterApi, vm : String) -> Result<(), ApiError> {
+    api_impl::VcenterApi::delete_vm(&api, vm)
+}
+
+pub fn shutdown_vm(api : api_impl::VcenterApi, vm : String) -> Result<(), ApiError> {
+    api_impl::VcenterApi::shutdown_vm(&api, vm)
+}
+
+pub fn poweron_vm(api : api_impl::VcenterApi, vm : String) -> Result<(), ApiError> {
+    api_impl::VcenterApi::start_vm(&api, vm)
+}
+
+pub fn reboot_vm(api : api_impl::VcenterApi, vm : String) -> Result<(), ApiError> {
+    api_impl::VcenterApi::reboot_vm(&api, vm)
+}
+
+pub fn suspend_vm(api : api_impl::VcenterApi, vm : String) -> Result<(), ApiError> {
+    api_impl::VcenterApi::suspend_vm(&api, vm)
+}
+
+pub fn guest_shutdown_vm(api : api_impl::VcenterApi, vm : String) -> Result<(), ApiError> {
+    api_impl::VcenterApi::guest_power(&api, vm, "shutdown")
+}
+
+pub fn guest_reboot_vm(api : api_impl::VcenterApi, vm : String) -> Result<(), ApiError> {
+    api_impl::VcenterApi::guest_power(&api, vm, "reboot")
+}
+
+pub fn get_power_state(api : api_impl::VcenterApi, vm : String) -> Result<String, ApiError> {
+    let power = api_impl::VcenterApi::get_power_state(&api, vm)?;
+    let power: VapiPowerInfo = parse_value(&api, &power)?;
+    Ok(power.state)
+}
+
+//poll the vm until it is POWERED_OFF, returns false if the timeout is reached first
+pub fn wait_for_power_off(api : api_impl::VcenterApi, vm : String, timeout : Duration) -> Result<bool, ApiError> {
+    let start = Instant::now();
+    loop {
+        if get_power_state(api.clone(), vm.clone())? == "POWERED_OFF" {
+            return Ok(true);
+        }
+        if start.elapsed() >= timeout {
+            return Ok(false);
+        }
+        thread::sleep(Duration::from_secs(2));
+    }
+}
+
+//login and make every following request of the api (and its clones) use the new session
+pub fn authenticate(api : api_impl::VcenterApi,username : String,password : String) -> Result<(), ApiError> {
+    api_impl::VcenterApi::login(&api
+                                ,username
+                              , password)
+}
+
+pub fn logout(api : api_impl::VcenterApi) -> Result<(), ApiError> {
+    api_impl::VcenterApi::logout(&api)
+}
+
+//snapshot tree of a vm, None when the vm has no snapshots
+pub fn get_snapshots(api : api_impl::VcenterApi, vm : String) -> Result<Option<VapiSnapshotInfo>, ApiError> {
+    let snapshots = api_impl::VcenterApi::get_snapshots(&api, vm)?;
+    if snapshots.trim().is_empty() {
+        return Ok(None);
+    }
+    //vi/json answers are never wrapped in {"value": ...}
+    let snapshots: Option<VapiSnapshotInfo> = serde_json::from_str(&snapshots)?;
+    Ok(snapshots)
+}
+
+//the snapshot calls wait for their task, so failures end up on the vm
+pub fn create_snapshot(api : api_impl::VcenterApi, vm : String, name : String, description : String, memory : bool, quiesce : bool) -> Result<(), ApiError> {
+    let task = api_impl::VcenterApi::create_snapshot(&api, vm, name, description, memory, quiesce)?;
+    let task: VapiMoRef = serde_json::from_str(&task)?;
+    wait_for_task(&api, &task.value, api.provision_timeout())
+}
+
+pub fn revert_snapshot(api : api_impl::VcenterApi, snapshot : String) -> Result<(), ApiError> {
+    let task = api_impl::VcenterApi::revert_snapshot(&api, snapshot)?;
+    let task: VapiMoRef = serde_json::from_str(&task)?;
+    wait_for_task(&api, &task.value, api.provision_timeout())
+}
+
+pub fn delete_snapshot(api : api_impl::VcenterApi, snapshot : String) -> Result<(), ApiError> {
+    let task = api_impl::VcenterApi::delete_snapshot(&api, snapshot)?;
+    let task: VapiMoRef = serde_json::from_str(&task)?;
+    wait_for_task(&api, &task.value, api.provision_timeout())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_api() -> api_impl::VcenterApi {
+        new_api("vcenter.test".to_string(), ConnectionConfig::default()).unwrap()
+    }
+
+    fn api_with(version: ApiVersion) -> api_impl::VcenterApi {
+        new_api("vcenter.test".to_string(), ConnectionConfig { api_version: Some(version), ..ConnectionConfig::default() }).unwrap()
+    }
+
+    #[test]
+    fn urls_follow_the_api_version() {
+        assert_eq!(api_with(ApiVersion::Rest).create_url_for_vcenter(), "https://vcenter.test/rest/");
+        assert_eq!(api_with(ApiVersion::Api).create_url_for_vcenter(), "https://vcenter.test/api/");
+        //the version is only known after the login, /rest is used until then
+        assert_eq!(test_api().create_url_for_vcenter(), "https://vcenter.test/rest/");
+    }
+
+    #[test]
+    fn parse_value_unwraps_only_rest_answers() {
+        let rest = api_with(ApiVersion::Rest);
+        let value: Vec<String> = parse_value(&rest, r#"{"value": ["vm-1", "vm-2"]}"#).unwrap();
+        assert_eq!(value, vec!["vm-1", "vm-2"]);
+        assert!(parse_value::<Vec<String>>(&rest, r#"["vm-1"]"#).is_err());
+
+        let api = api_with(ApiVersion::Api);
+        let value: Vec<String> = parse_value(&api, r#"["vm-1", "vm-2"]"#).unwrap();
+        assert_eq!(value, vec!["vm-1", "vm-2"]);
+        //a payload with a value field is kept whole
+        let value: serde_json::Value = parse_value(&api, r#"{"value": "vm-1"}"#).unwrap();
+        assert_eq!(value["value"], "vm-1");
+    }
+
+    #[test]
+    fn split_vm_filter_goes_host_by_host() {
+        let filter = VmFilter { hosts: vec!["host-1".to_string(), "host-2".to_string()], ..VmFilter::default() };
+        let parts = split_vm_filter(test_api(), &filter).unwrap().unwrap();
+        assert_eq!(parts.iter().map(|part| part.hosts.clone()).collect::<Vec<_>>(), vec![vec!["host-1".to_string()], vec!["host-2".to_string()]]);
+    }
+
+    #[test]
+    fn split_vm_filter_splits_a_host_by_power_state() {
+        let filter = VmFilter { hosts: vec!["host-1".to_string()], ..VmFilter::default() };
+        let parts = split_vm_filter(test_api(), &filter).unwrap().unwrap();
+        assert_eq!(parts.iter().map(|part| part.power_states.clone()).collect::<Vec<_>>(), POWER_STATES.iter().map(|state| vec![state.to_string()]).collect::<Vec<_>>());
+        assert!(parts.iter().all(|part| part.hosts == filter.hosts));
+
+        let filter = VmFilter { power_states: vec!["POWERED_ON".to_string()], ..filter };
+        assert!(split_vm_filter(test_api(), &filter).unwrap().is_none());
+    }
+}
@@ -1,279 +1,836 @@
 //create mapping between API and rust
 //use std::collections::HashMap;
 
-use base64;
-use reqwest::blocking::Client;
-use reqwest::header::HeaderMap;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use reqwest::blocking::{Client, RequestBuilder, Response};
+use reqwest::header::{HeaderMap, HeaderValue, CONTENT_TYPE};
 use reqwest::StatusCode;
+use reqwest::tls::{Certificate, TlsInfo};
+
+use super::config::{ApiVersion, ConnectionConfig};
+use super::error::ApiError;
+use super::filter::{ClusterFilter, DatastoreFilter, FolderFilter, HostFilter, ListFilter, NetworkFilter, ResourcePoolFilter, VmFilter};
+use super::tls::{self, TlsMode};
+
+//release used for the vi/json (/sdk/vim25) endpoints, newer vcenters keep accepting it
+const VIM_RELEASE: &str = "8.0.1.0";
 
 pub struct VcenterApi{
     pub hostname : String,
+    config : ConnectionConfig,
+    //one pooled client for every request, shared between clones
+    client : Arc<RwLock<Client>>,
+    //user and password of the last login, used to get a new session when it expires
+    login : Arc<RwLock<Option<(String, String)>>>,
+    //endpoint family in use, fixed by the config or detected at login
+    version : Arc<RwLock<ApiVersion>>,
 }
 
 impl VcenterApi {
-    pub(crate) fn new(hostname: String) -> VcenterApi {
-        VcenterApi {
+    pub(crate) fn new(hostname: String, config: ConnectionConfig) -> Result<VcenterApi, ApiError> {
+        let client = VcenterApi::build_client(&config, HeaderMap::new())?;
+        let version = config.api_version.unwrap_or(ApiVersion::Rest);
+        Ok(VcenterApi {
             hostname,
-        }
+            config,
+            client: Arc::new(RwLock::new(client)),
+            login: Arc::new(RwLock::new(None)),
+            version: Arc::new(RwLock::new(version)),
+        })
     }
 
     pub(crate) fn clone(&self) -> VcenterApi {
         VcenterApi {
             hostname: self.hostname.clone(),
+            config: self.config.clone(),
+            client: Arc::clone(&self.client),
+            login: Arc::clone(&self.login),
+            version: Arc::clone(&self.version),
         }
     }
 
+    fn build_client(config: &ConnectionConfig, headers: HeaderMap) -> Result<Client, ApiError> {
+        let builder = Client::builder()
+            .connect_timeout(config.connect_timeout)
+            .timeout(config.read_timeout)
+            .default_headers(headers);
+        let builder = match &config.tls {
+            TlsMode::System => builder,
+            TlsMode::CustomCa(path) => builder.add_root_certificate(Certificate::from_pem(&tls::load_ca(path)?)?),
+            //the fingerprint is checked in the handshake, send() checks it again on every answer
+            TlsMode::Pinned(fingerprint) => builder.use_preconfigured_tls(tls::pinned_config(fingerprint)).tls_info(true),
+            TlsMode::Insecure => builder.danger_accept_invalid_certs(true),
+        };
+        let client = builder.build()?;
+        Ok(client)
+    }
+
+    //the client is reference counted, cloning it keeps the same connection pool
+    fn client(&self) -> Client {
+        self.client.read().unwrap().clone()
+    }
+
+    //rebuild the shared client so every request carries the session header
+    fn set_session(&self, session: &str) -> Result<(), ApiError> {
+        let mut headers = HeaderMap::new();
+        let auth = HeaderValue::from_str(session).map_err(|_| ApiError::SessionExpired)?;
+        headers.insert("vmware-api-session-id", auth);
+        let client = VcenterApi::build_client(&self.config, headers)?;
+        *self.client.write().unwrap() = client;
+        Ok(())
+    }
+
+    //time allowed for vcenter tasks the api waits for
+    pub(crate) fn provision_timeout(&self) -> Duration {
+        self.config.provision_timeout
+    }
+
+    pub(crate) fn version(&self) -> ApiVersion {
+        *self.version.read().unwrap()
+    }
+
     pub(crate) fn create_url_for_vcenter(&self) -> String {
         let mut url = String::from("https://");
         url.push_str(self.hostname.as_str());
-        url.push_str("/rest/");
-        return url;
+        match self.version() {
+            ApiVersion::Rest => url.push_str("/rest/"),
+            ApiVersion::Api => url.push_str("/api/"),
+        }
+        url
     }
 
-    pub(crate) fn create_url(&self, host: String) -> String {
+    pub(crate) fn create_url(&self) -> String {
         let mut url = String::from("https://");
         url.push_str(self.hostname.as_str());
         url.push_str("/rest/com/vmware/");
-        return url;
+        url
+    }
+
+    fn session_url(&self, version: ApiVersion) -> String {
+        match version {
+            ApiVersion::Rest => {
+                let mut url = VcenterApi::create_url(self);
+                url.push_str("cis/session");
+                url
+            }
+            ApiVersion::Api => format!("https://{}/api/session", self.hostname),
+        }
+    }
+
+    //list filters are filter.names=... on /rest and names=... on /api
+    fn filter<F: ListFilter>(&self, filter: &F) -> Vec<(String, String)> {
+        self.filter_query(filter.query())
+    }
+
+    fn filter_query(&self, query: Vec<(&str, String)>) -> Vec<(String, String)> {
+        query
+            .into_iter()
+            .map(|(name, value)| match self.version() {
+                ApiVersion::Rest => (format!("filter.{}", name), value),
+                ApiVersion::Api => (name.to_string(), value),
+            })
+            .collect()
+    }
+
+    //power actions are .../power/start on /rest and .../power?action=start on /api
+    fn power_url(&self, vm: &str, action: &str) -> String {
+        let mut url = VcenterApi::create_url_for_vcenter(self);
+        url.push_str("vcenter/vm/");
+        url.push_str(vm);
+        match self.version() {
+            ApiVersion::Rest => url.push_str("/power/"),
+            ApiVersion::Api => url.push_str("/power?action="),
+        }
+        url.push_str(action);
+        url
+    }
+
+    //device is cdrom or ethernet, actions are /connect on /rest and ?action=connect on /api
+    fn device_url(&self, vm: &str, device: &str, id: &str, action: Option<&str>) -> String {
+        let mut url = VcenterApi::create_url_for_vcenter(self);
+        url.push_str("vcenter/vm/");
+        url.push_str(vm);
+        url.push_str("/hardware/");
+        url.push_str(device);
+        url.push('/');
+        url.push_str(id);
+        if let Some(action) = action {
+            match self.version() {
+                ApiVersion::Rest => url.push('/'),
+                ApiVersion::Api => url.push_str("?action="),
+            }
+            url.push_str(action);
+        }
+        url
+    }
+
+    //host actions are .../host/{host}/connect on /rest and .../host/{host}?action=connect on /api
+    fn host_action_url(&self, host: &str, action: &str) -> String {
+        let mut url = VcenterApi::create_url_for_vcenter(self);
+        url.push_str("vcenter/host/");
+        url.push_str(host);
+        match self.version() {
+            ApiVersion::Rest => url.push('/'),
+            ApiVersion::Api => url.push_str("?action="),
+        }
+        url.push_str(action);
+        url
+    }
+
+    //tagging, content library and ovf are under /rest/com/vmware with .../id:{id} and ?~action= on /rest,
+    //and under /api with .../{id} and ?action= on /api
+    fn com_url(&self, path: &str, id: Option<&str>, action: Option<&str>) -> String {
+        let mut url = match self.version() {
+            ApiVersion::Rest => VcenterApi::create_url(self),
+            ApiVersion::Api => VcenterApi::create_url_for_vcenter(self),
+        };
+        url.push_str(path);
+        if let Some(id) = id {
+            url.push('/');
+            if let ApiVersion::Rest = self.version() {
+                url.push_str("id:");
+            }
+            url.push_str(id);
+        }
+        if let Some(action) = action {
+            match self.version() {
+                ApiVersion::Rest => url.push_str("?~action="),
+                ApiVersion::Api => url.push_str("?action="),
+            }
+            url.push_str(action);
+        }
+        url
+    }
+
+    //send the request and turn every non 2xx answer into an error
+    fn send(&self, request: RequestBuilder) -> Result<Response, ApiError> {
+        let response = request.send()?;
+        //second check of the pin, the handshake already refused any other certificate
+        if let TlsMode::Pinned(expected) = &self.config.tls {
+            let found = response
+                .extensions()
+                .get::<TlsInfo>()
+                .and_then(|info| info.peer_certificate())
+                .map(tls::fingerprint);
+            match found {
+                Some(found) if tls::same_fingerprint(&found, expected) => {}
+                Some(found) => return Err(ApiError::Tls(format!("Certificate {} does not match the pinned one", found))),
+                None => return Err(ApiError::Tls("No certificate to check against the pinned one".to_string())),
+            }
+        }
+        let status = response.status();
+        if status.is_success() {
+            Ok(response)
+        } else {
+            let body = response.text().unwrap_or_default();
+            Err(ApiError::from_status(status, body))
+        }
+    }
+
+    //send a request and, if the session expired, login again and retry it once
+    fn execute<F: Fn(&Client) -> RequestBuilder>(&self, build: F) -> Result<Response, ApiError> {
+        match self.send(build(&self.client())) {
+            Err(ApiError::SessionExpired) if self.relogin()? => self.send(build(&self.client())),
+            result => result,
+        }
+    }
+
+    fn get_body(&self, url: &str) -> Result<String, ApiError> {
+        let body = self.execute(|client| client.get(url))?.text()?;
+        Ok(body)
+    }
+
+    //query values are url encoded by reqwest
+    fn get_query(&self, url: &str, query: &[(String, String)]) -> Result<String, ApiError> {
+        let body = self.execute(|client| client.get(url).query(query))?.text()?;
+        Ok(body)
+    }
+
+    fn post_action(&self, url: &str) -> Result<(), ApiError> {
+        self.execute(|client| client.post(url))?;
+        Ok(())
+    }
+
+    fn delete_action(&self, url: &str) -> Result<(), ApiError> {
+        self.execute(|client| client.delete(url))?;
+        Ok(())
+    }
+
+    //create and update calls of /rest want the spec wrapped in {"spec": ...}
+    fn spec_body(&self, spec: serde_json::Value) -> serde_json::Value {
+        match self.version() {
+            ApiVersion::Rest => serde_json::json!({ "spec": spec }),
+            ApiVersion::Api => spec,
+        }
+    }
+
+    fn patch_json(&self, url: &str, body: &serde_json::Value) -> Result<(), ApiError> {
+        let body = body.to_string();
+        self.execute(|client| {
+            client.patch(url)
+                .header(CONTENT_TYPE, "application/json")
+                .body(body.clone())
+        })?;
+        Ok(())
+    }
+
+    fn post_json(&self, url: &str, body: &serde_json::Value) -> Result<String, ApiError> {
+        self.post_json_timeout(url, body, self.config.read_timeout)
+    }
+
+    //post with its own timeout instead of read_timeout, for calls that wait for vcenter to finish
+    fn post_json_timeout(&self, url: &str, body: &serde_json::Value, timeout: Duration) -> Result<String, ApiError> {
+        let body = body.to_string();
+        let response = self.execute(|client| {
+            client.post(url)
+                .header(CONTENT_TYPE, "application/json")
+                .timeout(timeout)
+                .body(body.clone())
+        })?;
+        Ok(response.text()?)
+    }
+
+    //vi/json endpoints, they share the session of /api
+    pub(crate) fn create_url_for_vim(&self) -> String {
+        let mut url = String::from("https://");
+        url.push_str(self.hostname.as_str());
+        url.push_str("/sdk/vim25/");
+        url.push_str(VIM_RELEASE);
+        url.push('/');
+        url
+    }
+
+    //get a session and keep the user and password to renew it later
+    pub(crate) fn login(&self, username: String, password: String) -> Result<(), ApiError> {
+        //after the first login the detected version is reused
+        let known = self.config.api_version
+            .or_else(|| self.login.read().unwrap().as_ref().map(|_| self.version()));
+        let (version, body) = match known {
+            Some(version) => (version, self.get_session(version, username.clone(), password.clone())?),
+            //vcenter without /api answers 404 to its session endpoint
+            None => match self.get_session(ApiVersion::Api, username.clone(), password.clone()) {
+                Ok(body) => (ApiVersion::Api, body),
+                Err(ApiError::Status { status: StatusCode::NOT_FOUND, .. }) =>
+                    (ApiVersion::Rest, self.get_session(ApiVersion::Rest, username.clone(), password.clone())?),
+                Err(e) => return Err(e),
+            },
+        };
+        let json: serde_json::Value = serde_json::from_str(&body)?;
+        let session = match version {
+            ApiVersion::Rest => json["value"].as_str(),
+            ApiVersion::Api => json.as_str(),
+        }
+        .ok_or_else(|| ApiError::UnexpectedJson("missing session id".to_string()))?;
+        *self.version.write().unwrap() = version;
+        self.set_session(session)?;
+        *self.login.write().unwrap() = Some((username, password));
+        Ok(())
     }
 
-    pub(crate) fn get_session(&self, username: String, password: String, host: String) -> String {
-        let mut url = VcenterApi::create_url(self, host);
-        url.push_str("cis/session");
+    //returns false when there was no login to repeat
+    fn relogin(&self) -> Result<bool, ApiError> {
+        let login = self.login.read().unwrap().clone();
+        match login {
+            Some((username, password)) => {
+                self.login(username, password)?;
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    //close the session on vcenter, following requests will not login again
+    pub(crate) fn logout(&self) -> Result<(), ApiError> {
+        let url = self.session_url(self.version());
+
+        *self.login.write().unwrap() = None;
+        let request = self.client().delete(&url);
+        match self.send(request) {
+            //the session was already gone
+            Err(ApiError::SessionExpired) => Ok(()),
+            result => result.map(|_| ()),
+        }
+    }
+
+    fn get_session(&self, version: ApiVersion, username: String, password: String) -> Result<String, ApiError> {
+        let url = self.session_url(version);
 
         //create a header map with the authorization header
         let mut headers = HeaderMap::new();
         let mut auth = String::from("Basic ");
-        let mut auth_string = String::from(username);
-        auth_string.push_str(":");
+        let mut auth_string = username;
+        auth_string.push(':');
         auth_string.push_str(&password);
-        auth.push_str(&base64::encode(&auth_string));
-        headers.insert("Authorization", auth.parse().unwrap());
-
-        let client = Client::builder();
-        let response = client
-            .danger_accept_invalid_certs(true)
-            .build()
-            .unwrap()
+        auth.push_str(&STANDARD.encode(&auth_string));
+        headers.insert("Authorization", HeaderValue::from_str(&auth).map_err(|_| ApiError::SessionExpired)?);
+
+        //fresh client so an expired session header is not sent with the login
+        let request = VcenterApi::build_client(&self.config, HeaderMap::new())?
             .post(&url)
-            .headers(headers)
-            .send();
+            .headers(headers);
 
+        //get the response body, a 401 here means wrong user or password
+        let body = match self.send(request) {
+            Err(ApiError::SessionExpired) => Err(ApiError::Status { status: StatusCode::UNAUTHORIZED, body: String::new() }),
+            result => result,
+        }?.text()?;
 
-        //get the response
-        let response = response.unwrap();
+        Ok(body)
+    }
 
-        //get the response body
-        let body = response.text().unwrap();
+    pub(crate) fn list_vms(&self, filter: &VmFilter) -> Result<String, ApiError> {
+        let mut url = VcenterApi::create_url_for_vcenter(self);
+        url.push_str("vcenter/vm");
 
-        return body;
+        self.get_query(&url, &self.filter(filter))
     }
 
-    pub(crate) fn get_all_vms(&self, credentials: String) -> String {
+    pub(crate) fn list_datacenters(&self, names: &[String]) -> Result<String, ApiError> {
         let mut url = VcenterApi::create_url_for_vcenter(self);
-        url.push_str("vcenter/vm");
+        url.push_str("vcenter/datacenter");
 
-        //create a header map with the authorization header
-        let mut headers = HeaderMap::new();
-        let auth = String::from(&credentials);
-        headers.insert("vmware-api-session-id", auth.parse().unwrap());
+        let query = names.iter().map(|name| ("names", name.clone())).collect();
+        self.get_query(&url, &self.filter_query(query))
+    }
+
+    pub(crate) fn list_clusters(&self, filter: &ClusterFilter) -> Result<String, ApiError> {
+        let mut url = VcenterApi::create_url_for_vcenter(self);
+        url.push_str("vcenter/cluster");
+
+        self.get_query(&url, &self.filter(filter))
+    }
 
+    pub(crate) fn list_datastores(&self, filter: &DatastoreFilter) -> Result<String, ApiError> {
+        let mut url = VcenterApi::create_url_for_vcenter(self);
+        url.push_str("vcenter/datastore");
+
+        self.get_query(&url, &self.filter(filter))
+    }
+
+    //vms with files on the datastore, only the vi/json api has it
+    pub(crate) fn get_datastore_vms(&self, datastore : String) -> Result<String, ApiError> {
+        let mut url = VcenterApi::create_url_for_vim(self);
+        url.push_str("Datastore/");
+        url.push_str(&datastore);
+        url.push_str("/vm");
+
+        self.get_body(&url)
+    }
+
+    pub(crate) fn list_networks(&self, filter: &NetworkFilter) -> Result<String, ApiError> {
+        let mut url = VcenterApi::create_url_for_vcenter(self);
+        url.push_str("vcenter/network");
 
-        let client = Client::builder();
-        let response = client
-            .danger_accept_invalid_certs(true)
-            .build()
-            .unwrap()
-            .get(&url)
-            .headers(headers)
-            .send();
+        self.get_query(&url, &self.filter(filter))
+    }
+
+    //vms with a nic on the network, only the vi/json api has it
+    pub(crate) fn get_network_vms(&self, network : String) -> Result<String, ApiError> {
+        let mut url = VcenterApi::create_url_for_vim(self);
+        url.push_str("Network/");
+        url.push_str(&network);
+        url.push_str("/vm");
 
-        let response = response.unwrap();
+        self.get_body(&url)
+    }
 
-        //get the response body
-        let body = response.text().unwrap();
+    //fields missing from the spec are left unchanged
+    pub(crate) fn update_nic(&self, vm : String, nic : String, spec : serde_json::Value) -> Result<(), ApiError> {
+        let url = self.device_url(&vm, "ethernet", &nic, None);
 
-        //convert to json
-        //convert the json to object
+        self.patch_json(&url, &self.spec_body(spec))
+    }
 
+    //action is connect or disconnect, only on a powered on vm
+    pub(crate) fn nic_connection(&self, vm : String, nic : String, action : &str) -> Result<(), ApiError> {
+        let url = self.device_url(&vm, "ethernet", &nic, Some(action));
 
-        return body;
+        self.post_action(&url)
     }
 
-    pub(crate) fn get_vms_from_host(&self, credentials: String, host: String) -> String {
+    pub(crate) fn list_disks(&self, vm : String) -> Result<String, ApiError> {
         let mut url = VcenterApi::create_url_for_vcenter(self);
-        url.push_str("vcenter/vm");
-        url.push_str("?filter.hosts=");
-        url.push_str(&host);
+        url.push_str("vcenter/vm/");
+        url.push_str(&vm);
+        url.push_str("/hardware/disk");
 
-        //create a header map with the authorization header
-        let mut headers = HeaderMap::new();
-        let auth = String::from(&credentials);
-        headers.insert("vmware-api-session-id", auth.parse().unwrap());
+        self.get_body(&url)
+    }
+
+    pub(crate) fn get_disk(&self, vm : String, disk : String) -> Result<String, ApiError> {
+        let mut url = VcenterApi::create_url_for_vcenter(self);
+        url.push_str("vcenter/vm/");
+        url.push_str(&vm);
+        url.push_str("/hardware/disk/");
+        url.push_str(&disk);
+
+        self.get_body(&url)
+    }
+
+    //the disk leaves the vm but its vmdk file stays on the datastore
+    pub(crate) fn delete_disk(&self, vm : String, disk : String) -> Result<(), ApiError> {
+        let mut url = VcenterApi::create_url_for_vcenter(self);
+        url.push_str("vcenter/vm/");
+        url.push_str(&vm);
+        url.push_str("/hardware/disk/");
+        url.push_str(&disk);
+
+        self.delete_action(&url)
+    }
 
+    //fields missing from the spec are left unchanged
+    pub(crate) fn update_cdrom(&self, vm : String, cdrom : String, spec : serde_json::Value) -> Result<(), ApiError> {
+        let url = self.device_url(&vm, "cdrom", &cdrom, None);
 
-        let client = Client::builder();
-        let response = client
-            .danger_accept_invalid_certs(true)
-            .build()
-            .unwrap()
-            .get(&url)
-            .headers(headers)
-            .send();
+        self.patch_json(&url, &self.spec_body(spec))
+    }
+
+    //action is connect or disconnect, only on a powered on vm
+    pub(crate) fn cdrom_connection(&self, vm : String, cdrom : String, action : &str) -> Result<(), ApiError> {
+        let url = self.device_url(&vm, "cdrom", &cdrom, Some(action));
+
+        self.post_action(&url)
+    }
+
+    //the answer is the id of the new cd-rom, /api takes the bare vm id as body
+    pub(crate) fn mount_library_iso(&self, item : String, vm : String) -> Result<String, ApiError> {
+        let url = self.com_url("vcenter/iso/image", Some(&item), Some("mount"));
+
+        let body = match self.version() {
+            ApiVersion::Rest => serde_json::json!({ "vm": vm }),
+            ApiVersion::Api => serde_json::json!(vm),
+        };
+        self.post_json(&url, &body)
+    }
+
+    //removes the cd-rom made by mount_library_iso, /api takes the bare cd-rom id as body
+    pub(crate) fn unmount_library_iso(&self, vm : String, cdrom : String) -> Result<(), ApiError> {
+        let url = self.com_url("vcenter/iso/image", Some(&vm), Some("unmount"));
 
-        let response = response.unwrap();
+        let body = match self.version() {
+            ApiVersion::Rest => serde_json::json!({ "cdrom": cdrom }),
+            ApiVersion::Api => serde_json::json!(cdrom),
+        };
+        self.post_json(&url, &body)?;
+        Ok(())
+    }
+
+    pub(crate) fn list_folders(&self, filter: &FolderFilter) -> Result<String, ApiError> {
+        let mut url = VcenterApi::create_url_for_vcenter(self);
+        url.push_str("vcenter/folder");
 
-        //get the response body
-        let body = response.text().unwrap();
+        self.get_query(&url, &self.filter(filter))
+    }
 
-        //convert to json
+    pub(crate) fn list_resource_pools(&self, filter: &ResourcePoolFilter) -> Result<String, ApiError> {
+        let mut url = VcenterApi::create_url_for_vcenter(self);
+        url.push_str("vcenter/resource-pool");
 
-        //convert the json to object
-        return body;
+        self.get_query(&url, &self.filter(filter))
     }
 
-    pub(crate) fn get_all_hosts(&self, credentials: String) -> String {
+    pub(crate) fn list_hosts(&self, filter: &HostFilter) -> Result<String, ApiError> {
         let mut url = VcenterApi::create_url_for_vcenter(self);
         url.push_str("vcenter/host");
 
-        //create a header map with the authorization header
-        let mut headers = HeaderMap::new();
-        let auth = String::from(&credentials);
-        headers.insert("vmware-api-session-id", auth.parse().unwrap());
+        self.get_query(&url, &self.filter(filter))
+    }
 
-        let client = Client::builder();
-        let response = client
-            .danger_accept_invalid_certs(true)
-            .build()
-            .unwrap()
-            .get(&url)
-            .headers(headers)
-            .send();
+    pub(crate) fn shutdown_vm(&self, vm: String) -> Result<(), ApiError> {
+        let url = self.power_url(&vm, "stop");
 
-        let response = response.unwrap();
+        self.post_action(&url)
+    }
 
-        //get the response body
-        let body = response.text().unwrap();
+    pub(crate) fn start_vm(&self, vm: String) -> Result<(), ApiError> {
+        let url = self.power_url(&vm, "start");
 
-        //convert the json to object
-        return body;
+        self.post_action(&url)
     }
 
-    pub(crate) fn get_host(&self, credentials: String, host: String) -> String {
-        let mut url = VcenterApi::create_url_for_vcenter(self);
-        url.push_str("vcenter/host/");
-        url.push_str("?filter.names=");
+    pub(crate) fn reboot_vm(&self, vm : String) -> Result<(), ApiError> {
+        let url = self.power_url(&vm, "reset");
+
+        self.post_action(&url)
+    }
+
+    pub(crate) fn suspend_vm(&self, vm : String) -> Result<(), ApiError> {
+        let url = self.power_url(&vm, "suspend");
+
+        self.post_action(&url)
+    }
+
+    pub(crate) fn connect_host(&self, host : String) -> Result<(), ApiError> {
+        let url = self.host_action_url(&host, "connect");
+
+        self.post_action(&url)
+    }
+
+    pub(crate) fn disconnect_host(&self, host : String) -> Result<(), ApiError> {
+        let url = self.host_action_url(&host, "disconnect");
+
+        self.post_action(&url)
+    }
+
+    //maintenance mode and host power are only in the vi/json api
+    //properties of many objects in one call, the answer can be paged
+    pub(crate) fn retrieve_properties(&self, spec : serde_json::Value) -> Result<String, ApiError> {
+        let mut url = VcenterApi::create_url_for_vim(self);
+        url.push_str("PropertyCollector/propertyCollector/RetrievePropertiesEx");
+
+        self.post_json(&url, &serde_json::json!({ "specSet": [spec], "options": { "_typeName": "RetrieveOptions" } }))
+    }
+
+    pub(crate) fn continue_retrieve_properties(&self, token : String) -> Result<String, ApiError> {
+        let mut url = VcenterApi::create_url_for_vim(self);
+        url.push_str("PropertyCollector/propertyCollector/ContinueRetrievePropertiesEx");
+
+        self.post_json(&url, &serde_json::json!({ "token": token }))
+    }
+
+    //method is EnterMaintenanceMode_Task, ExitMaintenanceMode_Task, RebootHost_Task or ShutdownHost_Task
+    pub(crate) fn host_task(&self, host : String, method : &str, body : serde_json::Value) -> Result<String, ApiError> {
+        let mut url = VcenterApi::create_url_for_vim(self);
+        url.push_str("HostSystem/");
         url.push_str(&host);
+        url.push('/');
+        url.push_str(method);
 
-        println!("{}", url);
+        self.post_json(&url, &body)
+    }
 
-        //create a header map with the authorization header
-        let mut headers = HeaderMap::new();
-        let auth = String::from(&credentials);
-        headers.insert("vmware-api-session-id", auth.parse().unwrap());
+    pub(crate) fn get_vm(&self, vm : String) -> Result<String, ApiError> {
+        let mut url = VcenterApi::create_url_for_vcenter(self);
+        url.push_str("vcenter/vm/");
+        url.push_str(&vm);
+
+        self.get_body(&url)
+    }
+
+    pub(crate) fn list_categories(&self) -> Result<String, ApiError> {
+        let url = self.com_url("cis/tagging/category", None, None);
+
+        self.get_body(&url)
+    }
+
+    pub(crate) fn get_category(&self, category : String) -> Result<String, ApiError> {
+        let url = self.com_url("cis/tagging/category", Some(&category), None);
+
+        self.get_body(&url)
+    }
+
+    pub(crate) fn list_tags(&self) -> Result<String, ApiError> {
+        let url = self.com_url("cis/tagging/tag", None, None);
+
+        self.get_body(&url)
+    }
+
+    pub(crate) fn get_tag(&self, tag : String) -> Result<String, ApiError> {
+        let url = self.com_url("cis/tagging/tag", Some(&tag), None);
+
+        self.get_body(&url)
+    }
+
+    //object_ids is a list of {"type": ..., "id": ...}
+    pub(crate) fn list_attached_tags(&self, object_ids : serde_json::Value) -> Result<String, ApiError> {
+        let url = self.com_url("cis/tagging/tag-association", None, Some("list-attached-tags-on-objects"));
+
+        self.post_json(&url, &serde_json::json!({ "object_ids": object_ids }))
+    }
+
+    pub(crate) fn attach_tag(&self, tag : String, object_ids : serde_json::Value) -> Result<String, ApiError> {
+        let url = self.com_url("cis/tagging/tag-association", Some(&tag), Some("attach-tag-to-multiple-objects"));
+
+        self.post_json(&url, &serde_json::json!({ "object_ids": object_ids }))
+    }
+
+    pub(crate) fn detach_tag(&self, tag : String, object_ids : serde_json::Value) -> Result<String, ApiError> {
+        let url = self.com_url("cis/tagging/tag-association", Some(&tag), Some("detach-tag-from-multiple-objects"));
+
+        self.post_json(&url, &serde_json::json!({ "object_ids": object_ids }))
+    }
+
+    pub(crate) fn list_libraries(&self) -> Result<String, ApiError> {
+        let url = self.com_url("content/library", None, None);
+
+        self.get_body(&url)
+    }
+
+    pub(crate) fn get_library(&self, library : String) -> Result<String, ApiError> {
+        let url = self.com_url("content/library", Some(&library), None);
 
+        self.get_body(&url)
+    }
+
+    pub(crate) fn list_library_items(&self, library : String) -> Result<String, ApiError> {
+        let url = self.com_url("content/library/item", None, None);
+
+        self.get_query(&url, &[("library_id".to_string(), library)])
+    }
 
-        let client = Client::builder();
-        let response = client
-            .danger_accept_invalid_certs(true)
-            .build()
-            .unwrap()
-            .get(&url)
-            .headers(headers)
-            .send();
+    pub(crate) fn get_library_item(&self, item : String) -> Result<String, ApiError> {
+        let url = self.com_url("content/library/item", Some(&item), None);
 
-        let response = response.unwrap();
+        self.get_body(&url)
+    }
 
-        //get the response body
-        let body = response.text().unwrap();
+    //answers once the vm is created, body is {"target": ..., "deployment_spec": ...}
+    pub(crate) fn deploy_ovf(&self, item : String, body : serde_json::Value) -> Result<String, ApiError> {
+        let url = self.com_url("vcenter/ovf/library-item", Some(&item), Some("deploy"));
 
-        //convert the json to object
-        return body;
+        self.post_json_timeout(&url, &body, self.config.provision_timeout)
     }
 
-    pub(crate) fn shutdown_vm(&self, credentials: String, vm: String) -> StatusCode {
+    //same path on /rest and /api, /rest wants the spec wrapped in {"spec": ...}
+    pub(crate) fn deploy_vm_template(&self, item : String, spec : serde_json::Value) -> Result<String, ApiError> {
+        let mut url = VcenterApi::create_url_for_vcenter(self);
+        url.push_str("vcenter/vm-template/library-items/");
+        url.push_str(&item);
+        url.push_str("?action=deploy");
+
+        let body = self.spec_body(spec);
+        self.post_json_timeout(&url, &body, self.config.provision_timeout)
+    }
+
+    //same path on /rest and /api
+    pub(crate) fn create_vm(&self, spec : serde_json::Value) -> Result<String, ApiError> {
+        let mut url = VcenterApi::create_url_for_vcenter(self);
+        url.push_str("vcenter/vm");
+
+        let body = self.spec_body(spec);
+        self.post_json(&url, &body)
+    }
+
+    //same path on /rest and /api
+    pub(crate) fn clone_vm(&self, spec : serde_json::Value) -> Result<String, ApiError> {
+        let mut url = VcenterApi::create_url_for_vcenter(self);
+        url.push_str("vcenter/vm?action=clone");
+
+        let body = self.spec_body(spec);
+        self.post_json_timeout(&url, &body, self.config.provision_timeout)
+    }
+
+    //same path on /rest and /api, fields missing from the spec are left unchanged
+    pub(crate) fn update_cpu(&self, vm : String, spec : serde_json::Value) -> Result<(), ApiError> {
         let mut url = VcenterApi::create_url_for_vcenter(self);
         url.push_str("vcenter/vm/");
         url.push_str(&vm);
-        url.push_str("/power/stop");
+        url.push_str("/hardware/cpu");
 
-        //create a header map with the authorization header
-        let mut headers = HeaderMap::new();
-        let auth = String::from(&credentials);
-        headers.insert("vmware-api-session-id", auth.parse().unwrap());
-
-        let client = Client::builder();
-        let response = client
-            .danger_accept_invalid_certs(true)
-            .build()
-            .unwrap()
-            .post(&url)
-            .headers(headers)
-            .send();
+        self.patch_json(&url, &self.spec_body(spec))
+    }
 
-        let response = response.unwrap();
-        //get status code
-        let status_code = response.status();
+    //same path on /rest and /api, fields missing from the spec are left unchanged
+    pub(crate) fn update_memory(&self, vm : String, spec : serde_json::Value) -> Result<(), ApiError> {
+        let mut url = VcenterApi::create_url_for_vcenter(self);
+        url.push_str("vcenter/vm/");
+        url.push_str(&vm);
+        url.push_str("/hardware/memory");
 
-        return status_code;
+        self.patch_json(&url, &self.spec_body(spec))
     }
 
-    pub(crate) fn start_vm(&self, credentials: String, vm: String) -> StatusCode {
+    //same path on /rest and /api, vcenter refuses it while the vm is powered on
+    pub(crate) fn delete_vm(&self, vm : String) -> Result<(), ApiError> {
         let mut url = VcenterApi::create_url_for_vcenter(self);
         url.push_str("vcenter/vm/");
         url.push_str(&vm);
-        url.push_str("/power/start");
 
-        //create a header map with the authorization header
-        let mut headers = HeaderMap::new();
-        let auth = String::from(&credentials);
-        headers.insert("vmware-api-session-id", auth.parse().unwrap());
-
-        let client = Client::builder();
-        let response = client
-            .danger_accept_invalid_certs(true)
-            .build()
-            .unwrap()
-            .post(&url)
-            .headers(headers)
-            .send();
+        self.delete_action(&url)
+    }
 
-        let response = response.unwrap();
-        //get status code
-        let status_code = response.status();
+    pub(crate) fn get_power_state(&self, vm : String) -> Result<String, ApiError> {
+        let mut url = VcenterApi::create_url_for_vcenter(self);
+        url.push_str("vcenter/vm/");
+        url.push_str(&vm);
+        url.push_str("/power");
 
-        return status_code;
+        self.get_body(&url)
     }
 
-    pub(crate) fn reboot_vm(&self, credentials : String, vm : String) -> StatusCode {
+    //shutdown or reboot asked to the guest os through vmware tools, same path on /rest and /api
+    pub(crate) fn guest_power(&self, vm : String, action : &str) -> Result<(), ApiError> {
         let mut url = VcenterApi::create_url_for_vcenter(self);
         url.push_str("vcenter/vm/");
         url.push_str(&vm);
-        url.push_str("/power/reset");
+        url.push_str("/guest/power?action=");
+        url.push_str(action);
 
-        //create a header map with the authorization header
-        let mut headers = HeaderMap::new();
-        let auth = String::from(&credentials);
-        headers.insert("vmware-api-session-id", auth.parse().unwrap());
-
-        let client = Client::builder();
-        let response = client
-            .danger_accept_invalid_certs(true)
-            .build()
-            .unwrap()
-            .post(&url)
-            .headers(headers)
-            .send();
+        self.post_action(&url)
+    }
 
-        let response = response.unwrap();
-        //get status code
-        let status_code = response.status();
+    //config of the vm in the vi/json api, its devices keep the _typeName needed to send them back
+    pub(crate) fn get_vm_config(&self, vm : String) -> Result<String, ApiError> {
+        let mut url = VcenterApi::create_url_for_vim(self);
+        url.push_str("VirtualMachine/");
+        url.push_str(&vm);
+        url.push_str("/config");
 
-        return status_code;
+        self.get_body(&url)
     }
-}
 
+    //the answer is the task doing the change
+    pub(crate) fn reconfigure_vm(&self, vm : String, spec : serde_json::Value) -> Result<String, ApiError> {
+        let mut url = VcenterApi::create_url_for_vim(self);
+        url.push_str("VirtualMachine/");
+        url.push_str(&vm);
+        url.push_str("/ReconfigVM_Task");
 
+        self.post_json(&url, &serde_json::json!({ "spec": spec }))
+    }
+
+    pub(crate) fn get_task_info(&self, task : String) -> Result<String, ApiError> {
+        let mut url = VcenterApi::create_url_for_vim(self);
+        url.push_str("Task/");
+        url.push_str(&task);
+        url.push_str("/info");
+
+        self.get_body(&url)
+    }
 
+    pub(crate) fn get_snapshots(&self, vm : String) -> Result<String, ApiError> {
+        let mut url = VcenterApi::create_url_for_vim(self);
+        url.push_str("VirtualMachine/");
+        url.push_str(&vm);
+        url.push_str("/snapshot");
 
+        self.get_body(&url)
+    }
 
+    pub(crate) fn create_snapshot(&self, vm : String, name : String, description : String, memory : bool, quiesce : bool) -> Result<String, ApiError> {
+        let mut url = VcenterApi::create_url_for_vim(self);
+        url.push_str("VirtualMachine/");
+        url.push_str(&vm);
+        url.push_str("/CreateSnapshot_Task");
+
+        let body = serde_json::json!({
+            "name": name,
+            "description": description,
+            "memory": memory,
+            "quiesce": quiesce,
+        });
+        self.post_json(&url, &body)
+    }
+
+    pub(crate) fn revert_snapshot(&self, snapshot : String) -> Result<String, ApiError> {
+        let mut url = VcenterApi::create_url_for_vim(self);
+        url.push_str("VirtualMachineSnapshot/");
+        url.push_str(&snapshot);
+        url.push_str("/RevertToSnapshot_Task");
+
+        self.post_json(&url, &serde_json::json!({}))
+    }
+
+    pub(crate) fn delete_snapshot(&self, snapshot : String) -> Result<String, ApiError> {
+        let mut url = VcenterApi::create_url_for_vim(self);
+        url.push_str("VirtualMachineSnapshot/");
+        url.push_str(&snapshot);
+        url.push_str("/RemoveSnapshot_Task");
+
+        self.post_json(&url, &serde_json::json!({ "removeChildren": false }))
+    }
+}
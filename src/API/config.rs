@@ -0,0 +1,40 @@
+//settings used to build the http client of VcenterApi
+use std::time::Duration;
+
+use super::tls::TlsMode;
+
+//endpoint family used to talk with vcenter
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ApiVersion {
+    //deprecated /rest/... endpoints, payloads wrapped in {"value": ...}
+    Rest,
+    ///api/... endpoints of vcenter 7.0u2 and newer, bare payloads
+    Api,
+}
+
+#[derive(Debug, Clone)]
+pub struct ConnectionConfig {
+    //time allowed to open the tcp/tls connection
+    pub connect_timeout: Duration,
+    //time allowed for a whole request, from sending it to reading the body
+    pub read_timeout: Duration,
+    //time allowed for calls that only answer once a vm is created (deploy from a library, clone)
+    //and for the vcenter tasks the api waits for (disk changes)
+    pub provision_timeout: Duration,
+    //how the vcenter certificate is verified
+    pub tls: TlsMode,
+    //endpoint family to use, detected at login when None
+    pub api_version: Option<ApiVersion>,
+}
+
+impl Default for ConnectionConfig {
+    fn default() -> ConnectionConfig {
+        ConnectionConfig {
+            connect_timeout: Duration::from_secs(10),
+            read_timeout: Duration::from_secs(60),
+            provision_timeout: Duration::from_secs(1800),
+            tls: TlsMode::System,
+            api_version: None,
+        }
+    }
+}
@@ -0,0 +1,133 @@
+//error type shared by every call of the API module
+use std::fmt;
+
+use reqwest::StatusCode;
+
+#[derive(Debug)]
+pub enum ApiError {
+    //the request never got an answer (dns, tls, connection refused, timeout...)
+    Transport(reqwest::Error),
+    //vcenter answered with a non 2xx status, body keeps the error sent by the server
+    Status { status: StatusCode, body: String },
+    //the session id is no longer valid, a new login is needed
+    SessionExpired,
+    //the body is not the json we expected
+    UnexpectedJson(String),
+    //the object asked for does not exist in the inventory
+    NotFound(String),
+    //the server certificate could not be checked or is not the trusted one
+    Tls(String),
+    //a bulk call was accepted but failed on some of its objects
+    Partial(String),
+    //vcenter took the request but the operation did not complete
+    Failed(String),
+}
+
+impl ApiError {
+    //builds the error for a non 2xx response, 401 means the session is gone
+    pub(crate) fn from_status(status: StatusCode, body: String) -> ApiError {
+        if status == StatusCode::UNAUTHORIZED {
+            ApiError::SessionExpired
+        } else {
+            ApiError::Status { status, body }
+        }
+    }
+
+    //vcenter refuses list calls that would return more than its result cap
+    pub(crate) fn is_result_limit(&self) -> bool {
+        match self {
+            ApiError::Status { body, .. } => body.to_lowercase().contains("unable_to_allocate_resource"),
+            _ => false,
+        }
+    }
+}
+
+//vcenter errors look like {"type": ..., "value": {"messages": [{"default_message": ...}]}}
+//on /rest and {"error_type": ..., "messages": [...]} on /api
+fn server_message(body: &str) -> Option<String> {
+    let json: serde_json::Value = serde_json::from_str(body).ok()?;
+    let messages = json["value"]["messages"]
+        .as_array()
+        .or_else(|| json["messages"].as_array())?;
+    let text: Vec<&str> = messages
+        .iter()
+        .filter_map(|message| message["default_message"].as_str())
+        .collect();
+    if text.is_empty() {
+        None
+    } else {
+        Some(text.join(" "))
+    }
+}
+
+impl fmt::Display for ApiError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ApiError::Transport(e) => write!(f, "Connection error: {}", e),
+            ApiError::Status { status, body } => match server_message(body) {
+                Some(message) => write!(f, "vCenter returned {}: {}", status, message),
+                None if body.is_empty() => write!(f, "vCenter returned {}", status),
+                None => write!(f, "vCenter returned {}: {}", status, body),
+            },
+            ApiError::SessionExpired => write!(f, "Session expired, login again"),
+            ApiError::UnexpectedJson(e) => write!(f, "Unexpected response: {}", e),
+            ApiError::NotFound(what) => write!(f, "{} not found", what),
+            ApiError::Tls(e) => write!(f, "TLS error: {}", e),
+            ApiError::Partial(e) => write!(f, "Failed on some objects: {}", e),
+            ApiError::Failed(e) => write!(f, "Operation failed: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for ApiError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ApiError::Transport(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<reqwest::Error> for ApiError {
+    fn from(e: reqwest::Error) -> ApiError {
+        ApiError::Transport(e)
+    }
+}
+
+impl From<serde_json::Error> for ApiError {
+    fn from(e: serde_json::Error) -> ApiError {
+        ApiError::UnexpectedJson(e.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn server_message_reads_rest_bodies() {
+        let body = r#"{"type": "com.vmware.vapi.std.errors.not_found", "value": {"messages": [{"id": "a", "default_message": "VM not found."}]}}"#;
+        assert_eq!(server_message(body).as_deref(), Some("VM not found."));
+    }
+
+    #[test]
+    fn server_message_reads_api_bodies() {
+        let body = r#"{"error_type": "INVALID_ARGUMENT", "messages": [{"default_message": "Bad name."}, {"default_message": "Bad host."}]}"#;
+        assert_eq!(server_message(body).as_deref(), Some("Bad name. Bad host."));
+    }
+
+    #[test]
+    fn server_message_is_none_without_messages() {
+        assert_eq!(server_message("not json"), None);
+        assert_eq!(server_message(r#"{"messages": []}"#), None);
+        assert_eq!(server_message(r#"{"value": "vm-1"}"#), None);
+    }
+
+    #[test]
+    fn result_limit_is_found_in_the_body() {
+        let error = ApiError::Status { status: StatusCode::BAD_REQUEST, body: r#"{"error_type": "UNABLE_TO_ALLOCATE_RESOURCE"}"#.to_string() };
+        assert!(error.is_result_limit());
+        let error = ApiError::Status { status: StatusCode::BAD_REQUEST, body: r#"{"error_type": "INVALID_ARGUMENT"}"#.to_string() };
+        assert!(!error.is_result_limit());
+    }
+}
@@ -0,0 +1,306 @@
+//typed filters of the vcenter/vm and vcenter/host list calls
+
+//values sent in a single request, bigger filters are split in several requests
+pub const LIST_CHUNK: usize = 50;
+
+//power states a vm can be in, used to split a query that returns too many vms
+pub const POWER_STATES: [&str; 3] = ["POWERED_ON", "POWERED_OFF", "SUSPENDED"];
+
+//every field is a list of identifiers, an empty list does not filter
+#[derive(Debug, Clone, Default)]
+pub struct VmFilter {
+    pub vms: Vec<String>,
+    pub names: Vec<String>,
+    pub folders: Vec<String>,
+    pub datacenters: Vec<String>,
+    pub hosts: Vec<String>,
+    pub clusters: Vec<String>,
+    pub resource_pools: Vec<String>,
+    pub power_states: Vec<String>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct HostFilter {
+    pub hosts: Vec<String>,
+    pub names: Vec<String>,
+    pub folders: Vec<String>,
+    pub datacenters: Vec<String>,
+    pub clusters: Vec<String>,
+    pub connection_states: Vec<String>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ClusterFilter {
+    pub clusters: Vec<String>,
+    pub names: Vec<String>,
+    pub folders: Vec<String>,
+    pub datacenters: Vec<String>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct DatastoreFilter {
+    pub datastores: Vec<String>,
+    pub names: Vec<String>,
+    pub types: Vec<String>,
+    pub folders: Vec<String>,
+    pub datacenters: Vec<String>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct NetworkFilter {
+    pub networks: Vec<String>,
+    pub names: Vec<String>,
+    pub types: Vec<String>,
+    pub folders: Vec<String>,
+    pub datacenters: Vec<String>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct FolderFilter {
+    pub folders: Vec<String>,
+    pub names: Vec<String>,
+    pub types: Vec<String>,
+    pub parent_folders: Vec<String>,
+    pub datacenters: Vec<String>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ResourcePoolFilter {
+    pub resource_pools: Vec<String>,
+    pub names: Vec<String>,
+    pub parent_resource_pools: Vec<String>,
+    pub datacenters: Vec<String>,
+    pub hosts: Vec<String>,
+    pub clusters: Vec<String>,
+}
+
+//shared by every filter: the query parameters and the split in chunks
+pub(crate) trait ListFilter: Clone {
+    fn fields(&self) -> Vec<(&'static str, &Vec<String>)>;
+    fn fields_mut(&mut self) -> Vec<&mut Vec<String>>;
+
+    //(name, value) pairs, a list is sent as the same name repeated
+    fn query(&self) -> Vec<(&'static str, String)> {
+        self.fields()
+            .into_iter()
+            .flat_map(|(name, values)| values.iter().map(move |value| (name, value.clone())))
+            .collect()
+    }
+
+    //split the longest list so no request carries more than size values of it
+    fn chunks(&self, size: usize) -> Vec<Self> {
+        let mut filter = self.clone();
+        let (index, values) = match filter
+            .fields_mut()
+            .into_iter()
+            .enumerate()
+            .max_by_key(|(_, values)| values.len())
+        {
+            Some((index, values)) if values.len() > size => (index, std::mem::take(values)),
+            _ => return vec![self.clone()],
+        };
+        values
+            .chunks(size)
+            .map(|chunk| {
+                let mut part = filter.clone();
+                *part.fields_mut().remove(index) = chunk.to_vec();
+                part
+            })
+            .collect()
+    }
+}
+
+impl ListFilter for VmFilter {
+    fn fields(&self) -> Vec<(&'static str, &Vec<String>)> {
+        vec![
+            ("vms", &self.vms),
+            ("names", &self.names),
+            ("folders", &self.folders),
+            ("datacenters", &self.datacenters),
+            ("hosts", &self.hosts),
+            ("clusters", &self.clusters),
+            ("resource_pools", &self.resource_pools),
+            ("power_states", &self.power_states),
+        ]
+    }
+
+    fn fields_mut(&mut self) -> Vec<&mut Vec<String>> {
+        vec![
+            &mut self.vms,
+            &mut self.names,
+            &mut self.folders,
+            &mut self.datacenters,
+            &mut self.hosts,
+            &mut self.clusters,
+            &mut self.resource_pools,
+            &mut self.power_states,
+        ]
+    }
+}
+
+impl ListFilter for ClusterFilter {
+    fn fields(&self) -> Vec<(&'static str, &Vec<String>)> {
+        vec![
+            ("clusters", &self.clusters),
+            ("names", &self.names),
+            ("folders", &self.folders),
+            ("datacenters", &self.datacenters),
+        ]
+    }
+
+    fn fields_mut(&mut self) -> Vec<&mut Vec<String>> {
+        vec![
+            &mut self.clusters,
+            &mut self.names,
+            &mut self.folders,
+            &mut self.datacenters,
+        ]
+    }
+}
+
+impl ListFilter for DatastoreFilter {
+    fn fields(&self) -> Vec<(&'static str, &Vec<String>)> {
+        vec![
+            ("datastores", &self.datastores),
+            ("names", &self.names),
+            ("types", &self.types),
+            ("folders", &self.folders),
+            ("datacenters", &self.datacenters),
+        ]
+    }
+
+    fn fields_mut(&mut self) -> Vec<&mut Vec<String>> {
+        vec![
+            &mut self.datastores,
+            &mut self.names,
+            &mut self.types,
+            &mut self.folders,
+            &mut self.datacenters,
+        ]
+    }
+}
+
+impl ListFilter for NetworkFilter {
+    fn fields(&self) -> Vec<(&'static str, &Vec<String>)> {
+        vec![
+            ("networks", &self.networks),
+            ("names", &self.names),
+            ("types", &self.types),
+            ("folders", &self.folders),
+            ("datacenters", &self.datacenters),
+        ]
+    }
+
+    fn fields_mut(&mut self) -> Vec<&mut Vec<String>> {
+        vec![
+            &mut self.networks,
+            &mut self.names,
+            &mut self.types,
+            &mut self.folders,
+            &mut self.datacenters,
+        ]
+    }
+}
+
+impl ListFilter for FolderFilter {
+    fn fields(&self) -> Vec<(&'static str, &Vec<String>)> {
+        vec![
+            ("folders", &self.folders),
+            ("names", &self.names),
+            ("type", &self.types),
+            ("parent_folders", &self.parent_folders),
+            ("datacenters", &self.datacenters),
+        ]
+    }
+
+    fn fields_mut(&mut self) -> Vec<&mut Vec<String>> {
+        vec![
+            &mut self.folders,
+            &mut self.names,
+            &mut self.types,
+            &mut self.parent_folders,
+            &mut self.datacenters,
+        ]
+    }
+}
+
+impl ListFilter for ResourcePoolFilter {
+    fn fields(&self) -> Vec<(&'static str, &Vec<String>)> {
+        vec![
+            ("resource_pools", &self.resource_pools),
+            ("names", &self.names),
+            ("parent_resource_pools", &self.parent_resource_pools),
+            ("datacenters", &self.datacenters),
+            ("hosts", &self.hosts),
+            ("clusters", &self.clusters),
+        ]
+    }
+
+    fn fields_mut(&mut self) -> Vec<&mut Vec<String>> {
+        vec![
+            &mut self.resource_pools,
+            &mut self.names,
+            &mut self.parent_resource_pools,
+            &mut self.datacenters,
+            &mut self.hosts,
+            &mut self.clusters,
+        ]
+    }
+}
+
+impl ListFilter for HostFilter {
+    fn fields(&self) -> Vec<(&'static str, &Vec<String>)> {
+        vec![
+            ("hosts", &self.hosts),
+            ("names", &self.names),
+            ("folders", &self.folders),
+            ("datacenters", &self.datacenters),
+            ("clusters", &self.clusters),
+            ("connection_states", &self.connection_states),
+        ]
+    }
+
+    fn fields_mut(&mut self) -> Vec<&mut Vec<String>> {
+        vec![
+            &mut self.hosts,
+            &mut self.names,
+            &mut self.folders,
+            &mut self.datacenters,
+            &mut self.clusters,
+            &mut self.connection_states,
+        ]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ids(prefix: &str, count: usize) -> Vec<String> {
+        (0..count).map(|i| format!("{}-{}", prefix, i)).collect()
+    }
+
+    #[test]
+    fn chunks_keep_a_small_filter_whole() {
+        let filter = VmFilter { hosts: ids("host", LIST_CHUNK), ..VmFilter::default() };
+        let chunks = filter.chunks(LIST_CHUNK);
+        assert_eq!(chunks.len(), 1);
+        assert_eq!(chunks[0].hosts, filter.hosts);
+    }
+
+    #[test]
+    fn chunks_split_the_longest_list_at_list_chunk() {
+        let filter = VmFilter { hosts: ids("host", 120), vms: ids("vm", 3), ..VmFilter::default() };
+        let chunks = filter.chunks(LIST_CHUNK);
+        assert_eq!(chunks.iter().map(|chunk| chunk.hosts.len()).collect::<Vec<usize>>(), vec![50, 50, 20]);
+        assert_eq!(chunks.iter().flat_map(|chunk| chunk.hosts.clone()).collect::<Vec<String>>(), filter.hosts);
+        //the other lists go whole in every chunk
+        assert!(chunks.iter().all(|chunk| chunk.vms == filter.vms));
+    }
+
+    #[test]
+    fn query_repeats_the_name_of_a_list() {
+        let filter = HostFilter { clusters: ids("domain", 2), ..HostFilter::default() };
+        assert_eq!(filter.query(), vec![("clusters", "domain-0".to_string()), ("clusters", "domain-1".to_string())]);
+    }
+}
@@ -1,25 +1,311 @@
 //import the required libraries
-use serde::{Serialize, Deserialize};
+use std::collections::BTreeMap;
+use serde::{Serialize, Deserialize, Deserializer};
 
+//maps are {"key": value} on /api and [{"key": ..., "value": ...}] on /rest
+fn keyed<'de, D, T>(deserializer: D) -> Result<BTreeMap<String, T>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    #[derive(Deserialize)]
+    struct Pair<T> {
+        key : String,
+        value : T,
+    }
+
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Keyed<T> {
+        Map(BTreeMap<String, T>),
+        Pairs(Vec<Pair<T>>),
+    }
+
+    Ok(match Keyed::deserialize(deserializer)? {
+        Keyed::Map(map) => map,
+        Keyed::Pairs(pairs) => pairs.into_iter().map(|pair| (pair.key, pair.value)).collect(),
+    })
+}
+
+//same fields on /rest and /api, cpu and memory are missing for some vms
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct VapiVm {
-    pub memory_size_MiB : u32,
+    #[serde(rename = "memory_size_MiB", default)]
+    pub memory_size_mib : u32,
     pub vm : String,
     pub name : String,
     pub power_state : String,
+    #[serde(default)]
     pub cpu_count : u32,
 }
 
-impl VapiVm{
-    fn new(memory_size_MiB : u32, vm : String, name : String, power_state : String, cpu_count : u32) -> VapiVm {
-        VapiVm {
-            memory_size_MiB,
-            vm,
-            name,
-            power_state,
-            cpu_count,
-        }
-    }
+//full information of vcenter/vm/{vm}
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VapiVmInfo {
+    pub name : String,
+    pub power_state : String,
+    #[serde(rename = "guest_OS")]
+    pub guest_os : String,
+    pub hardware : VapiHardware,
+    pub cpu : VapiCpu,
+    pub memory : VapiMemory,
+    pub boot : VapiBoot,
+    #[serde(default)]
+    pub boot_devices : Vec<VapiBootDevice>,
+    #[serde(default, deserialize_with = "keyed")]
+    pub disks : BTreeMap<String, VapiDisk>,
+    #[serde(default, deserialize_with = "keyed")]
+    pub nics : BTreeMap<String, VapiNic>,
+    #[serde(default, deserialize_with = "keyed")]
+    pub cdroms : BTreeMap<String, VapiCdrom>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VapiHardware {
+    pub version : String,
+    #[serde(default)]
+    pub upgrade_policy : String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VapiCpu {
+    pub count : u32,
+    #[serde(default)]
+    pub cores_per_socket : u32,
+    #[serde(default)]
+    pub hot_add_enabled : bool,
+    #[serde(default)]
+    pub hot_remove_enabled : bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VapiMemory {
+    #[serde(rename = "size_MiB")]
+    pub size_mib : u64,
+    #[serde(default)]
+    pub hot_add_enabled : bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VapiBoot {
+    #[serde(rename = "type")]
+    pub boot_type : String,
+    #[serde(default)]
+    pub delay : u64,
+    #[serde(default)]
+    pub enter_setup_mode : bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VapiBootDevice {
+    #[serde(rename = "type")]
+    pub device_type : String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VapiDisk {
+    //id of the disk in the vm, filled from the key it was listed with
+    #[serde(default)]
+    pub disk : String,
+    pub label : String,
+    #[serde(rename = "type", default)]
+    pub disk_type : String,
+    #[serde(default)]
+    pub capacity : u64,
+    pub backing : VapiDiskBacking,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VapiDiskBacking {
+    #[serde(rename = "type")]
+    pub backing_type : String,
+    #[serde(default)]
+    pub vmdk_file : Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VapiNic {
+    //id of the nic in the vm, filled from the key it was listed with
+    #[serde(default)]
+    pub nic : String,
+    pub label : String,
+    #[serde(rename = "type", default)]
+    pub nic_type : String,
+    #[serde(default)]
+    pub mac_address : Option<String>,
+    pub state : String,
+    #[serde(default)]
+    pub start_connected : bool,
+    pub backing : VapiNicBacking,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VapiNicBacking {
+    #[serde(rename = "type")]
+    pub backing_type : String,
+    #[serde(default)]
+    pub network : Option<String>,
+    #[serde(default)]
+    pub network_name : Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VapiCdrom {
+    //id of the cd-rom in the vm, filled from the key it was listed with
+    #[serde(default)]
+    pub cdrom : String,
+    pub label : String,
+    #[serde(rename = "type", default)]
+    pub cdrom_type : String,
+    pub state : String,
+    #[serde(default)]
+    pub start_connected : bool,
+    pub backing : VapiCdromBacking,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VapiCdromBacking {
+    #[serde(rename = "type")]
+    pub backing_type : String,
+    #[serde(default)]
+    pub iso_file : Option<String>,
+    #[serde(default)]
+    pub host_device : Option<String>,
+}
+
+//info of a vi/json task, error is set when state is error
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VapiTaskInfo {
+    pub state : String,
+    #[serde(default)]
+    pub error : Option<VapiFault>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VapiFault {
+    #[serde(default)]
+    pub localized_message : String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VapiPowerInfo {
+    pub state : String,
+}
+
+//managed object reference of the vi/json api
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VapiMoRef {
+    #[serde(rename = "type")]
+    pub mo_type : String,
+    pub value : String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VapiSnapshotInfo {
+    pub current_snapshot : Option<VapiMoRef>,
+    #[serde(default)]
+    pub root_snapshot_list : Vec<VapiSnapshot>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VapiSnapshot {
+    pub snapshot : VapiMoRef,
+    pub name : String,
+    #[serde(default)]
+    pub description : String,
+    pub create_time : String,
+    pub state : String,
+    #[serde(default)]
+    pub quiesced : bool,
+    #[serde(default)]
+    pub child_snapshot_list : Vec<VapiSnapshot>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VapiDatacenter {
+    pub datacenter : String,
+    pub name : String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VapiCluster {
+    pub cluster : String,
+    pub name : String,
+    #[serde(default)]
+    pub ha_enabled : bool,
+    #[serde(default)]
+    pub drs_enabled : bool,
+}
+
+//sizes in bytes, missing when the datastore is not accessible
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VapiDatastore {
+    pub datastore : String,
+    pub name : String,
+    #[serde(rename = "type")]
+    pub datastore_type : String,
+    #[serde(default)]
+    pub free_space : u64,
+    #[serde(default)]
+    pub capacity : u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VapiNetwork {
+    pub network : String,
+    pub name : String,
+    #[serde(rename = "type")]
+    pub network_type : String,
+}
+
+//entry of vcenter/vm/{vm}/hardware/disk
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VapiDiskSummary {
+    pub disk : String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VapiFolder {
+    pub folder : String,
+    pub name : String,
+    #[serde(rename = "type")]
+    pub folder_type : String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VapiResourcePool {
+    pub resource_pool : String,
+    pub name : String,
+}
+
+//page of a vi/json RetrievePropertiesEx, token is set when more pages are left
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VapiRetrieveResult {
+    #[serde(default)]
+    pub objects : Vec<VapiObjectContent>,
+    #[serde(default)]
+    pub token : Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VapiObjectContent {
+    pub obj : VapiMoRef,
+    #[serde(default)]
+    pub prop_set : Vec<VapiDynamicProperty>,
+}
+
+//val is typed, a boolean is {"_typeName": "boolean", "_value": true}
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VapiDynamicProperty {
+    pub name : String,
+    #[serde(default)]
+    pub val : serde_json::Value,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -30,13 +316,87 @@ pub struct VapiHost {
     pub power_state : String,
 }
 
-impl VapiHost   {
-    fn new(host : String, name : String, connection_state : String, power_state : String) -> VapiHost {
-        VapiHost {
-            host,
-            name,
-            connection_state,
-            power_state,
-        }
-    }
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VapiCategory {
+    pub id : String,
+    pub name : String,
+    #[serde(default)]
+    pub description : String,
+    //SINGLE or MULTIPLE tags of the category on one object
+    #[serde(default)]
+    pub cardinality : String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VapiTag {
+    pub id : String,
+    pub category_id : String,
+    pub name : String,
+    #[serde(default)]
+    pub description : String,
+}
+
+//object reference of the tagging api
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VapiObjectId {
+    #[serde(rename = "type")]
+    pub object_type : String,
+    pub id : String,
+}
+
+//entry of tag-association list-attached-tags-on-objects
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VapiObjectTags {
+    pub object_id : VapiObjectId,
+    #[serde(default)]
+    pub tag_ids : Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VapiMessage {
+    #[serde(default)]
+    pub default_message : String,
+}
+
+//answer of the bulk attach and detach calls, success is false when some object failed
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VapiBatchResult {
+    pub success : bool,
+    #[serde(default)]
+    pub error_messages : Vec<VapiMessage>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VapiLibrary {
+    pub id : String,
+    pub name : String,
+    //LOCAL or SUBSCRIBED
+    #[serde(rename = "type", default)]
+    pub library_type : String,
+    #[serde(default)]
+    pub description : String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VapiLibraryItem {
+    pub id : String,
+    pub library_id : String,
+    pub name : String,
+    //ovf, vm-template, iso...
+    #[serde(rename = "type", default)]
+    pub item_type : String,
+    #[serde(default)]
+    pub description : String,
+    #[serde(default)]
+    pub size : u64,
+}
+
+//answer of the ovf deploy, resource_id is the new vm
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VapiDeploymentResult {
+    pub succeeded : bool,
+    #[serde(default)]
+    pub resource_id : Option<VapiObjectId>,
+    #[serde(default)]
+    pub error : Option<serde_json::Value>,
 }
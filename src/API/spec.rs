@@ -0,0 +1,78 @@
+//specs the tui fills in for the calls that create or change vms, turned into request bodies by the api module
+use super::objects::VapiNetwork;
+
+//where a content library item is deployed, host pins the vm to one host of the cluster
+#[derive(Debug, Clone, Default)]
+pub struct DeploySpec {
+    pub name: String,
+    pub folder: String,
+    pub datastore: String,
+    pub cluster: Option<String>,
+    pub host: Option<String>,
+}
+
+//clone of a vm, it keeps the folder, host and datastore of the source
+#[derive(Debug, Clone, Default)]
+pub struct CloneSpec {
+    pub name: String,
+    pub source: String,
+    pub power_on: bool,
+}
+
+//vm made from scratch with one disk and, when network is set, one nic on it
+#[derive(Debug, Clone, Default)]
+pub struct CreateSpec {
+    pub name: String,
+    pub guest_os: String,
+    pub cpu_count: u32,
+    pub memory_mib: u64,
+    pub disk_gib: u64,
+    pub network: Option<VapiNetwork>,
+    pub datastore: String,
+    pub folder: String,
+    pub host: String,
+}
+
+//how the blocks of a new disk are allocated on the datastore
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum DiskProvisioning {
+    #[default]
+    Thin,
+    //thick lazy zeroed
+    Thick,
+    EagerZeroedThick,
+}
+
+//new disk of a vm, its vmdk goes in the folder of the vm on the datastore
+#[derive(Debug, Clone, Default)]
+pub struct DiskSpec {
+    pub capacity_gib: u64,
+    //name of the datastore, not its id
+    pub datastore: String,
+    pub provisioning: DiskProvisioning,
+}
+
+//iso put in the cd-rom of a vm
+#[derive(Debug, Clone)]
+pub enum IsoImage {
+    //path of the file like "[datastore1] iso/ubuntu.iso"
+    Datastore(String),
+    //id of an iso item of a content library, it is mounted on a new cd-rom
+    Library(String),
+}
+
+//new cpu settings of a vm, the fields left as None keep their value
+#[derive(Debug, Clone, Default)]
+pub struct CpuUpdate {
+    pub count: Option<u32>,
+    pub cores_per_socket: Option<u32>,
+    pub hot_add_enabled: Option<bool>,
+    pub hot_remove_enabled: Option<bool>,
+}
+
+//new memory settings of a vm, the fields left as None keep their value
+#[derive(Debug, Clone, Default)]
+pub struct MemoryUpdate {
+    pub size_mib: Option<u64>,
+    pub hot_add_enabled: Option<bool>,
+}
@@ -0,0 +1,232 @@
+//certificate checks for the connection with vcenter
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufRead, BufReader, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+
+use native_tls::TlsConnector;
+use rustls::client::{ServerCertVerified, ServerCertVerifier};
+use sha2::{Digest, Sha256};
+
+use super::error::ApiError;
+
+#[derive(Debug, Clone)]
+pub enum TlsMode {
+    //verify against the roots of the operating system
+    System,
+    //verify against the system roots plus the CA in this PEM file
+    CustomCa(PathBuf),
+    //accept only the certificate with this SHA-256 fingerprint
+    Pinned(String),
+    //accept any certificate, only used when asked for in the config
+    Insecure,
+}
+
+//result of looking at the certificate before sending any credentials
+pub enum CertificateCheck {
+    Trusted,
+    //the certificate does not validate, fingerprint is shown to the user
+    Unknown { fingerprint: String },
+}
+
+//SHA-256 of a DER certificate as AB:CD:..., same format as openssl x509 -fingerprint
+pub fn fingerprint(der: &[u8]) -> String {
+    Sha256::digest(der)
+        .iter()
+        .map(|byte| format!("{:02X}", byte))
+        .collect::<Vec<String>>()
+        .join(":")
+}
+
+//compare two fingerprints ignoring case and separators
+pub fn same_fingerprint(a: &str, b: &str) -> bool {
+    let normalize = |f: &str| {
+        f.chars()
+            .filter(|c| c.is_ascii_hexdigit())
+            .map(|c| c.to_ascii_lowercase())
+            .collect::<String>()
+    };
+    normalize(a) == normalize(b)
+}
+
+//accepts only the certificate with the pinned fingerprint, checked during the handshake so
+//nothing is sent to another server, the handshake signature is still verified by rustls
+struct PinnedVerifier {
+    fingerprint: String,
+}
+
+impl ServerCertVerifier for PinnedVerifier {
+    fn verify_server_cert(
+        &self,
+        end_entity: &rustls::Certificate,
+        _intermediates: &[rustls::Certificate],
+        _server_name: &rustls::ServerName,
+        _scts: &mut dyn Iterator<Item = &[u8]>,
+        _ocsp_response: &[u8],
+        _now: SystemTime,
+    ) -> Result<ServerCertVerified, rustls::Error> {
+        let found = fingerprint(&end_entity.0);
+        if same_fingerprint(&found, &self.fingerprint) {
+            Ok(ServerCertVerified::assertion())
+        } else {
+            Err(rustls::Error::General(format!("certificate {} does not match the pinned one", found)))
+        }
+    }
+}
+
+//tls config of the pinned mode, the chain and hostname are not looked at, only the fingerprint
+pub fn pinned_config(fingerprint: &str) -> rustls::ClientConfig {
+    rustls::ClientConfig::builder()
+        .with_safe_defaults()
+        .with_custom_certificate_verifier(Arc::new(PinnedVerifier { fingerprint: fingerprint.to_string() }))
+        .with_no_client_auth()
+}
+
+pub fn load_ca(path: &Path) -> Result<Vec<u8>, ApiError> {
+    std::fs::read(path).map_err(|e| ApiError::Tls(format!("Cannot read CA file {}: {}", path.display(), e)))
+}
+
+fn split_host(hostname: &str) -> (&str, u16) {
+    match hostname.rsplit_once(':') {
+        Some((host, port)) => match port.parse() {
+            Ok(port) => (host, port),
+            Err(_) => (hostname, 443),
+        },
+        None => (hostname, 443),
+    }
+}
+
+//tls handshake with vcenter, returns the fingerprint of the leaf certificate
+fn handshake(hostname: &str, connector: &TlsConnector, timeout: Duration) -> Result<String, ApiError> {
+    let (host, port) = split_host(hostname);
+    let address = (host, port)
+        .to_socket_addrs()
+        .map_err(|e| ApiError::Tls(format!("Cannot resolve {}: {}", host, e)))?
+        .next()
+        .ok_or_else(|| ApiError::NotFound(format!("Address of {}", host)))?;
+    let stream = TcpStream::connect_timeout(&address, timeout)
+        .map_err(|e| ApiError::Tls(format!("Cannot connect to {}: {}", hostname, e)))?;
+    let stream = connector
+        .connect(host, stream)
+        .map_err(|e| ApiError::Tls(format!("TLS handshake with {} failed: {}", hostname, e)))?;
+    let certificate = stream
+        .peer_certificate()
+        .map_err(|e| ApiError::Tls(e.to_string()))?
+        .ok_or_else(|| ApiError::Tls(format!("{} did not send a certificate", hostname)))?;
+    let der = certificate.to_der().map_err(|e| ApiError::Tls(e.to_string()))?;
+    Ok(fingerprint(&der))
+}
+
+fn insecure_connector() -> Result<TlsConnector, ApiError> {
+    TlsConnector::builder()
+        .danger_accept_invalid_certs(true)
+        .danger_accept_invalid_hostnames(true)
+        .build()
+        .map_err(|e| ApiError::Tls(e.to_string()))
+}
+
+//look at the vcenter certificate before the login so credentials never reach an untrusted server
+pub fn check_certificate(hostname: &str, mode: &TlsMode, timeout: Duration) -> Result<CertificateCheck, ApiError> {
+    let mut builder = TlsConnector::builder();
+    match mode {
+        TlsMode::Insecure => return Ok(CertificateCheck::Trusted),
+        TlsMode::Pinned(expected) => {
+            let found = handshake(hostname, &insecure_connector()?, timeout)?;
+            return if same_fingerprint(&found, expected) {
+                Ok(CertificateCheck::Trusted)
+            } else {
+                Err(ApiError::Tls(format!("Certificate of {} is {} but {} is pinned", hostname, found, expected)))
+            };
+        }
+        TlsMode::CustomCa(path) => {
+            let pem = load_ca(path)?;
+            let ca = native_tls::Certificate::from_pem(&pem).map_err(|e| ApiError::Tls(e.to_string()))?;
+            builder.add_root_certificate(ca);
+        }
+        TlsMode::System => {}
+    }
+    let connector = builder.build().map_err(|e| ApiError::Tls(e.to_string()))?;
+
+    match handshake(hostname, &connector, timeout) {
+        Ok(_) => Ok(CertificateCheck::Trusted),
+        //if the handshake works without verification the certificate is the problem
+        Err(verified_error) => match handshake(hostname, &insecure_connector()?, timeout) {
+            Ok(fingerprint) => Ok(CertificateCheck::Unknown { fingerprint }),
+            Err(_) => Err(verified_error),
+        },
+    }
+}
+
+//known hosts file: one "hostname fingerprint" pair per line
+pub fn load_known_hosts(path: &Path) -> HashMap<String, String> {
+    let mut hosts = HashMap::new();
+    if let Ok(file) = File::open(path) {
+        BufReader::new(file).lines().map_while(Result::ok).for_each(|line| {
+            let mut fields = line.split_whitespace();
+            if let (Some(host), Some(fingerprint)) = (fields.next(), fields.next()) {
+                if !host.starts_with('#') {
+                    hosts.insert(host.to_string(), fingerprint.to_string());
+                }
+            }
+        });
+    }
+    hosts
+}
+
+//the line of the host is replaced, the other lines and comments are kept
+pub fn save_known_host(path: &Path, hostname: &str, fingerprint: &str) -> Result<(), ApiError> {
+    let lines: Vec<String> = match File::open(path) {
+        Ok(file) => BufReader::new(file)
+            .lines()
+            .map_while(Result::ok)
+            .filter(|line| line.split_whitespace().next() != Some(hostname))
+            .collect(),
+        Err(_) => Vec::new(),
+    };
+    let mut file = File::create(path).map_err(|e| ApiError::Tls(format!("Cannot write {}: {}", path.display(), e)))?;
+    lines
+        .iter()
+        .try_for_each(|line| writeln!(file, "{}", line))
+        .and_then(|()| writeln!(file, "{} {}", hostname, fingerprint))
+        .map_err(|e| ApiError::Tls(format!("Cannot write {}: {}", path.display(), e)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn same_fingerprint_ignores_case_and_colons() {
+        assert!(same_fingerprint("AB:CD:EF:01", "abcdef01"));
+        assert!(same_fingerprint("ab:cd:ef:01", "AB CD EF 01"));
+        assert!(!same_fingerprint("AB:CD:EF:01", "AB:CD:EF:02"));
+        assert!(!same_fingerprint("AB:CD:EF", "AB:CD:EF:01"));
+    }
+
+    #[test]
+    fn save_known_host_replaces_the_line_of_the_host() {
+        let path = std::env::temp_dir().join(format!("known_hosts_test_{}", std::process::id()));
+        std::fs::write(&path, "# trusted hosts\nvcenter.test AA:BB\nother.test CC:DD\n").unwrap();
+        save_known_host(&path, "vcenter.test", "EE:FF").unwrap();
+        let hosts = load_known_hosts(&path);
+        let text = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(hosts.get("vcenter.test").map(String::as_str), Some("EE:FF"));
+        assert_eq!(hosts.get("other.test").map(String::as_str), Some("CC:DD"));
+        assert_eq!(text.matches("vcenter.test").count(), 1);
+        assert!(text.starts_with("# trusted hosts"));
+    }
+
+    #[test]
+    fn pinned_verifier_accepts_only_the_pinned_certificate() {
+        let der = b"certificate".to_vec();
+        let verifier = PinnedVerifier { fingerprint: fingerprint(&der).to_lowercase() };
+        let name = rustls::ServerName::try_from("vcenter.test").unwrap();
+        let check = |der: &[u8]| verifier.verify_server_cert(&rustls::Certificate(der.to_vec()), &[], &name, &mut std::iter::empty(), &[], SystemTime::now());
+        assert!(check(&der).is_ok());
+        assert!(check(b"another certificate").is_err());
+    }
+}
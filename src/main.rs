@@ -1,45 +1,119 @@
+#[allow(non_snake_case)]
 mod API;
 
-use std::env;
-use dotenv::dotenv;
+use std::collections::{HashMap, HashSet};
 use std::error::Error;
 use std::fs::File;
-use std::io::{BufRead, BufReader};
+use std::io::BufReader;
+use std::path::{Path, PathBuf};
 use tui::text::{Span, Spans};
-use tui::widgets::{Block, Borders, BorderType, Clear, List, ListItem, Paragraph, Wrap};
+use tui::widgets::{Block, Borders, BorderType, Clear, List, ListItem, ListState, Paragraph, Wrap};
 use crossterm::event::{DisableMouseCapture, EnableMouseCapture, Event, KeyCode, KeyEvent};
 use crossterm::{event, execute};
 
-use crossterm::terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen, size};
+use crossterm::terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen};
 use tui::backend::Backend;
 use tui::{Frame, Terminal};
 use tui::layout::{Alignment, Constraint, Corner, Direction, Layout, Rect};
 use tui::style::{Color, Modifier, Style};
 
 //import thread and duration
+use std::sync::mpsc::{self, Sender};
+use std::thread;
 use std::time::Duration;
 use std::time::Instant;
-use crossterm::event::{poll};
+use crossterm::event::poll;
 use serde::{Serialize, Deserialize};
 use xml::reader::{EventReader, XmlEvent};
 
 
-use crate::API::{poweron_vm, reboot_vm, shutdown_vm};
+use crate::API::{create_snapshot, delete_snapshot, get_snapshots, guest_reboot_vm, guest_shutdown_vm, poweron_vm, reboot_vm, revert_snapshot, shutdown_vm, suspend_vm, wait_for_power_off, ApiError, MAX_CPU_COUNT, MAX_DISK_GIB, MAX_MEMORY_MIB, CloneSpec, CpuUpdate, CreateSpec, DeploySpec, DiskProvisioning, DiskSpec, IsoImage, MemoryUpdate, VapiCategory, VapiCdrom, VapiDatacenter, VapiDatastore, VapiDisk, VapiFolder, VapiLibraryItem, VapiNetwork, VapiNic, VapiSnapshot, VapiTag, VmFilter, VapiVmInfo, ApiVersion, CertificateCheck, ConnectionConfig, TlsMode, VcenterApi, same_fingerprint};
+
+//certificates accepted from the trust popup, one "host fingerprint" per line
+const KNOWN_HOSTS_FILE: &str = "src/known_hosts";
+
+//nics of the isolated vms of each vcenter before they were isolated, the undo still works after a restart
+const ISOLATED_FILE: &str = "src/isolated.json";
+
+//(nic, connected, start connected) of each isolated vm, by vm id
+type IsolatedNics = HashMap<String, Vec<(String, bool, bool)>>;
+
+//most clones a single name pattern can create
+const CLONE_LIMIT: usize = 100;
+
+//guest os offered by the new vm wizard, a yaml file can use any other one vcenter knows
+const GUEST_OS: [&str; 10] = [
+    "OTHER_LINUX_64",
+    "UBUNTU_64",
+    "DEBIAN_11_64",
+    "RHEL_8_64",
+    "RHEL_9_64",
+    "CENTOS_8_64",
+    "SLES_15_64",
+    "WINDOWS_9_64",
+    "WINDOWS_9_SERVER_64",
+    "OTHER_64",
+];
+
+//provisioning choices of a new disk
+const DISK_PROVISIONING: [(DiskProvisioning, &str); 3] = [
+    (DiskProvisioning::Thin, "Thin"),
+    (DiskProvisioning::Thick, "Thick Lazy Zeroed"),
+    (DiskProvisioning::EagerZeroedThick, "Thick Eager Zeroed"),
+];
+
+//steps of the new vm wizard, the last one is the review
+const CREATE_STEPS: [&str; 4] = ["Name and Guest OS", "CPU, Memory and Disk", "Placement", "Review"];
 
 
 const APP_KEYS_DESC: &str = r#"
 S:           Search Mode
-H:           Host Mode
-F:           Select File
-Up:          Select Previous Vm
-Down:        Select Next Vm
-Intro:       Select Host/VM
-O:           PowerOff VMs
-P:           PowerOn Vms
-R:           Reboot Vms
+F:           Select File / Create from YAML
+N:           New VM
+D:           Select Datacenter
+G / W:       Datastores / Networks
+V:           Group by Host/Folder/Pool
+A:           Host Actions
+L:           Tags / Filter by Tag
+Up/Down:     Previous / Next Item
+Right/Left:  Expand / Collapse
+C:           Deploy from Library
+Intro:       Open Host / Toggle
+F5:          Reload Inventory
 Esc:         Exit
 "#;
 
+const VM_KEYS_DESC: &str = r#"
+H:           Host Mode
+Up/Down:     Previous / Next Vm
+Space:       Select VM
+O / R:       Shutdown / Reboot Guest (Shift: Hard)
+P / U:       PowerOn / Suspend Vms
+T / N:       Snapshot Tree / New Snapshot
+K:           Disks of VM
+M:           Mount / Eject ISO
+I:           VM Details
+L:           Tag Selected Vms
+E:           Resize Selected Vms
+Z:           Isolate Vms (Shift: Undo)
+W:           Move NIC to Network
+C / X:       Clone VM / Delete Vms
+"#;
+
+const NETWORK_KEYS_DESC: &str = r#"
+Up:          Select Previous Network
+Down:        Select Next Network
+Intro:       VMs on Network
+Esc:         Back
+"#;
+
+const DATASTORE_KEYS_DESC: &str = r#"
+Up:          Select Previous Datastore
+Down:        Select Next Datastore
+Intro:       VMs on Datastore
+Esc:         Back
+"#;
+
 
 //Enum for the different objects
 
@@ -48,6 +122,24 @@ enum InputMode {
     Search,
     ListVM,
     File,
+    Snapshots,
+    NewSnapshot,
+    Datacenters,
+    Datastores,
+    Networks,
+    HostActions,
+    Tags,
+    Library,
+    Deploy,
+    Clone,
+    CloneResults,
+    SnapshotResults,
+    CreateVm,
+    DeleteVm,
+    Resize,
+    Disks,
+    Iso,
+    MoveNic,
 }
 
 impl Clone for InputMode {
@@ -57,19 +149,34 @@ impl Clone for InputMode {
             InputMode::Search => InputMode::Search,
             InputMode::ListVM => InputMode::ListVM,
             InputMode::File => InputMode::File,
+            InputMode::Snapshots => InputMode::Snapshots,
+            InputMode::NewSnapshot => InputMode::NewSnapshot,
+            InputMode::Datacenters => InputMode::Datacenters,
+            InputMode::Datastores => InputMode::Datastores,
+            InputMode::Networks => InputMode::Networks,
+            InputMode::HostActions => InputMode::HostActions,
+            InputMode::Tags => InputMode::Tags,
+            InputMode::Library => InputMode::Library,
+            InputMode::Deploy => InputMode::Deploy,
+            InputMode::Clone => InputMode::Clone,
+            InputMode::CloneResults => InputMode::CloneResults,
+            InputMode::SnapshotResults => InputMode::SnapshotResults,
+            InputMode::CreateVm => InputMode::CreateVm,
+            InputMode::DeleteVm => InputMode::DeleteVm,
+            InputMode::Resize => InputMode::Resize,
+            InputMode::Disks => InputMode::Disks,
+            InputMode::Iso => InputMode::Iso,
+            InputMode::MoveNic => InputMode::MoveNic,
         }
     }
 }
 
-#[derive(Debug, Deserialize, Serialize)]
-struct PowerTUI {
-    vms: Vec<String>,
-}
-
 struct Vm {
     name: String,
     state: String,
     vm_id: String,
+    cpu_count: u32,
+    memory_mib: u32,
     selected: bool,
     error: bool,
     error_msg: String,
@@ -82,10 +189,12 @@ impl Clone for Vm {
             name: self.name.clone(),
             state: self.state.clone(),
             vm_id: self.vm_id.clone(),
-            selected: self.selected.clone(),
-            error: self.error.clone(),
+            cpu_count: self.cpu_count,
+            memory_mib: self.memory_mib,
+            selected: self.selected,
+            error: self.error,
             error_msg: self.error_msg.clone(),
-            delete: self.delete.clone(),
+            delete: self.delete,
         }
     }
 }
@@ -94,6 +203,11 @@ struct Host {
     name: String,
     state: String,
     ip: String,
+    //id of the cluster the host belongs to, None for a standalone host
+    cluster: Option<String>,
+    connection_state: String,
+    //None when the vcenter does not tell it (no vi/json api)
+    maintenance: Option<bool>,
     vms: Vec<Vm>,
     cpy_vms: Vec<Vm>,
 }
@@ -104,43 +218,418 @@ impl Clone for Host {
             name: self.name.clone(),
             state: self.state.clone(),
             ip: self.ip.clone(),
+            cluster: self.cluster.clone(),
+            connection_state: self.connection_state.clone(),
+            maintenance: self.maintenance,
             vms: self.vms.clone(),
             cpy_vms: self.cpy_vms.clone(),
         }
     }
 }
 
+#[derive(Clone)]
+struct Cluster {
+    id: String,
+    name: String,
+}
+
+//how the vms are grouped in the tree
+#[derive(Clone, Copy, PartialEq)]
+enum Grouping {
+    Host,
+    Folder,
+    ResourcePool,
+}
+
+//folder or resource pool with the ids of the vms it holds
+#[derive(Clone)]
+struct VmGroup {
+    id: String,
+    name: String,
+    vms: Vec<String>,
+}
+
+//row of the inventory tree, the indexes point into State.clusters, State.groups and State.hosts
+#[derive(Clone, Copy, PartialEq)]
+enum TreeNode {
+    Datacenter,
+    Cluster(usize),
+    Group(usize),
+    Host(usize),
+    Vm(usize, usize),
+}
+
 struct State {
     mode: InputMode,
+    clusters: Vec<Cluster>,
     hosts: Vec<Host>,
-    host_cursor: Option<usize>,
-    vm_cursor: Option<usize>,
+    //row of the inventory tree under the cursor
+    cursor: usize,
+    //ids of the tree nodes whose children are hidden
+    collapsed: HashSet<String>,
+    grouping: Grouping,
+    //folders or resource pools shown when the vms are not grouped by host
+    groups: Vec<VmGroup>,
     search_string : String,
     file_path: String,
+    status_msg: String,
+    //time given to the guest os to power off before a hard stop
+    shutdown_timeout: Duration,
+    //snapshot tree of the vm under the cursor
+    snapshots: Vec<SnapshotRow>,
+    snapshot_cursor: usize,
+    //'v' (revert) or 'd' (delete) waiting for the user to confirm it
+    snapshot_confirm: Option<char>,
+    //revert ('v') or delete ('d') running in the background
+    snapshot_task: Option<char>,
+    snapshot_form: SnapshotForm,
+    //one row per vm of the last snapshot created on the selected vms
+    snapshot_results: Vec<SnapshotResult>,
+    //disks of the vm under the cursor
+    disks: Vec<VapiDisk>,
+    disk_cursor: usize,
+    //add or grow form shown under the disks
+    disk_form: Option<DiskForm>,
+    //label typed to confirm the removal of the disk under the cursor
+    disk_remove: Option<String>,
+    //"Adding", "Growing" or "Removing" while a disk task runs in the background
+    disk_task: Option<&'static str>,
+    //full info of the vms already looked at, cleared when the inventory is reloaded
+    vm_details: HashMap<String, VapiVmInfo>,
+    show_details: bool,
+    //datacenter the hosts belong to, every host of vcenter when None
+    datacenter: Option<VapiDatacenter>,
+    datacenters: Vec<VapiDatacenter>,
+    datacenter_cursor: usize,
+    datastores: Vec<VapiDatastore>,
+    datastore_cursor: usize,
+    //names of the vms on the datastore under the cursor
+    datastore_vms: Vec<String>,
+    //percent used above which a datastore is shown in red
+    datastore_threshold: u64,
+    networks: Vec<VapiNetwork>,
+    network_cursor: usize,
+    //(vm name, nic) attached to the network under the cursor, the nic is None until the info of the vm is loaded
+    network_nics: Vec<(String, Option<VapiNic>)>,
+    iso_form: IsoForm,
+    //(nic, connected, start connected) of the isolated vms before they were isolated, used by the undo
+    isolated: IsolatedNics,
+    //label of the action running on each host, by host id, a host runs one action at a time
+    host_tasks: HashMap<String, &'static str>,
+    nic_form: NicForm,
+    //host action ('m', 'b', 'x' or 'c') waiting for the user to confirm it
+    host_action: Option<char>,
+    tags: Vec<VapiTag>,
+    categories: Vec<VapiCategory>,
+    tag_cursor: usize,
+    //ids of the tags attached to each vm, reloaded with the inventory
+    vm_tags: HashMap<String, Vec<String>>,
+    //only the vms with this tag are listed when set
+    tag_filter: Option<String>,
+    //'a' (attach) or 'd' (detach) waiting for the user to confirm it
+    tag_confirm: Option<char>,
+    //mode the tags popup goes back to
+    tag_return: InputMode,
+    //ovf and vm templates of every content library with the name of their library
+    library_items: Vec<(String, VapiLibraryItem)>,
+    library_cursor: usize,
+    deploy_form: DeployForm,
+    clone_form: CloneForm,
+    //vm cloned by the last clone action and one row per copy
+    clone_source: String,
+    clone_results: Vec<CloneResult>,
+    create_form: CreateForm,
+    delete_form: DeleteForm,
+    resize_form: ResizeForm,
     input: KeyEvent,
 }
 
+//one snapshot of the tree, depth is used to indent it under its parent
+#[derive(Clone)]
+struct SnapshotRow {
+    id: String,
+    name: String,
+    description: String,
+    created: String,
+    depth: usize,
+    current: bool,
+}
+
+//fields of the new snapshot popup, field is the index of the one being edited
+#[derive(Clone, Default)]
+struct SnapshotForm {
+    name: String,
+    description: String,
+    memory: bool,
+    quiesce: bool,
+    field: usize,
+}
+
+//fields of the deploy popup, Left and Right cycle through the lists of choices
+#[derive(Clone, Default)]
+struct DeployForm {
+    name: String,
+    //(cluster, host, label) of every cluster and host of the datacenter
+    targets: Vec<(Option<String>, Option<String>, String)>,
+    target: usize,
+    folders: Vec<VapiFolder>,
+    folder: usize,
+    datastores: Vec<VapiDatastore>,
+    datastore: usize,
+    field: usize,
+}
+
+//fields of the clone popup, field is the index of the one being edited
+#[derive(Clone, Default)]
+struct CloneForm {
+    pattern: String,
+    power_on: bool,
+    field: usize,
+}
+
+//copy made by a clone action, result is None until its clone finished
+#[derive(Clone)]
+struct CloneResult {
+    name: String,
+    result: Option<Result<String, String>>,
+}
+
+//vm of a snapshot created in the background, result is None until its task ended
+#[derive(Clone)]
+struct SnapshotResult {
+    vm_id: String,
+    name: String,
+    result: Option<Result<(), String>>,
+}
+
+//fields of the new vm wizard, sizes are kept as typed and checked when the step is left
+#[derive(Clone, Default)]
+struct CreateForm {
+    step: usize,
+    field: usize,
+    name: String,
+    guest_os: usize,
+    cpu: String,
+    memory_mib: String,
+    disk_gib: String,
+    //the choice after the last network is no nic
+    networks: Vec<VapiNetwork>,
+    network: usize,
+    datastores: Vec<VapiDatastore>,
+    datastore: usize,
+    folders: Vec<VapiFolder>,
+    folder: usize,
+    //(id, name) of the hosts of the datacenter
+    hosts: Vec<(String, String)>,
+    host: usize,
+    //problem found in the form or error text of vcenter
+    error: String,
+}
+
+//vms to delete, taken from the selection when the popup was opened
+#[derive(Clone, Default)]
+struct DeleteForm {
+    //(id, name, power state)
+    vms: Vec<(String, String, String)>,
+    typed: String,
+    //power off the powered on vms before deleting them
+    power_off: bool,
+}
+
+impl DeleteForm {
+    //what has to be typed: the vm name for one vm, "delete N vms" for more
+    fn phrase(&self) -> String {
+        match self.vms.as_slice() {
+            [(_, name, _)] => name.clone(),
+            vms => format!("delete {} vms", vms.len()),
+        }
+    }
+
+    fn powered_on(&self) -> usize {
+        self.vms.iter().filter(|vm| vm.2 == "POWERED_ON").count()
+    }
+}
+
+//new size of the selected vms, an empty field or Keep leaves the setting of each vm as it is
+#[derive(Clone, Default)]
+struct ResizeForm {
+    cpu: String,
+    cores_per_socket: String,
+    memory_mib: String,
+    cpu_hot_add: Option<bool>,
+    cpu_hot_remove: Option<bool>,
+    memory_hot_add: Option<bool>,
+    field: usize,
+    error: String,
+}
+
+//new size of the disk under the cursor (grow), or size and place of a new disk
+#[derive(Clone, Default)]
+struct DiskForm {
+    grow: bool,
+    size_gib: String,
+    datastores: Vec<VapiDatastore>,
+    datastore: usize,
+    provisioning: usize,
+    field: usize,
+    error: String,
+}
+
+//iso to mount on the selected vms, or the eject of their isos
+#[derive(Clone, Default)]
+struct IsoForm {
+    //0: datastore iso, 1: content library iso, 2: eject
+    action: usize,
+    datastores: Vec<VapiDatastore>,
+    datastore: usize,
+    //path of the iso in the datastore
+    path: String,
+    //iso items of every content library with the name of their library
+    items: Vec<(String, VapiLibraryItem)>,
+    item: usize,
+    field: usize,
+    error: String,
+}
+
+//nic of the vm under the cursor and the network it moves to
+#[derive(Clone, Default)]
+struct NicForm {
+    nics: Vec<VapiNic>,
+    nic: usize,
+    networks: Vec<VapiNetwork>,
+    network: usize,
+    field: usize,
+    error: String,
+}
+
+//vms of a yaml file given in File mode, the objects are named as in vcenter
+#[derive(Deserialize)]
+struct CreateFile {
+    create: Vec<CreateEntry>,
+}
+
+#[derive(Deserialize)]
+struct CreateEntry {
+    name: String,
+    guest_os: String,
+    cpu: u32,
+    memory_mib: u64,
+    disk_gib: u64,
+    #[serde(default)]
+    network: Option<String>,
+    datastore: String,
+    folder: String,
+    host: String,
+}
+
+//result of a deploy or clone finished in the background, the id of the new vm or the error
+struct ProvisionUpdate {
+    name: String,
+    result: Result<String, String>,
+}
+
+//result of a host action finished in the background, with the host state it started from
+struct HostUpdate {
+    host: String,
+    action: char,
+    in_maintenance: bool,
+    disconnected: bool,
+    result: Result<(), String>,
+}
+
+//result of a snapshot task finished in the background, action is 'c' (create), 'v' (revert) or 'd' (delete)
+struct SnapshotUpdate {
+    vm_id: String,
+    action: char,
+    result: Result<(), String>,
+}
+
+//result of a disk task finished in the background, action is the verb shown while it runs
+struct DiskUpdate {
+    vm_id: String,
+    action: &'static str,
+    result: Result<(), String>,
+}
+
+//result of a power action finished in the background, applied to the vm with vm_id
+struct PowerUpdate {
+    vm_id: String,
+    state: String,
+    error_msg: Option<String>,
+}
+
 impl Clone for State {
     fn clone(&self) -> State {
         State {
             mode: self.mode.clone(),
+            clusters: self.clusters.clone(),
             hosts: self.hosts.clone(),
-            host_cursor: self.host_cursor.clone(),
-            vm_cursor: self.vm_cursor.clone(),
+            cursor: self.cursor,
+            collapsed: self.collapsed.clone(),
+            grouping: self.grouping,
+            groups: self.groups.clone(),
             search_string: self.search_string.clone(),
-            input: self.input.clone(),
+            input: self.input,
             file_path: self.file_path.clone(),
+            status_msg: self.status_msg.clone(),
+            shutdown_timeout: self.shutdown_timeout,
+            snapshots: self.snapshots.clone(),
+            snapshot_cursor: self.snapshot_cursor,
+            snapshot_confirm: self.snapshot_confirm,
+            snapshot_task: self.snapshot_task,
+            snapshot_form: self.snapshot_form.clone(),
+            snapshot_results: self.snapshot_results.clone(),
+            disks: self.disks.clone(),
+            disk_cursor: self.disk_cursor,
+            disk_form: self.disk_form.clone(),
+            disk_remove: self.disk_remove.clone(),
+            disk_task: self.disk_task,
+            vm_details: self.vm_details.clone(),
+            show_details: self.show_details,
+            datacenter: self.datacenter.clone(),
+            datacenters: self.datacenters.clone(),
+            datacenter_cursor: self.datacenter_cursor,
+            datastores: self.datastores.clone(),
+            datastore_cursor: self.datastore_cursor,
+            datastore_vms: self.datastore_vms.clone(),
+            datastore_threshold: self.datastore_threshold,
+            networks: self.networks.clone(),
+            network_cursor: self.network_cursor,
+            network_nics: self.network_nics.clone(),
+            iso_form: self.iso_form.clone(),
+            isolated: self.isolated.clone(),
+            host_tasks: self.host_tasks.clone(),
+            nic_form: self.nic_form.clone(),
+            host_action: self.host_action,
+            tags: self.tags.clone(),
+            categories: self.categories.clone(),
+            tag_cursor: self.tag_cursor,
+            vm_tags: self.vm_tags.clone(),
+            tag_filter: self.tag_filter.clone(),
+            tag_confirm: self.tag_confirm,
+            tag_return: self.tag_return.clone(),
+            library_items: self.library_items.clone(),
+            library_cursor: self.library_cursor,
+            deploy_form: self.deploy_form.clone(),
+            clone_form: self.clone_form.clone(),
+            clone_source: self.clone_source.clone(),
+            clone_results: self.clone_results.clone(),
+            create_form: self.create_form.clone(),
+            delete_form: self.delete_form.clone(),
+            resize_form: self.resize_form.clone(),
         }
     }
 }
 
 impl Vm {
-    fn new(name: &str, state: &str,vm : &str) -> Vm {
+    fn new(name: &str, state: &str,vm : &str, cpu_count: u32, memory_mib: u32) -> Vm {
         Vm {
             name: name.to_string(),
             state: state.to_string(),
             selected: false,
             vm_id: vm.to_string(),
+            cpu_count,
+            memory_mib,
             error: false,
             error_msg: "".to_string(),
             delete: false,
@@ -154,6 +643,9 @@ impl Host {
             name: name.to_string(),
             state: state.to_string(),
             ip: ip.to_string(),
+            cluster: None,
+            connection_state: "".to_string(),
+            maintenance: None,
             vms: Vec::new(),
             cpy_vms: Vec::new(),
         }
@@ -171,615 +663,4477 @@ impl State {
     fn new() -> State {
         State{
             mode: InputMode::Normal,
+            clusters: Vec::new(),
             hosts: Vec::new(),
-            host_cursor: None,
-            vm_cursor: None,
+            cursor: 0,
+            collapsed: HashSet::new(),
+            grouping: Grouping::Host,
+            groups: Vec::new(),
             search_string: "".to_string(),
             file_path: "C:\\Users\\a2780\\Desktop\\vms.yaml".to_string(),
+            status_msg: "".to_string(),
+            shutdown_timeout: Duration::from_secs(120),
+            snapshots: Vec::new(),
+            snapshot_cursor: 0,
+            snapshot_confirm: None,
+            snapshot_task: None,
+            disks: Vec::new(),
+            disk_cursor: 0,
+            disk_form: None,
+            disk_remove: None,
+            disk_task: None,
+            snapshot_form: SnapshotForm::default(),
+            snapshot_results: Vec::new(),
+            vm_details: HashMap::new(),
+            show_details: false,
+            datacenter: None,
+            datacenters: Vec::new(),
+            datacenter_cursor: 0,
+            datastores: Vec::new(),
+            datastore_cursor: 0,
+            datastore_vms: Vec::new(),
+            datastore_threshold: 85,
+            networks: Vec::new(),
+            network_cursor: 0,
+            network_nics: Vec::new(),
+            iso_form: IsoForm::default(),
+            isolated: HashMap::new(),
+            host_tasks: HashMap::new(),
+            nic_form: NicForm::default(),
+            host_action: None,
+            tags: Vec::new(),
+            categories: Vec::new(),
+            tag_cursor: 0,
+            vm_tags: HashMap::new(),
+            tag_filter: None,
+            tag_confirm: None,
+            tag_return: InputMode::Normal,
+            library_items: Vec::new(),
+            library_cursor: 0,
+            deploy_form: DeployForm::default(),
+            clone_form: CloneForm::default(),
+            clone_source: "".to_string(),
+            clone_results: Vec::new(),
+            create_form: CreateForm::default(),
+            delete_form: DeleteForm::default(),
+            resize_form: ResizeForm::default(),
             //create input
             input: KeyEvent::from(KeyCode::Null),
         }
     }
 
-}
-
-#[derive(Serialize, Deserialize)]
-struct Credentials {
-    ip: String,
-    user: String,
-    password: String,
-    datacenter: String,
-}
+    //id used to remember if a node is collapsed
+    fn node_id(&self, node: TreeNode) -> String {
+        match node {
+            TreeNode::Datacenter => self.datacenter.as_ref().map(|datacenter| datacenter.datacenter.clone()).unwrap_or_default(),
+            TreeNode::Cluster(cluster) => self.clusters[cluster].id.clone(),
+            TreeNode::Group(group) => self.groups[group].id.clone(),
+            TreeNode::Host(host) => self.hosts[host].ip.clone(),
+            TreeNode::Vm(host, vm) => self.hosts[host].vms[vm].vm_id.clone(),
+        }
+    }
 
-fn search(state : &mut State) {
+    fn expanded(&self, node: TreeNode) -> bool {
+        !self.collapsed.contains(&self.node_id(node))
+    }
 
-    state.hosts.iter_mut().for_each(|host| {
-        host.cpy_vms.iter_mut().for_each(|vm| {
-            if !(vm.name.to_lowercase().contains(&state.search_string.to_lowercase())) {
-                vm.delete = true;
-            }else{
-                vm.delete = false;
+    //visible rows of the tree with their depth: datacenter, clusters with their hosts, standalone hosts
+    //or datacenter, folders (or resource pools) with their vms
+    fn tree(&self) -> Vec<(TreeNode, usize)> {
+        let mut rows = vec![(TreeNode::Datacenter, 0)];
+        if !self.expanded(TreeNode::Datacenter) {
+            return rows;
+        }
+        if self.grouping != Grouping::Host {
+            //the vms stay in their host so selection and actions work the same in every grouping
+            let positions: HashMap<&str, (usize, usize)> = self.hosts
+                .iter()
+                .enumerate()
+                .flat_map(|(host, h)| h.vms.iter().enumerate().map(move |(vm, v)| (v.vm_id.as_str(), (host, vm))))
+                .collect();
+            self.groups.iter().enumerate().for_each(|(index, group)| {
+                rows.push((TreeNode::Group(index), 1));
+                if self.expanded(TreeNode::Group(index)) {
+                    group.vms
+                        .iter()
+                        .filter_map(|vm_id| positions.get(vm_id.as_str()))
+                        .for_each(|&(host, vm)| rows.push((TreeNode::Vm(host, vm), 2)));
+                }
+            });
+            return rows;
+        }
+        let push_host = |rows: &mut Vec<(TreeNode, usize)>, host: usize, depth: usize| {
+            rows.push((TreeNode::Host(host), depth));
+            if self.expanded(TreeNode::Host(host)) {
+                (0..self.hosts[host].vms.len()).for_each(|vm| rows.push((TreeNode::Vm(host, vm), depth + 1)));
+            }
+        };
+        self.clusters.iter().enumerate().for_each(|(index, cluster)| {
+            rows.push((TreeNode::Cluster(index), 1));
+            if self.expanded(TreeNode::Cluster(index)) {
+                self.hosts
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, host)| host.cluster.as_ref() == Some(&cluster.id))
+                    .for_each(|(host, _)| push_host(&mut rows, host, 2));
             }
         });
-    });
-
+        self.hosts
+            .iter()
+            .enumerate()
+            .filter(|(_, host)| host.cluster.is_none())
+            .for_each(|(host, _)| push_host(&mut rows, host, 1));
+        rows
+    }
 
+    fn node(&self) -> TreeNode {
+        self.tree().get(self.cursor).map(|row| row.0).unwrap_or(TreeNode::Datacenter)
+    }
 
-}
+    //host under the cursor, or host of the vm under the cursor
+    fn host_index(&self) -> Option<usize> {
+        match self.node() {
+            TreeNode::Host(host) | TreeNode::Vm(host, _) => Some(host),
+            _ => None,
+        }
+    }
 
-fn delete(state : &mut State) {
-    state.hosts.iter_mut().for_each(|host| {
-        //create a temporal copy of the vms
-        let mut vms = host.cpy_vms.clone();
-        //delete the vms that are marked as delete
-        vms.retain(|vm| !vm.delete);
-        //replace the vms with the new vms
-        if vms.len() > 0 {
-            host.vms = vms;
+    fn current_vm(&self) -> Option<&Vm> {
+        match self.node() {
+            TreeNode::Vm(host, vm) => self.hosts[host].vms.get(vm),
+            _ => None,
         }
-    });
-}
+    }
 
+    fn current_vm_mut(&mut self) -> Option<&mut Vm> {
+        match self.node() {
+            TreeNode::Vm(host, vm) => self.hosts[host].vms.get_mut(vm),
+            _ => None,
+        }
+    }
 
-fn load_crendetials() -> (String, String, String, String) {
-    //open the file credentials.xml who is in the src folder
-    let file = File::open("src/credentials.json").unwrap();
-    let reader = BufReader::new(file);
-    let credentials: Credentials = serde_json::from_reader(reader).unwrap();
+    //move the cursor to the next (or previous) row, only stopping on vms when vms_only is set
+    fn move_cursor(&mut self, down: bool, vms_only: bool) {
+        let rows = self.tree();
+        let next = if down {
+            rows.iter().enumerate().skip(self.cursor + 1)
+                .find(|(_, row)| !vms_only || matches!(row.0, TreeNode::Vm(..)))
+                .map(|(index, _)| index)
+        } else {
+            rows.iter().enumerate().take(self.cursor).rev()
+                .find(|(_, row)| !vms_only || matches!(row.0, TreeNode::Vm(..)))
+                .map(|(index, _)| index)
+        };
+        if let Some(next) = next {
+            self.cursor = next;
+        }
+    }
 
-    //return the credentials
+    fn set_expanded(&mut self, node: TreeNode, expanded: bool) {
+        let id = self.node_id(node);
+        if expanded {
+            self.collapsed.remove(&id);
+        } else {
+            self.collapsed.insert(id);
+        }
+    }
 
-    return (credentials.ip, credentials.user, credentials.password, credentials.datacenter);
+    //nearest row above the cursor with a smaller depth
+    fn parent_node(&self) -> Option<TreeNode> {
+        let rows = self.tree();
+        let depth = rows.get(self.cursor)?.1;
+        rows[..self.cursor].iter().rev().find(|row| row.1 < depth).map(|row| row.0)
+    }
 
-}
+    //put the cursor on the node if it is visible
+    fn select_node(&mut self, node: TreeNode) {
+        if let Some(index) = self.tree().iter().position(|row| row.0 == node) {
+            self.cursor = index;
+        }
+    }
 
+    //keep the cursor in the tree after rows were removed
+    fn clamp_cursor(&mut self) {
+        self.cursor = self.cursor.min(self.tree().len() - 1);
+    }
 
-fn main() -> Result<(), Box<dyn Error>> {
-    let (hostname, username, password, datacenter) = load_crendetials();
+}
 
-    let api = API::new_api(hostname.clone());
+#[derive(Serialize, Deserialize)]
+struct Credentials {
+    ip: String,
+    user: String,
+    password: String,
+    datacenter: String,
+    //timeouts in seconds, the api defaults are used when missing
+    #[serde(default)]
+    connect_timeout: Option<u64>,
+    #[serde(default)]
+    read_timeout: Option<u64>,
+    #[serde(default)]
+    tls: TlsConfig,
+    //endpoint family, detected at login when missing
+    #[serde(default)]
+    api: Option<ApiConfig>,
+    //seconds to wait for a guest shutdown before a hard stop
+    #[serde(default)]
+    guest_shutdown_timeout: Option<u64>,
+    //percent used above which a datastore is highlighted
+    #[serde(default)]
+    datastore_threshold: Option<u64>,
+    //seconds to wait for a deploy or a clone to finish
+    #[serde(default)]
+    provision_timeout: Option<u64>,
+}
 
+//"api": "rest" | "api"
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum ApiConfig {
+    Rest,
+    Api,
+}
 
+//"tls": {"mode": "system"} | {"mode": "ca", "ca_file": ...} | {"mode": "pinned", "fingerprint": ...} | {"mode": "insecure"}
+#[derive(Serialize, Deserialize, Default)]
+#[serde(tag = "mode", rename_all = "lowercase")]
+enum TlsConfig {
+    #[default]
+    System,
+    Ca { ca_file: String },
+    Pinned { fingerprint: String },
+    Insecure,
+}
 
-    //run API main func
-    let credentials = API::authenticate(api.clone()
-                                                    ,username
-                                                    ,password
-                                                     , datacenter
-    );
+impl Credentials {
+    fn connection_config(&self) -> ConnectionConfig {
+        let mut config = ConnectionConfig::default();
+        if let Some(secs) = self.connect_timeout {
+            config.connect_timeout = Duration::from_secs(secs);
+        }
+        if let Some(secs) = self.read_timeout {
+            config.read_timeout = Duration::from_secs(secs);
+        }
+        if let Some(secs) = self.provision_timeout {
+            config.provision_timeout = Duration::from_secs(secs);
+        }
+        config.tls = match &self.tls {
+            TlsConfig::System => TlsMode::System,
+            TlsConfig::Ca { ca_file } => TlsMode::CustomCa(PathBuf::from(ca_file)),
+            TlsConfig::Pinned { fingerprint } => TlsMode::Pinned(fingerprint.clone()),
+            TlsConfig::Insecure => TlsMode::Insecure,
+        };
+        config.api_version = self.api.as_ref().map(|api| match api {
+            ApiConfig::Rest => ApiVersion::Rest,
+            ApiConfig::Api => ApiVersion::Api,
+        });
+        config
+    }
+}
 
+//run a power action on the selected vms of every host
+fn power_selected<F: Fn(&Vm) -> Result<(), ApiError>>(state: &mut State, action: F, new_state: &str, error_msg: &str) {
+    let mut vms = state.hosts
+        .iter_mut()
+        .flat_map(|host| host.vms.iter_mut())
+        .filter(|x| x.selected)
+        .collect::<Vec<&mut Vm>>();
+
+    vms.iter_mut().for_each(|x| {
+        match action(x) {
+            Ok(()) => {
+                //unselect vm and set the new state
+                x.selected = false;
+                x.state = new_state.to_string();
+                x.error = false;
+            }
+            Err(e) => {
+                x.error = true;
+                x.error_msg = format!("{}: {}", error_msg, e);
+            }
+        }
+    });
+}
 
-    enable_raw_mode()?;
-    execute!(std::io::stdout(), EnableMouseCapture, EnterAlternateScreen)?;
-    let mut state = State::new();
+//shut down ('o') or reboot ('r') the guest os of the selected vms, a vm without vmware tools is
+//hard stopped or reset instead, the shut down ones are watched until they are powered off
+fn guest_power_selected(api: &VcenterApi, state: &mut State, action: char, updates: Sender<PowerUpdate>) {
+    let timeout = state.shutdown_timeout;
+    state.hosts
+        .iter_mut()
+        .flat_map(|host| host.vms.iter_mut())
+        .filter(|x| x.selected)
+        .for_each(|x| {
+            let (guest, fallback, hard_state, verb) = if action == 'o' {
+                (guest_shutdown_vm(api.clone(), x.vm_id.clone()), "Powering off vm", "POWERED_OFF", "powered off")
+            } else {
+                (guest_reboot_vm(api.clone(), x.vm_id.clone()), "Resetting vm", "POWERED_ON", "reset")
+            };
+            let result = match guest {
+                Ok(()) if action == 'o' => {
+                    watch_guest_shutdown(api.clone(), x.vm_id.clone(), timeout, updates.clone());
+                    Ok(("SHUTTING_DOWN", None))
+                }
+                Ok(()) => Ok(("POWERED_ON", None)),
+                Err(guest_error) => {
+                    let hard = if action == 'o' { shutdown_vm(api.clone(), x.vm_id.clone()) } else { reboot_vm(api.clone(), x.vm_id.clone()) };
+                    hard.map(|()| (hard_state, Some(format!("Guest did not answer ({}), vm {}", guest_error, verb))))
+                }
+            };
+            match result {
+                Ok((new_state, note)) => {
+                    x.selected = false;
+                    x.state = new_state.to_string();
+                    x.error = note.is_some();
+                    x.error_msg = note.unwrap_or_default();
+                }
+                Err(e) => {
+                    x.error = true;
+                    x.error_msg = format!("Error While {}: {}", fallback, e);
+                }
+            }
+        });
+}
 
-    let hosts = API::get_all_hosts(api.clone(),credentials.clone());
+//wait in the background for the guest os to power off and hard stop the vm when it takes too long
+fn watch_guest_shutdown(api: VcenterApi, vm_id: String, timeout: Duration, updates: Sender<PowerUpdate>) {
+    thread::spawn(move || {
+        let update = match wait_for_power_off(api.clone(), vm_id.clone(), timeout) {
+            Ok(true) => PowerUpdate { vm_id, state: "POWERED_OFF".to_string(), error_msg: None },
+            Ok(false) => match shutdown_vm(api, vm_id.clone()) {
+                Ok(()) => PowerUpdate {
+                    vm_id,
+                    state: "POWERED_OFF".to_string(),
+                    error_msg: Some(format!("Guest did not shut down in {}s, vm powered off", timeout.as_secs())),
+                },
+                Err(e) => PowerUpdate {
+                    vm_id,
+                    state: "POWERED_ON".to_string(),
+                    error_msg: Some(format!("Error While Powering off vm: {}", e)),
+                },
+            },
+            Err(e) => PowerUpdate {
+                vm_id,
+                state: "SHUTTING_DOWN".to_string(),
+                error_msg: Some(format!("Error While Waiting for shutdown: {}", e)),
+            },
+        };
+        //the receiver is gone when the app already exited
+        let _ = updates.send(update);
+    });
+}
 
-    hosts.iter().for_each(|host| {
-        state.hosts.push(Host::new(&host.name, &host.power_state, &host.host));
+fn apply_power_update(state: &mut State, update: PowerUpdate) {
+    state.hosts.iter_mut().for_each(|host| {
+        host.vms.iter_mut().chain(host.cpy_vms.iter_mut())
+            .filter(|vm| vm.vm_id == update.vm_id)
+            .for_each(|vm| {
+                vm.state = update.state.clone();
+                vm.error = update.error_msg.is_some();
+                vm.error_msg = update.error_msg.clone().unwrap_or_default();
+            });
+    });
+}
 
-        let vms = API::get_vms_from_host(api.clone(),credentials.clone(), host.clone().name);
-        vms.iter().for_each(|vm| {
-            state.hosts.last_mut().unwrap().add_vm(Vm::new(&vm.name, &vm.power_state,&vm.vm));
-            state.hosts.last_mut().unwrap().add_cpy_vm(Vm::new(&vm.name, &vm.power_state,&vm.vm));
+//items of these types in every content library, with the name of their library
+fn library_items(api: &VcenterApi, types: &[&str]) -> Result<Vec<(String, VapiLibraryItem)>, ApiError> {
+    let mut found = Vec::new();
+    for library in API::get_libraries(api.clone())? {
+        API::get_library_items(api.clone(), library.id.clone())?
+            .into_iter()
+            .filter(|item| types.contains(&item.item_type.as_str()))
+            .for_each(|item| found.push((library.name.clone(), item)));
+    }
+    found.sort_by(|a, b| (&a.0, &a.1.name).cmp(&(&b.0, &b.1.name)));
+    Ok(found)
+}
 
-        });
+//ovf and vm template items of every content library
+fn load_library_items(api: &VcenterApi, state: &mut State) -> Result<(), ApiError> {
+    state.library_items = library_items(api, &["ovf", "vm-template"])?;
+    state.library_cursor = 0;
+    Ok(())
+}
 
-    });
+//choices of the deploy popup: clusters and hosts already loaded, folders and datastores of the datacenter
+fn open_deploy_form(api: &VcenterApi, state: &mut State) -> Result<(), ApiError> {
+    let datacenter = state.datacenter.as_ref().map(|datacenter| datacenter.datacenter.clone());
+    let mut folders = API::get_vm_folders(api.clone(), datacenter.clone())?;
+    folders.sort_by(|a, b| a.name.cmp(&b.name));
+    let mut datastores = API::get_datastores(api.clone(), datacenter)?;
+    datastores.sort_by(|a, b| a.name.cmp(&b.name));
 
+    let mut targets: Vec<(Option<String>, Option<String>, String)> = state.clusters
+        .iter()
+        .map(|cluster| (Some(cluster.id.clone()), None, format!("Cluster {}", cluster.name)))
+        .collect();
+    state.hosts.iter().for_each(|host| targets.push((host.cluster.clone(), Some(host.ip.clone()), format!("Host {}", host.name))));
 
-    state.host_cursor = Some(0);
-    
+    state.deploy_form = DeployForm { targets, folders, datastores, ..DeployForm::default() };
+    Ok(())
+}
 
+//deploy in the background, vcenter only answers once the disks are copied
+fn deploy_in_background(api: VcenterApi, item: VapiLibraryItem, spec: DeploySpec, deploys: Sender<ProvisionUpdate>) {
+    thread::spawn(move || {
+        let result = API::deploy_library_item(api, &item, &spec).map_err(|e| e.to_string());
+        //the receiver is gone when the app already exited
+        let _ = deploys.send(ProvisionUpdate { name: spec.name, result });
+    });
+}
 
-    let backend = tui::backend::CrosstermBackend::new(std::io::stdout());
-    let mut terminal = Terminal::new(backend)?;
-    let result = run_app(&mut terminal,credentials,&mut state, Duration::from_millis(100),hostname);
+//start the deploy of the item under the library cursor with the choices of the form
+fn deploy_form_submit(api: &VcenterApi, state: &mut State, deploys: Sender<ProvisionUpdate>) {
+    let form = &state.deploy_form;
+    let (Some(target), Some(folder), Some(datastore)) = (
+        form.targets.get(form.target),
+        form.folders.get(form.folder),
+        form.datastores.get(form.datastore),
+    ) else {
+        state.status_msg = "A target, a folder and a datastore are needed to deploy".to_string();
+        return;
+    };
+    let spec = DeploySpec {
+        name: form.name.clone(),
+        folder: folder.folder.clone(),
+        datastore: datastore.datastore.clone(),
+        cluster: target.0.clone(),
+        host: target.1.clone(),
+    };
+    let (_, item) = state.library_items[state.library_cursor].clone();
+    state.status_msg = format!("Deploying {} from {}...", spec.name, item.name);
+    deploy_in_background(api.clone(), item, spec, deploys);
+}
 
-    disable_raw_mode()?;
+//reload the inventory so the new vm shows up and put the cursor on it
+fn apply_provision_update(api: &VcenterApi, state: &mut State, update: ProvisionUpdate) {
+    let vm_id = match update.result {
+        Ok(vm_id) => vm_id,
+        Err(e) => {
+            state.status_msg = format!("Error While Deploying {}: {}", update.name, e);
+            return;
+        }
+    };
+    state.status_msg = "".to_string();
+    if let Err(e) = load_inventory(api, state) {
+        state.status_msg = format!("Error reloading the inventory: {}", e);
+    }
+    search(state);
+    delete(state);
+    let found = state.hosts
+        .iter()
+        .enumerate()
+        .find_map(|(host, h)| h.vms.iter().position(|vm| vm.vm_id == vm_id).map(|vm| (host, vm)));
+    if let Some((host, vm)) = found {
+        state.set_expanded(TreeNode::Host(host), true);
+        state.select_node(TreeNode::Vm(host, vm));
+    }
+}
 
-    execute!(terminal.backend_mut(), DisableMouseCapture,LeaveAlternateScreen)?;
+//choices of the wizard: networks, datastores and vm folders of the datacenter and the hosts already loaded
+fn open_create_form(api: &VcenterApi, state: &mut State) -> Result<(), ApiError> {
+    let datacenter = state.datacenter.as_ref().map(|datacenter| datacenter.datacenter.clone());
+    let mut networks = API::get_networks(api.clone(), datacenter.clone())?;
+    networks.sort_by(|a, b| a.name.cmp(&b.name));
+    let mut datastores = API::get_datastores(api.clone(), datacenter.clone())?;
+    datastores.sort_by(|a, b| a.name.cmp(&b.name));
+    let mut folders = API::get_vm_folders(api.clone(), datacenter)?;
+    folders.sort_by(|a, b| a.name.cmp(&b.name));
+    let hosts = state.hosts.iter().map(|host| (host.ip.clone(), host.name.clone())).collect();
+
+    state.create_form = CreateForm {
+        cpu: "2".to_string(),
+        memory_mib: "4096".to_string(),
+        disk_gib: "40".to_string(),
+        networks,
+        datastores,
+        folders,
+        hosts,
+        ..CreateForm::default()
+    };
+    Ok(())
+}
 
-    if let Err(e) = result {
-        println!("{}", e.to_string());
+//checks done before sending a new vm, the error comes with the wizard step it belongs to
+fn validate_create(state: &State, spec: &CreateSpec) -> Result<(), (usize, String)> {
+    if spec.name.trim().is_empty() {
+        return Err((0, "The VM needs a name".to_string()));
+    }
+    if spec.name.chars().count() > 80 {
+        return Err((0, "VM names are at most 80 characters".to_string()));
+    }
+    if state.hosts.iter().flat_map(|host| host.cpy_vms.iter()).any(|vm| vm.name == spec.name) {
+        return Err((0, format!("A VM named {} already exists", spec.name)));
+    }
+    if spec.guest_os.is_empty() {
+        return Err((0, "The VM needs a guest OS".to_string()));
+    }
+    if spec.cpu_count == 0 || spec.cpu_count > MAX_CPU_COUNT {
+        return Err((1, format!("The CPU count must be between 1 and {}", MAX_CPU_COUNT)));
+    }
+    if spec.memory_mib < 4 || !spec.memory_mib.is_multiple_of(4) {
+        return Err((1, "The memory must be a multiple of 4 MiB".to_string()));
+    }
+    if spec.memory_mib > MAX_MEMORY_MIB {
+        return Err((1, format!("The memory must be at most {} MiB", MAX_MEMORY_MIB)));
+    }
+    if spec.disk_gib == 0 {
+        return Err((1, "The disk must be at least 1 GiB".to_string()));
+    }
+    if spec.disk_gib > MAX_DISK_GIB || spec.disk_gib.checked_mul(1024 * 1024 * 1024).is_none() {
+        return Err((1, format!("The disk must be at most {} GiB", MAX_DISK_GIB)));
+    }
+    if spec.datastore.is_empty() || spec.folder.is_empty() || spec.host.is_empty() {
+        return Err((2, "The VM needs a datastore, a folder and a host".to_string()));
     }
-
     Ok(())
+}
 
+//spec of the wizard, or the first problem found in it
+fn create_form_spec(state: &State) -> Result<CreateSpec, (usize, String)> {
+    let form = &state.create_form;
+    let number = |value: &str, what: &str| value.trim().parse::<u64>().map_err(|_| (1, format!("The {} must be a number", what)));
+    let spec = CreateSpec {
+        name: form.name.trim().to_string(),
+        guest_os: GUEST_OS[form.guest_os].to_string(),
+        cpu_count: u32::try_from(number(&form.cpu, "CPU count")?)
+            .map_err(|_| (1, format!("The CPU count must be between 1 and {}", MAX_CPU_COUNT)))?,
+        memory_mib: number(&form.memory_mib, "memory")?,
+        disk_gib: number(&form.disk_gib, "disk size")?,
+        network: form.networks.get(form.network).cloned(),
+        datastore: form.datastores.get(form.datastore).map(|datastore| datastore.datastore.clone()).unwrap_or_default(),
+        folder: form.folders.get(form.folder).map(|folder| folder.folder.clone()).unwrap_or_default(),
+        host: form.hosts.get(form.host).map(|host| host.0.clone()).unwrap_or_default(),
+    };
+    validate_create(state, &spec)?;
+    Ok(spec)
+}
 
+//next step once the ones before are valid, the last step sends the vm to vcenter
+fn create_form_next(api: &VcenterApi, state: &mut State) {
+    let step = state.create_form.step;
+    let spec = match create_form_spec(state) {
+        Err((found, e)) if found <= step => {
+            state.create_form.step = found;
+            state.create_form.error = e;
+            return;
+        }
+        result => result,
+    };
+    state.create_form.error = "".to_string();
+    if step + 1 < CREATE_STEPS.len() {
+        state.create_form.step += 1;
+        state.create_form.field = 0;
+        return;
+    }
+    //every step was checked, the spec is valid here
+    let Ok(spec) = spec else {
+        return;
+    };
+    match API::create_vm(api.clone(), &spec) {
+        Ok(vm_id) => {
+            state.mode = InputMode::Normal;
+            apply_provision_update(api, state, ProvisionUpdate { name: spec.name, result: Ok(vm_id) });
+        }
+        Err(e) => {
+            state.create_form.error = format!("Error While Creating vm: {}", e);
+        }
+    }
 }
 
-fn run_app<B: Backend>(
-    terminal: &mut Terminal<B>,
-    credentials: String,
-    state: &mut State,
-    tick_rate: Duration,
-    hostname: String,
-)
-    -> Result<(), std::io::Error> {
+//delete the vms of the form, the powered on ones are powered off first
+fn delete_selected(api: &VcenterApi, state: &mut State) {
+    let mut deleted = Vec::new();
+    let mut powered_off = Vec::new();
+    let mut errors = HashMap::new();
+    for (vm_id, _, power_state) in &state.delete_form.vms {
+        if power_state == "POWERED_ON" {
+            //vcenter refuses to delete a running vm, it is only powered off when the form asks for it
+            if !state.delete_form.power_off {
+                errors.insert(vm_id.clone(), "Error While Deleting vm: the vm is powered on".to_string());
+                continue;
+            }
+            if let Err(e) = shutdown_vm(api.clone(), vm_id.clone()) {
+                errors.insert(vm_id.clone(), format!("Error While Powering off vm: {}", e));
+                continue;
+            }
+            powered_off.push(vm_id.clone());
+        }
+        match API::delete_vm(api.clone(), vm_id.clone()) {
+            Ok(()) => deleted.push(vm_id.clone()),
+            Err(e) => {
+                errors.insert(vm_id.clone(), format!("Error While Deleting vm: {}", e));
+            }
+        }
+    }
 
-    let mut last_tick = Instant::now();
-    let api = API::new_api(hostname);
+    state.hosts.iter_mut().for_each(|host| {
+        host.cpy_vms.retain(|vm| !deleted.contains(&vm.vm_id));
+        host.vms.retain(|vm| !deleted.contains(&vm.vm_id));
+        host.vms.iter_mut().chain(host.cpy_vms.iter_mut()).for_each(|vm| {
+            if powered_off.contains(&vm.vm_id) {
+                vm.state = "POWERED_OFF".to_string();
+            }
+            if let Some(e) = errors.get(&vm.vm_id) {
+                vm.error = true;
+                vm.error_msg = e.clone();
+            } else {
+                vm.selected = false;
+            }
+        });
+    });
+    deleted.iter().for_each(|vm_id| {
+        state.vm_tags.remove(vm_id);
+        state.vm_details.remove(vm_id);
+    });
+    if !errors.is_empty() {
+        state.status_msg = format!("Deleted {} of {} VMs", deleted.len(), state.delete_form.vms.len());
+    }
 
-    loop {
-        terminal.draw(|f| ui(f, state))?;
-
-        let timeout = tick_rate
-            .checked_sub(last_tick.elapsed())
-            .unwrap_or_else(|| Duration::from_secs(0));
+    //the cursor goes to the nearest vm left, or back to the hosts when there is none
+    state.clamp_cursor();
+    if state.current_vm().is_none() {
+        state.move_cursor(false, true);
+    }
+    if state.current_vm().is_none() {
+        state.move_cursor(true, true);
+    }
+    state.mode = if state.current_vm().is_some() { InputMode::ListVM } else { InputMode::Normal };
+}
 
-        if poll(timeout)? {
-            if let Event::Key(key) = event::read()? {
-                match state.mode {
-                    InputMode::Normal => {
-                        match key.code {
-                            KeyCode::Esc => {
-                                disable_raw_mode()?;
-                                return Ok(());
-                            }
-                            KeyCode::Down => {
-                                    if state.host_cursor.unwrap_or(0) < (state.hosts.len() - 1) {
-                                        state.host_cursor = Some(state.host_cursor.unwrap_or(0).saturating_add(1));
-                                    }
-                            }
-                            KeyCode::Up=> {
-                                    if state.host_cursor.unwrap_or(0) > 0 {
-                                        state.host_cursor = Some(state.host_cursor.unwrap_or(0).saturating_sub(1));
-                                    }
-                            }
-                            KeyCode::Enter => {
-                                state.mode = InputMode::ListVM;
-                                state.vm_cursor = Some(0);
-                            }
+//updates of the resize form, or what is wrong in it
+fn resize_updates(form: &ResizeForm) -> Result<(CpuUpdate, MemoryUpdate), String> {
+    let number = |value: &str, what: &str| -> Result<Option<u64>, String> {
+        if value.is_empty() {
+            Ok(None)
+        } else {
+            value.parse::<u64>().map(Some).map_err(|_| format!("The {} must be a number", what))
+        }
+    };
+    let cpu_error = || format!("The CPU count must be between 1 and {}", MAX_CPU_COUNT);
+    let cpu = number(&form.cpu, "CPU count")?.map(|cpu| u32::try_from(cpu).map_err(|_| cpu_error())).transpose()?;
+    let cores = number(&form.cores_per_socket, "cores per socket")?.map(|cores| u32::try_from(cores).map_err(|_| cpu_error())).transpose()?;
+    let memory = number(&form.memory_mib, "memory")?;
+    if cpu.is_some_and(|cpu| cpu == 0 || cpu > MAX_CPU_COUNT) {
+        return Err(cpu_error());
+    }
+    if cores == Some(0) {
+        return Err("There must be at least 1 core per socket".to_string());
+    }
+    //without a new cpu count the cores are checked against each vm by vcenter
+    if cores.is_some_and(|cores| cores > cpu.unwrap_or(MAX_CPU_COUNT)) {
+        return Err("The cores per socket can not be more than the CPU count".to_string());
+    }
+    if let (Some(cpu), Some(cores)) = (cpu, cores) {
+        if !cpu.is_multiple_of(cores) {
+            return Err("The CPU count must be a multiple of the cores per socket".to_string());
+        }
+    }
+    if memory.is_some_and(|memory| memory < 4 || !memory.is_multiple_of(4)) {
+        return Err("The memory must be a multiple of 4 MiB".to_string());
+    }
+    if memory.is_some_and(|memory| memory > MAX_MEMORY_MIB) {
+        return Err(format!("The memory must be at most {} MiB", MAX_MEMORY_MIB));
+    }
+    if cpu.is_none() && cores.is_none() && memory.is_none() && form.cpu_hot_add.is_none() && form.cpu_hot_remove.is_none() && form.memory_hot_add.is_none() {
+        return Err("Nothing to change".to_string());
+    }
+    Ok((
+        CpuUpdate {
+            count: cpu,
+            cores_per_socket: cores,
+            hot_add_enabled: form.cpu_hot_add,
+            hot_remove_enabled: form.cpu_hot_remove,
+        },
+        MemoryUpdate { size_mib: memory, hot_add_enabled: form.memory_hot_add },
+    ))
+}
 
-                            KeyCode::Char('s') => {
-                                state.mode = InputMode::Search;
-                            }
+//why the update can not be done while the vm runs, None when it can be done live
+//without the vm details the hot add and hot remove flags are taken as off
+fn resize_needs_power_off(vm: &Vm, info: Option<&VapiVmInfo>, cpu: &CpuUpdate, memory: &MemoryUpdate) -> Option<&'static str> {
+    if vm.state != "POWERED_ON" {
+        return None;
+    }
+    let cpu_hot_add = info.is_some_and(|info| info.cpu.hot_add_enabled);
+    let cpu_hot_remove = info.is_some_and(|info| info.cpu.hot_remove_enabled);
+    let memory_hot_add = info.is_some_and(|info| info.memory.hot_add_enabled);
+    let count = cpu.count.unwrap_or(vm.cpu_count);
+    let size = memory.size_mib.unwrap_or(vm.memory_mib as u64);
+    if cpu.cores_per_socket.is_some() {
+        Some("cores per socket")
+    } else if cpu.hot_add_enabled.is_some() || cpu.hot_remove_enabled.is_some() || memory.hot_add_enabled.is_some() {
+        Some("hot add settings")
+    } else if count > vm.cpu_count && !cpu_hot_add {
+        Some("CPU hot add is off")
+    } else if count < vm.cpu_count && !cpu_hot_remove {
+        Some("CPU hot remove is off")
+    } else if size < vm.memory_mib as u64 {
+        Some("memory can not shrink live")
+    } else if size > vm.memory_mib as u64 && !memory_hot_add {
+        Some("memory hot add is off")
+    } else {
+        None
+    }
+}
 
-                            KeyCode::Char('f') => {
-                                state.mode = InputMode::File;
-                            }
-                            _ => {
-                            }
-                        }
-                    }
-                    InputMode::ListVM => {
-                        match key.code {
+//hot add flags of the powered on selected vms are needed to tell which ones need a power off
+fn open_resize_form(api: &VcenterApi, state: &mut State) {
+    let vm_ids = state.hosts.iter()
+        .flat_map(|host| host.vms.iter())
+        .filter(|vm| vm.selected && vm.state == "POWERED_ON" && !state.vm_details.contains_key(&vm.vm_id))
+        .map(|vm| vm.vm_id.clone())
+        .collect::<Vec<String>>();
+    vm_ids.into_iter().for_each(|vm_id| {
+        if let Ok(info) = API::get_vm_details(api.clone(), vm_id.clone()) {
+            state.vm_details.insert(vm_id, info);
+        }
+    });
+    state.resize_form = ResizeForm::default();
+    state.mode = InputMode::Resize;
+}
 
-                            KeyCode::Char('h') => {
-                                state.mode = InputMode::Normal;
-                                state.vm_cursor = None;
-                            }
-                            KeyCode::Up => {
+//apply the form to every selected vm, the new sizes are kept in the inventory
+fn resize_selected(api: &VcenterApi, state: &mut State, cpu: &CpuUpdate, memory: &MemoryUpdate) {
+    let change_cpu = cpu.count.is_some() || cpu.cores_per_socket.is_some() || cpu.hot_add_enabled.is_some() || cpu.hot_remove_enabled.is_some();
+    let change_memory = memory.size_mib.is_some() || memory.hot_add_enabled.is_some();
+    let mut resized = Vec::new();
+    let mut failed = 0;
+    state.hosts.iter_mut().flat_map(|host| host.vms.iter_mut()).filter(|vm| vm.selected).for_each(|vm| {
+        let result = if change_cpu { API::update_cpu(api.clone(), vm.vm_id.clone(), cpu) } else { Ok(()) };
+        let result = result.and_then(|()| if change_memory { API::update_memory(api.clone(), vm.vm_id.clone(), memory) } else { Ok(()) });
+        match result {
+            Ok(()) => {
+                vm.selected = false;
+                vm.error = false;
+                vm.cpu_count = cpu.count.unwrap_or(vm.cpu_count);
+                vm.memory_mib = memory.size_mib.and_then(|size| u32::try_from(size).ok()).unwrap_or(vm.memory_mib);
+                resized.push((vm.vm_id.clone(), vm.cpu_count, vm.memory_mib));
+            }
+            Err(e) => {
+                vm.error = true;
+                vm.error_msg = format!("Error While Resizing vm: {}", e);
+                failed += 1;
+            }
+        }
+    });
 
-                                    if state.vm_cursor.unwrap_or(0) > 0 {
-                                        state.vm_cursor = Some(state.vm_cursor.unwrap_or(0).saturating_sub(1));
-                                    }
-                            }
-                            KeyCode::Down => {
-                                if state.vm_cursor.unwrap_or(0) < (state.hosts[state.host_cursor.unwrap_or(0)].vms.len() - 1) {
-                                    state.vm_cursor = Some(state.vm_cursor.unwrap_or(0).saturating_add(1));
-                                }
-                            }
-                            KeyCode::Char(' ') => {
-                                    let mut vm = &mut state.hosts[state.host_cursor.unwrap_or(0)].vms[state.vm_cursor.unwrap_or(0)];
-                                    //edit selected
-                                    vm.selected = !vm.selected;
-                            }
-                            KeyCode::Char('p') => {
-                             //   if key_pressed {
-                                    let mut vms = state.hosts[state.host_cursor.unwrap_or(0)]
-                                        .vms
-                                        .iter_mut()
-                                        .filter(|x| x.selected)
-                                        .collect::<Vec<&mut Vm>>();
-
-                                    vms.iter_mut().for_each(|x| {
-                                        let statuscode = poweron_vm(api.clone(), credentials.clone(), x.vm_id.clone());
-
-                                        if statuscode == 200 {
-                                            //unselect vm and set state to POWERED_ON
-                                            x.selected = false;
-                                            x.state = "POWERED_ON".to_string();
-                                            x.error = false;
-
-                                        } else {
-                                            x.error = true;
-                                            x.error_msg = ("Error While Powering on vm".to_string())
+    //the copy used by the search keeps the new sizes too
+    state.hosts.iter_mut().flat_map(|host| host.cpy_vms.iter_mut()).for_each(|vm| {
+        if let Some((_, cpu_count, memory_mib)) = resized.iter().find(|resized| resized.0 == vm.vm_id) {
+            vm.cpu_count = *cpu_count;
+            vm.memory_mib = *memory_mib;
+        }
+    });
+    resized.iter().for_each(|resized| {
+        state.vm_details.remove(&resized.0);
+    });
+    if failed > 0 {
+        state.status_msg = format!("Resized {} of {} VMs", resized.len(), resized.len() + failed);
+    }
+    load_vm_details(api, state);
+}
 
-                                        }
-                                    });
-                            }
-                            KeyCode::Char('o') => {
+//create the vms of a yaml file with a create: list, false when the file is not one
+fn create_from_file(api: &VcenterApi, state: &mut State) -> bool {
+    let Ok(text) = std::fs::read_to_string(&state.file_path) else {
+        return false;
+    };
+    match serde_yaml::from_str::<serde_yaml::Value>(&text) {
+        Ok(serde_yaml::Value::Mapping(map)) if map.contains_key("create") => {}
+        _ => return false,
+    }
+    let file: CreateFile = match serde_yaml::from_str(&text) {
+        Ok(file) => file,
+        Err(e) => {
+            state.status_msg = format!("Error reading {}: {}", state.file_path, e);
+            return true;
+        }
+    };
 
-                                    let mut vms = state.hosts[state.host_cursor.unwrap_or(0)]
-                                        .vms
-                                        .iter_mut()
-                                        .filter(|x| x.selected)
-                                        .collect::<Vec<&mut Vm>>();
-                                    vms.iter_mut().for_each(|x| {
-                                        let statuscode = shutdown_vm(api.clone(), credentials.clone(), x.vm_id.clone());
-
-                                        if statuscode == 200 {
-                                            //unselect vm and set state to POWERED_ON
-                                            x.selected = false;
-                                            x.state = "POWERED_OFF".to_string();
-                                            x.error = false;
-                                        } else {
-                                            x.error = true;
-                                            x.error_msg = ("Error While Powering on vm".to_string())
-                                        }
-                                    });
-                            }
-                            KeyCode::Char('r') => {
+    let datacenter = state.datacenter.as_ref().map(|datacenter| datacenter.datacenter.clone());
+    let lookups = API::get_networks(api.clone(), datacenter.clone()).and_then(|networks| {
+        Ok((networks, API::get_datastores(api.clone(), datacenter.clone())?, API::get_vm_folders(api.clone(), datacenter)?))
+    });
+    let (networks, datastores, folders) = match lookups {
+        Ok(lookups) => lookups,
+        Err(e) => {
+            state.status_msg = format!("Error loading the placement choices: {}", e);
+            return true;
+        }
+    };
+
+    let total = file.create.len();
+    let mut errors = Vec::new();
+    for entry in file.create {
+        let found = |kind: &str, name: &str, id: Option<String>| id.ok_or_else(|| format!("{} {} not found", kind, name));
+        let spec = (|| -> Result<CreateSpec, String> {
+            let network = match &entry.network {
+                Some(name) => Some(networks.iter().find(|network| &network.name == name).cloned().ok_or_else(|| format!("Network {} not found", name))?),
+                None => None,
+            };
+            let spec = CreateSpec {
+                name: entry.name.clone(),
+                guest_os: entry.guest_os.clone(),
+                cpu_count: entry.cpu,
+                memory_mib: entry.memory_mib,
+                disk_gib: entry.disk_gib,
+                network,
+                datastore: found("Datastore", &entry.datastore, datastores.iter().find(|datastore| datastore.name == entry.datastore).map(|datastore| datastore.datastore.clone()))?,
+                folder: found("Folder", &entry.folder, folders.iter().find(|folder| folder.name == entry.folder).map(|folder| folder.folder.clone()))?,
+                host: found("Host", &entry.host, state.hosts.iter().find(|host| host.name == entry.host).map(|host| host.ip.clone()))?,
+            };
+            //the entries go through the same checks as the wizard
+            validate_create(state, &spec).map_err(|(_, e)| e)?;
+            Ok(spec)
+        })();
+        if let Err(e) = spec.and_then(|spec| API::create_vm(api.clone(), &spec).map_err(|e| e.to_string())) {
+            errors.push(format!("{}: {}", entry.name, e));
+        }
+    }
 
-                                    let mut vms = state.hosts[state.host_cursor.unwrap_or(0)]
-                                        .vms
-                                        .iter_mut()
-                                        .filter(|x| x.selected)
-                                        .collect::<Vec<&mut Vm>>();
-                                    vms.iter_mut().for_each(|x| {
-                                        let statuscode = reboot_vm(api.clone(), credentials.clone(), x.vm_id.clone());
-
-                                        if statuscode == 200 {
-                                            //unselect vm and set state to POWERED_ON
-                                            x.selected = false;
-                                            x.state = "POWERED_ON".to_string();
-                                            x.error = false;
-                                            x.error_msg = ("Error While Powering on vm".to_string())
-                                        } else {
-                                            x.error = true;
-                                            x.error_msg = ("Error While Powering on vm".to_string())
+    if errors.len() < total {
+        if let Err(e) = load_inventory(api, state) {
+            errors.push(format!("Error reloading the inventory: {}", e));
+        }
+        search(state);
+        delete(state);
+    }
+    if !errors.is_empty() {
+        state.status_msg = format!("Created {} of {} VMs from {}: {}", total - errors.len(), total, state.file_path, errors.join(", "));
+    }
+    true
+}
 
-                                        }
-                                    });
-                            }
-                            _ => {
-                            }
-                        }
-                    }
-                    InputMode::Search => {
-                        match key.code {
-                             KeyCode::Esc => {
-                                    state.mode = InputMode::Normal;
-                                    state.vm_cursor = None;
-                            }
-                            KeyCode::Char(c) => {
-                                    state.search_string.push(c);
-                                    search(state);
-                                    delete(state);
-                            }
+//web-{01..05} gives web-01 to web-05, a leading zero pads the numbers and several ranges multiply
+fn expand_pattern(pattern: &str) -> Result<Vec<String>, String> {
+    let Some(start) = pattern.find('{') else {
+        return Ok(vec![pattern.to_string()]);
+    };
+    let end = pattern[start..].find('}').map(|end| start + end).ok_or("Missing } in the name pattern")?;
+    let range = &pattern[start + 1..end];
+    let (from, to) = range.split_once("..").ok_or_else(|| format!("{{{}}} is not a range like {{01..05}}", range))?;
+    let width = if from.starts_with('0') || to.starts_with('0') { from.len().max(to.len()) } else { 0 };
+    let (from, to) = match (from.parse::<u32>(), to.parse::<u32>()) {
+        (Ok(from), Ok(to)) => (from, to),
+        _ => return Err(format!("{{{}}} is not a range of numbers", range)),
+    };
+    //the size is checked before any name is built, a huge range must not be allocated
+    let count = (from.abs_diff(to) as usize).saturating_add(1);
+    let rest = expand_pattern(&pattern[end + 1..])?;
+    if count.saturating_mul(rest.len()) > CLONE_LIMIT {
+        return Err(format!("The pattern gives more than {} names", CLONE_LIMIT));
+    }
+    let numbers = (0..count as u32).map(|step| if from <= to { from + step } else { from - step });
+    let mut names = Vec::with_capacity(count * rest.len());
+    for number in numbers {
+        for tail in &rest {
+            names.push(format!("{}{:0width$}{}", &pattern[..start], number, tail, width = width));
+        }
+    }
+    Ok(names)
+}
 
-                            KeyCode::Backspace => {
-                                    state.search_string.pop();
-                                    search(state);
-                                    delete(state);
-                            }
-                            _ => {
+//clone the vm once per name in the background, one update is sent after each copy
+fn clone_in_background(api: VcenterApi, source: String, names: Vec<String>, power_on: bool, clones: Sender<ProvisionUpdate>) {
+    thread::spawn(move || {
+        for name in names {
+            let spec = CloneSpec { name: name.clone(), source: source.clone(), power_on };
+            let result = API::clone_vm(api.clone(), &spec).map_err(|e| e.to_string());
+            //the receiver is gone when the app already exited
+            if clones.send(ProvisionUpdate { name, result }).is_err() {
+                return;
+            }
+        }
+    });
+}
 
-                            }
-                        }
-                    }
-                    InputMode::File =>{
-                        match key.code {
-                            KeyCode::Char(c) => {
-                                    state.file_path.push(c);
-                            }
+//start cloning the vm under the cursor with the names of the pattern
+fn clone_form_submit(api: &VcenterApi, state: &mut State, clones: Sender<ProvisionUpdate>) {
+    let Some(vm) = state.current_vm() else {
+        return;
+    };
+    let source = vm.vm_id.clone();
+    match expand_pattern(&state.clone_form.pattern) {
+        Ok(names) => {
+            state.clone_results = names.iter().map(|name| CloneResult { name: name.clone(), result: None }).collect();
+            state.clone_source = source.clone();
+            clone_in_background(api.clone(), source, names, state.clone_form.power_on, clones);
+            state.mode = InputMode::CloneResults;
+        }
+        Err(e) => {
+            if let Some(vm) = state.current_vm_mut() {
+                vm.error = true;
+                vm.error_msg = format!("Error While Cloning vm: {}", e);
+            }
+            state.mode = InputMode::ListVM;
+        }
+    }
+}
 
-                            KeyCode::Enter => {
-                                    state.mode = InputMode::Normal;
-                                    select_by_file(state);
-                                    state.file_path = "".to_string();
-                            }
+//record the copy, once every copy is done the inventory is reloaded and failures are shown on the source vm
+fn apply_clone_update(api: &VcenterApi, state: &mut State, update: ProvisionUpdate) {
+    if let Some(row) = state.clone_results.iter_mut().find(|row| row.name == update.name && row.result.is_none()) {
+        row.result = Some(update.result);
+    }
+    if state.clone_results.iter().any(|row| row.result.is_none()) {
+        return;
+    }
+    if let Err(e) = load_inventory(api, state) {
+        state.status_msg = format!("Error reloading the inventory: {}", e);
+    }
+    search(state);
+    delete(state);
+    let failed: Vec<String> = state.clone_results
+        .iter()
+        .filter_map(|row| match &row.result {
+            Some(Err(e)) => Some(format!("{}: {}", row.name, e)),
+            _ => None,
+        })
+        .collect();
+    if failed.is_empty() {
+        return;
+    }
+    let total = state.clone_results.len();
+    let source = state.clone_source.clone();
+    state.hosts
+        .iter_mut()
+        .flat_map(|host| host.vms.iter_mut())
+        .filter(|vm| vm.vm_id == source)
+        .for_each(|vm| {
+            vm.error = true;
+            vm.error_msg = format!("{} of {} clones failed: {}", failed.len(), total, failed.join(", "));
+        });
+}
 
-                            KeyCode::Backspace => {
-                                    state.file_path.pop();
-                            }
+fn flatten_snapshots(list: &[VapiSnapshot], current: &str, depth: usize, rows: &mut Vec<SnapshotRow>) {
+    list.iter().for_each(|snapshot| {
+        rows.push(SnapshotRow {
+            id: snapshot.snapshot.value.clone(),
+            name: snapshot.name.clone(),
+            description: snapshot.description.clone(),
+            //2024-01-02T10:11:12.345Z -> 2024-01-02 10:11:12
+            created: snapshot.create_time.replace('T', " ").chars().take(19).collect(),
+            depth,
+            current: snapshot.snapshot.value == current,
+        });
+        flatten_snapshots(&snapshot.child_snapshot_list, current, depth + 1, rows);
+    });
+}
 
-                            _ => {
+//load the snapshot tree of the vm under the cursor
+fn load_snapshots(api: &VcenterApi, state: &mut State) {
+    let Some(vm_id) = state.current_vm().map(|vm| vm.vm_id.clone()) else {
+        return;
+    };
+    state.snapshots.clear();
+    match get_snapshots(api.clone(), vm_id) {
+        Ok(Some(info)) => {
+            let current = info.current_snapshot.map(|snapshot| snapshot.value).unwrap_or_default();
+            flatten_snapshots(&info.root_snapshot_list, &current, 0, &mut state.snapshots);
+        }
+        Ok(None) => {}
+        Err(e) => {
+            if let Some(vm) = state.current_vm_mut() {
+                vm.error = true;
+                vm.error_msg = format!("Error While Loading snapshots: {}", e);
+            }
+        }
+    }
+    if state.snapshot_cursor >= state.snapshots.len() {
+        state.snapshot_cursor = state.snapshots.len().saturating_sub(1);
+    }
+}
 
-                            }
-                        }
-                    }
-                }
-            } else {
+//disks of the vm under the cursor, an error is shown on the vm
+fn load_disks(api: &VcenterApi, state: &mut State) {
+    let Some(vm_id) = state.current_vm().map(|vm| vm.vm_id.clone()) else {
+        return;
+    };
+    state.disks.clear();
+    match API::get_vm_disks(api.clone(), vm_id) {
+        Ok(disks) => state.disks = disks,
+        Err(e) => {
+            if let Some(vm) = state.current_vm_mut() {
+                vm.error = true;
+                vm.error_msg = format!("Error While Loading disks: {}", e);
+            }
+        }
+    }
+    if state.disk_cursor >= state.disks.len() {
+        state.disk_cursor = state.disks.len().saturating_sub(1);
+    }
+}
 
+//the add form offers the datastores of the datacenter
+fn open_disk_form(api: &VcenterApi, state: &mut State, grow: bool) {
+    if grow {
+        let size = state.disks.get(state.disk_cursor).map(|disk| disk.capacity.div_ceil(1024 * 1024 * 1024)).unwrap_or_default();
+        state.disk_form = Some(DiskForm { grow, size_gib: size.to_string(), ..DiskForm::default() });
+        return;
+    }
+    let datacenter = state.datacenter.as_ref().map(|datacenter| datacenter.datacenter.clone());
+    match API::get_datastores(api.clone(), datacenter) {
+        Ok(mut datastores) => {
+            datastores.sort_by(|a, b| a.name.cmp(&b.name));
+            state.disk_form = Some(DiskForm { size_gib: "10".to_string(), datastores, ..DiskForm::default() });
+        }
+        Err(e) => {
+            if let Some(vm) = state.current_vm_mut() {
+                vm.error = true;
+                vm.error_msg = format!("Error While Loading datastores: {}", e);
             }
         }
-        if last_tick.elapsed() >= tick_rate {
-            last_tick = Instant::now();
+    }
+}
+
+//grow or add the disk of the form, it stays open with the error when vcenter refuses it
+fn disk_form_submit(api: &VcenterApi, state: &mut State, disks: Sender<DiskUpdate>) {
+    let (Some(vm_id), Some(form)) = (state.current_vm().map(|vm| vm.vm_id.clone()), state.disk_form.as_mut()) else {
+        return;
+    };
+    let size = match form.size_gib.parse::<u64>() {
+        Ok(size) if size > MAX_DISK_GIB => {
+            form.error = format!("Disks are at most {} GiB", MAX_DISK_GIB);
+            return;
+        }
+        Ok(size) if size > 0 => size,
+        _ => {
+            form.error = "The size must be at least 1 GiB".to_string();
+            return;
+        }
+    };
+    let api = api.clone();
+    if form.grow {
+        let Some(disk) = state.disks.get(state.disk_cursor) else {
+            return;
+        };
+        if size * 1024 * 1024 * 1024 <= disk.capacity {
+            form.error = format!("Disks can only grow, {} is {}", disk.label, gib(disk.capacity));
+            return;
         }
+        let disk = disk.disk.clone();
+        disk_in_background("Growing", vm_id.clone(), disks, move || API::grow_disk(api, vm_id, disk, size));
+    } else {
+        let Some(datastore) = form.datastores.get(form.datastore) else {
+            form.error = "There is no datastore for the disk".to_string();
+            return;
+        };
+        let spec = DiskSpec { capacity_gib: size, datastore: datastore.name.clone(), provisioning: DISK_PROVISIONING[form.provisioning].0 };
+        disk_in_background("Adding", vm_id.clone(), disks, move || API::add_disk(api, vm_id, &spec));
     }
+    state.disk_task = Some(if form.grow { "Growing" } else { "Adding" });
+    state.disk_form = None;
 }
 
-fn ui<B: Backend>(f: &mut Frame<B>, state: &mut State) {
-    f.size();
+fn remove_disk(api: &VcenterApi, state: &mut State, disks: Sender<DiskUpdate>) {
+    let (Some(vm_id), Some(disk)) = (state.current_vm().map(|vm| vm.vm_id.clone()), state.disks.get(state.disk_cursor)) else {
+        return;
+    };
+    let (api, disk) = (api.clone(), disk.disk.clone());
+    disk_in_background("Removing", vm_id.clone(), disks, move || API::remove_disk(api, vm_id, disk));
+    state.disk_task = Some("Removing");
+}
 
-    let parent_layout = Layout::default()
-        .direction(tui::layout::Direction::Horizontal)
-        .constraints(
-            [
-                Constraint::Percentage(30),
-                Constraint::Percentage(70)
-            ].as_ref()
-        )
-        .split(f.size());
+//the reconfigure of the vm waits for its task, it runs on its own thread
+fn disk_in_background<F>(action: &'static str, vm_id: String, disks: Sender<DiskUpdate>, task: F)
+where
+    F: FnOnce() -> Result<(), ApiError> + Send + 'static,
+{
+    thread::spawn(move || {
+        let result = task().map_err(|e| e.to_string());
+        //the receiver is gone when the app already exited
+        let _ = disks.send(DiskUpdate { vm_id, action, result });
+    });
+}
 
-    let new_section_block = Block::default()
-        .title("Info")
-        .borders(Borders::ALL)
-        .border_type(BorderType::Rounded);
-    f.render_widget(new_section_block, parent_layout[0]);
-    show_info(f,state, parent_layout[0]);
+//the vm is only updated once its disk task ended, the disks are reloaded when they are still shown
+fn apply_disk_update(api: &VcenterApi, state: &mut State, update: DiskUpdate) {
+    state.disk_task = None;
+    state.hosts.iter_mut().for_each(|host| {
+        host.vms.iter_mut().chain(host.cpy_vms.iter_mut())
+            .filter(|vm| vm.vm_id == update.vm_id)
+            .for_each(|vm| match &update.result {
+                Ok(()) => vm.error = false,
+                Err(e) => {
+                    vm.error = true;
+                    vm.error_msg = format!("Error While {} disk: {}", update.action, e);
+                }
+            });
+    });
+    if let InputMode::Disks = state.mode {
+        if state.current_vm().is_some_and(|vm| vm.vm_id == update.vm_id) {
+            disks_changed(api, state, &update.vm_id);
+            return;
+        }
+    }
+    state.vm_details.remove(&update.vm_id);
+}
 
+//the details of the vm have the old disks, they are loaded again
+fn disks_changed(api: &VcenterApi, state: &mut State, vm_id: &str) {
+    state.vm_details.remove(vm_id);
+    load_vm_details(api, state);
+    load_disks(api, state);
+}
 
-    let new_list_section = Block::default()
-        .title("Datacenter")
-        .borders(Borders::ALL)
-        .border_type(BorderType::Rounded);
-    f.render_widget(new_list_section, parent_layout[1]);
-    list_section(f,state, parent_layout[1]);
+//revert ('v') or delete ('d') the snapshot under the cursor in the background
+fn snapshot_action(api: &VcenterApi, state: &mut State, action: char, snapshots: Sender<SnapshotUpdate>) {
+    let Some(vm_id) = state.current_vm().map(|vm| vm.vm_id.clone()) else {
+        return;
+    };
+    let snapshot = state.snapshots[state.snapshot_cursor].id.clone();
+    state.snapshot_task = Some(action);
+    let api = api.clone();
+    thread::spawn(move || {
+        let result = if action == 'v' {
+            revert_snapshot(api, snapshot)
+        } else {
+            delete_snapshot(api, snapshot)
+        };
+        //the receiver is gone when the app already exited
+        let _ = snapshots.send(SnapshotUpdate { vm_id, action, result: result.map_err(|e| e.to_string()) });
+    });
+}
 
-    file_popup(f, state);
+//create the snapshot of the form on every selected vm in the background, one update is sent after each vm
+fn snapshot_selected(api: &VcenterApi, state: &mut State, snapshots: Sender<SnapshotUpdate>) {
+    let form = state.snapshot_form.clone();
+    state.snapshot_results = state.hosts
+        .iter()
+        .flat_map(|host| host.vms.iter())
+        .filter(|vm| vm.selected)
+        .map(|vm| SnapshotResult { vm_id: vm.vm_id.clone(), name: vm.name.clone(), result: None })
+        .collect();
+    if state.snapshot_results.is_empty() {
+        state.mode = InputMode::ListVM;
+        return;
+    }
+    let vms: Vec<String> = state.snapshot_results.iter().map(|row| row.vm_id.clone()).collect();
+    let api = api.clone();
+    thread::spawn(move || {
+        for vm_id in vms {
+            let result = create_snapshot(api.clone(), vm_id.clone(), form.name.clone(), form.description.clone(), form.memory, form.quiesce)
+                .map_err(|e| e.to_string());
+            //the receiver is gone when the app already exited
+            if snapshots.send(SnapshotUpdate { vm_id, action: 'c', result }).is_err() {
+                return;
+            }
+        }
+    });
+    state.mode = InputMode::SnapshotResults;
+}
+
+//the vm is only updated once its snapshot task ended, the tree is reloaded when it is still shown
+fn apply_snapshot_update(api: &VcenterApi, state: &mut State, update: SnapshotUpdate) {
+    if update.action == 'c' {
+        if let Some(row) = state.snapshot_results.iter_mut().find(|row| row.vm_id == update.vm_id && row.result.is_none()) {
+            row.result = Some(update.result.clone());
+        }
+    } else {
+        state.snapshot_task = None;
+    }
+    let verb = if update.action == 'c' { "Creating" } else { "Updating" };
+    state.hosts.iter_mut().for_each(|host| {
+        host.vms.iter_mut().chain(host.cpy_vms.iter_mut())
+            .filter(|vm| vm.vm_id == update.vm_id)
+            .for_each(|vm| match &update.result {
+                Ok(()) => {
+                    //the vms left selected are the ones that failed
+                    if update.action == 'c' {
+                        vm.selected = false;
+                    }
+                    vm.error = false;
+                }
+                Err(e) => {
+                    vm.error = true;
+                    vm.error_msg = format!("Error While {} snapshot: {}", verb, e);
+                }
+            });
+    });
+    if let InputMode::Snapshots = state.mode {
+        if state.current_vm().is_some_and(|vm| vm.vm_id == update.vm_id) {
+            load_snapshots(api, state);
+        }
+    }
+}
+
+//full info of the vm, from the cache when it was already fetched
+fn vm_info(api: &VcenterApi, state: &mut State, vm_id: &str) -> Result<VapiVmInfo, ApiError> {
+    if let Some(info) = state.vm_details.get(vm_id) {
+        return Ok(info.clone());
+    }
+    let info = API::get_vm_details(api.clone(), vm_id.to_string())?;
+    state.vm_details.insert(vm_id.to_string(), info.clone());
+    Ok(info)
+}
+
+//fetch the full info of the vm under the cursor, the details and the nic and cd-rom boxes use it
+fn load_vm_details(api: &VcenterApi, state: &mut State) {
+    let Some(vm_id) = state.current_vm().map(|vm| vm.vm_id.clone()) else {
+        return;
+    };
+    if let Err(e) = vm_info(api, state, &vm_id) {
+        if let Some(vm) = state.current_vm_mut() {
+            vm.error = true;
+            vm.error_msg = format!("Error While Loading vm details: {}", e);
+        }
+    }
+}
+
+//clusters, hosts and vms of the datacenter, replaces the ones already loaded
+fn load_inventory(api: &VcenterApi, state: &mut State) -> Result<(), ApiError> {
+    let datacenter = state.datacenter.as_ref().map(|datacenter| datacenter.datacenter.clone());
+    let clusters = API::get_clusters(api.clone(), datacenter.clone())?;
+    let hosts = API::get_all_hosts(api.clone(), datacenter)?;
+
+    //hosts found in no cluster are standalone hosts of the datacenter
+    let mut host_cluster = HashMap::new();
+    for cluster in &clusters {
+        API::get_cluster_hosts(api.clone(), cluster.cluster.clone())?.into_iter().for_each(|host| {
+            host_cluster.insert(host.host, cluster.cluster.clone());
+        });
+    }
+
+    state.clusters = clusters.iter().map(|cluster| Cluster { id: cluster.cluster.clone(), name: cluster.name.clone() }).collect();
+    let host_ids: Vec<String> = hosts.iter().map(|host| host.host.clone()).collect();
+    let maintenance = API::get_hosts_maintenance(api.clone(), &host_ids).unwrap_or_default();
+    state.hosts.clear();
+    state.vm_details.clear();
+    hosts.iter().for_each(|host| {
+        state.hosts.push(Host::new(&host.name, &host.power_state, &host.host));
+        let last = state.hosts.last_mut().unwrap();
+        last.cluster = host_cluster.get(&host.host).cloned();
+        last.connection_state = host.connection_state.clone();
+        last.maintenance = maintenance.get(&host.host).copied();
+
+        match API::get_vms_from_host(api.clone(), host.host.clone()) {
+            Ok(vms) => {
+                vms.iter().for_each(|vm| {
+                    state.hosts.last_mut().unwrap().add_vm(Vm::new(&vm.name, &vm.power_state,&vm.vm, vm.cpu_count, vm.memory_size_mib));
+                    state.hosts.last_mut().unwrap().add_cpy_vm(Vm::new(&vm.name, &vm.power_state,&vm.vm, vm.cpu_count, vm.memory_size_mib));
+
+                });
+            }
+            Err(e) => {
+                state.status_msg = format!("Error loading vms of {}: {}", host.name, e);
+            }
+        }
 
+    });
 
+    load_tags(api, state);
+    load_groups(api, state)?;
+    state.clamp_cursor();
+    Ok(())
 }
 
-fn list_section<B: Backend>(f: &mut Frame<B>, state: &mut State, rect: Rect) {
-    let items: Vec<ListItem> = state
-        .hosts
-        .iter()
-        .enumerate()
-        .map(|(index,host)| {
-            //create a host_item empty
-
-            let mut host_item = ListItem::new(format!("\n• {}", host.name))
-                .style(Style::default().fg(Color::Red));
-            let selected_host = state.host_cursor == Some(index);
-            if selected_host {
-                host_item = ListItem::new(format!("\n• {}", host.name))
-                    .style(Style::default().fg(Color::Green));
-            } else {
-                host_item = ListItem::new(format!("\n• {}", host.name))
-                    .style(Style::default().fg(Color::Gray));
-            }
+//tag catalog and the tags of every vm, the inventory is still usable when tagging fails
+//the categories and tags already loaded are reused, only the new ones are fetched
+fn load_tags(api: &VcenterApi, state: &mut State) {
+    let vms: Vec<String> = state.hosts.iter().flat_map(|host| host.cpy_vms.iter()).map(|vm| vm.vm_id.clone()).collect();
+    let result = API::get_categories(api.clone(), &state.categories).and_then(|categories| {
+        let tags = API::get_tags(api.clone(), &state.tags)?;
+        let vm_tags = API::get_vm_tags(api.clone(), &vms)?;
+        Ok((categories, tags, vm_tags))
+    });
+    match result {
+        Ok((categories, mut tags, vm_tags)) => {
+            state.categories = categories;
+            tags.sort_by_key(|tag| tag_label(state, tag));
+            state.tags = tags;
+            state.vm_tags = vm_tags;
+        }
+        Err(e) => {
+            state.tags.clear();
+            state.vm_tags.clear();
+            state.status_msg = format!("Error loading the tags: {}", e);
+        }
+    }
+    //a filter on a tag that was deleted would hide every vm
+    if !state.tags.iter().any(|tag| Some(&tag.id) == state.tag_filter.as_ref()) {
+        state.tag_filter = None;
+    }
+    state.tag_cursor = state.tag_cursor.min(state.tags.len().saturating_sub(1));
+}
+
+//tags are shown as category:name, env:prod
+fn tag_label(state: &State, tag: &VapiTag) -> String {
+    match state.categories.iter().find(|category| category.id == tag.category_id) {
+        Some(category) => format!("{}:{}", category.name, tag.name),
+        None => tag.name.clone(),
+    }
+}
+
+//labels of the tags attached to the vm
+fn vm_tag_labels(state: &State, vm_id: &str) -> Vec<String> {
+    state.vm_tags
+        .get(vm_id)
+        .map(|ids| {
+            ids.iter()
+                .map(|id| match state.tags.iter().find(|tag| &tag.id == id) {
+                    Some(tag) => tag_label(state, tag),
+                    None => id.clone(),
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+//attach ('a') or detach ('d') the tag under the cursor on every selected vm
+fn tag_selected(api: &VcenterApi, state: &mut State, action: char) {
+    let Some(tag) = state.tags.get(state.tag_cursor).cloned() else {
+        return;
+    };
+    let vms: Vec<String> = state.hosts
+        .iter()
+        .flat_map(|host| host.vms.iter())
+        .filter(|vm| vm.selected)
+        .map(|vm| vm.vm_id.clone())
+        .collect();
+    let result = if action == 'a' {
+        API::attach_tag(api.clone(), tag.id.clone(), &vms)
+    } else {
+        API::detach_tag(api.clone(), tag.id.clone(), &vms)
+    };
+    match result {
+        Ok(()) => {
+            state.hosts.iter_mut().flat_map(|host| host.vms.iter_mut()).for_each(|vm| vm.selected = false);
+        }
+        Err(e) => {
+            state.status_msg = format!("Error While Updating tag {}: {}", tag_label(state, &tag), e);
+        }
+    }
+    //the call can fail on some vms only, the tags are read again instead of guessed
+    match API::get_vm_tags(api.clone(), &vms) {
+        Ok(vm_tags) => state.vm_tags.extend(vm_tags),
+        Err(e) => state.status_msg = format!("Error loading the tags: {}", e),
+    }
+}
+
+//names of the vms with files on the datastore under the cursor, vms of other datacenters keep their id
+fn load_datastore_vms(api: &VcenterApi, state: &mut State) {
+    state.datastore_vms.clear();
+    let Some(datastore) = state.datastores.get(state.datastore_cursor) else {
+        return;
+    };
+    match API::get_datastore_vms(api.clone(), datastore.datastore.clone()) {
+        Ok(vms) => {
+            state.datastore_vms = vms
+                .into_iter()
+                .map(|vm_id| {
+                    state.hosts
+                        .iter()
+                        .flat_map(|host| host.cpy_vms.iter())
+                        .find(|vm| vm.vm_id == vm_id)
+                        .map(|vm| vm.name.clone())
+                        .unwrap_or(vm_id)
+                })
+                .collect();
+            state.datastore_vms.sort();
+        }
+        Err(e) => {
+            state.status_msg = format!("Error loading the vms of {}: {}", datastore.name, e);
+        }
+    }
+}
+
+//folders or resource pools of the datacenter with the vms in each of them
+fn load_groups(api: &VcenterApi, state: &mut State) -> Result<(), ApiError> {
+    state.groups.clear();
+    let datacenter = state.datacenter.as_ref().map(|datacenter| datacenter.datacenter.clone());
+    let (groups, none) = match state.grouping {
+        Grouping::Host => return Ok(()),
+        Grouping::Folder => {
+            let folders = API::get_vm_folders(api.clone(), datacenter)?;
+            (folders.into_iter().map(|folder| (folder.folder, folder.name)).collect::<Vec<(String, String)>>(), "No folder")
+        }
+        Grouping::ResourcePool => {
+            let pools = API::get_resource_pools(api.clone(), datacenter)?;
+            (pools.into_iter().map(|pool| (pool.resource_pool, pool.name)).collect::<Vec<(String, String)>>(), "No resource pool")
+        }
+    };
+
+    let mut found = Vec::new();
+    for (id, name) in groups {
+        let filter = match state.grouping {
+            Grouping::Folder => VmFilter { folders: vec![id.clone()], ..VmFilter::default() },
+            _ => VmFilter { resource_pools: vec![id.clone()], ..VmFilter::default() },
+        };
+        let vms = API::list_vms(api.clone(), &filter)?.into_iter().map(|vm| vm.vm).collect();
+        found.push(VmGroup { id, name, vms });
+    }
+
+    //a vm listed in nested groups stays in the smallest one, the one closest to it
+    let mut order: Vec<usize> = (0..found.len()).collect();
+    order.sort_by_key(|&index| found[index].vms.len());
+    let mut placed = HashSet::new();
+    order.into_iter().for_each(|index| found[index].vms.retain(|vm_id| placed.insert(vm_id.clone())));
+    found.retain(|group| !group.vms.is_empty());
+    found.sort_by(|a, b| a.name.cmp(&b.name));
+
+    let rest: Vec<String> = state.hosts
+        .iter()
+        .flat_map(|host| host.cpy_vms.iter())
+        .filter(|vm| !placed.contains(&vm.vm_id))
+        .map(|vm| vm.vm_id.clone())
+        .collect();
+    if !rest.is_empty() {
+        found.push(VmGroup { id: "ungrouped".to_string(), name: none.to_string(), vms: rest });
+    }
+    state.groups = found;
+    Ok(())
+}
+
+//what a host action does on the host under the cursor, maintenance and connect toggle with the host state
+fn host_action_label(host: &Host, action: char) -> &'static str {
+    match action {
+        'm' if host.maintenance == Some(true) => "Exit maintenance mode",
+        'm' => "Enter maintenance mode",
+        'b' => "Reboot",
+        'x' => "Shut down",
+        'c' if host.connection_state == "DISCONNECTED" => "Reconnect",
+        _ => "Disconnect",
+    }
+}
+
+//run the confirmed action on the host under the cursor in the background, maintenance mode can take minutes
+fn host_action(api: &VcenterApi, state: &mut State, action: char, host_tasks: Sender<HostUpdate>) {
+    let Some(index) = state.host_index() else {
+        return;
+    };
+    let host = &state.hosts[index];
+    if let Some(running) = state.host_tasks.get(&host.ip) {
+        state.status_msg = format!("{} is still running on {}", running, host.name);
+        return;
+    }
+    let label = host_action_label(host, action);
+    let in_maintenance = host.maintenance == Some(true);
+    let disconnected = host.connection_state == "DISCONNECTED";
+    let id = host.ip.clone();
+    state.status_msg = format!("{} on {}...", label, host.name);
+    state.host_tasks.insert(id.clone(), label);
+    let api = api.clone();
+    thread::spawn(move || {
+        let result = match action {
+            'm' if in_maintenance => API::exit_maintenance(api, id.clone()),
+            'm' => API::enter_maintenance(api, id.clone()),
+            'b' => API::reboot_host(api, id.clone()),
+            'x' => API::shutdown_host(api, id.clone()),
+            'c' if disconnected => API::connect_host(api, id.clone()),
+            _ => API::disconnect_host(api, id.clone()),
+        };
+        //the receiver is gone when the app already exited
+        let _ = host_tasks.send(HostUpdate { host: id, action, in_maintenance, disconnected, result: result.map_err(|e| e.to_string()) });
+    });
+}
+
+//the host is only updated once its task ended
+fn apply_host_update(state: &mut State, update: HostUpdate) {
+    let label = state.host_tasks.remove(&update.host).unwrap_or_default();
+    let Some(host) = state.hosts.iter_mut().find(|host| host.ip == update.host) else {
+        return;
+    };
+    match update.result {
+        Ok(()) => {
+            match update.action {
+                'm' => host.maintenance = Some(!update.in_maintenance),
+                'x' => host.state = "POWERED_OFF".to_string(),
+                'c' if update.disconnected => host.connection_state = "CONNECTED".to_string(),
+                'c' => host.connection_state = "DISCONNECTED".to_string(),
+                _ => {}
+            }
+            state.status_msg = format!("{} done on {}", label, host.name);
+        }
+        Err(e) => {
+            state.status_msg = format!("Error While running {} on {}: {}", label.to_lowercase(), host.name, e);
+        }
+    }
+}
+
+//nics of the vm, taken from its cached full info so the cursor costs one request per vm
+fn vm_nics(api: &VcenterApi, state: &mut State, vm_id: &str) -> Result<Vec<VapiNic>, ApiError> {
+    Ok(vm_info(api, state, vm_id)?.nics.into_values().collect())
+}
+
+//cd-roms of the vm, from its cached full info as the nics
+fn vm_cdroms(api: &VcenterApi, state: &mut State, vm_id: &str) -> Result<Vec<VapiCdrom>, ApiError> {
+    Ok(vm_info(api, state, vm_id)?.cdroms.into_values().collect())
+}
+
+//iso file or device behind the cd-rom
+fn cdrom_backing(cdrom: &VapiCdrom) -> String {
+    cdrom.backing.iso_file.clone()
+        .or_else(|| cdrom.backing.host_device.clone())
+        .unwrap_or_else(|| cdrom.backing.backing_type.clone())
+}
+
+//the choices of the popup are loaded once, a failure is shown in it
+fn open_iso_form(api: &VcenterApi, state: &mut State) {
+    let datacenter = state.datacenter.as_ref().map(|datacenter| datacenter.datacenter.clone());
+    let mut form = IsoForm::default();
+    match API::get_datastores(api.clone(), datacenter) {
+        Ok(mut datastores) => {
+            datastores.sort_by(|a, b| a.name.cmp(&b.name));
+            form.datastores = datastores;
+        }
+        Err(e) => form.error = format!("Error loading the datastores: {}", e),
+    }
+    match library_items(api, &["iso"]) {
+        Ok(items) => form.items = items,
+        Err(e) => form.error = format!("Error loading the content libraries: {}", e),
+    }
+    state.iso_form = form;
+    state.mode = InputMode::Iso;
+}
+
+//mount or eject the iso of the form on every selected vm, the popup stays open while the form is not complete
+fn iso_selected(api: &VcenterApi, state: &mut State) {
+    let form = &mut state.iso_form;
+    let image = match form.action {
+        0 => match form.datastores.get(form.datastore) {
+            Some(_) if form.path.trim().is_empty() => {
+                form.error = "Type the path of the ISO in the datastore".to_string();
+                return;
+            }
+            Some(datastore) => Some(IsoImage::Datastore(format!("[{}] {}", datastore.name, form.path.trim()))),
+            None => {
+                form.error = "There is no datastore to take the ISO from".to_string();
+                return;
+            }
+        },
+        1 => match form.items.get(form.item) {
+            Some((_, item)) => Some(IsoImage::Library(item.id.clone())),
+            None => {
+                form.error = "There is no ISO in the content libraries".to_string();
+                return;
+            }
+        },
+        _ => None,
+    };
+
+    let vms = state.hosts.iter()
+        .flat_map(|host| host.vms.iter())
+        .filter(|vm| vm.selected)
+        .map(|vm| (vm.vm_id.clone(), vm.state == "POWERED_ON"))
+        .collect::<Vec<(String, bool)>>();
+    let mut errors = HashMap::new();
+    for (vm_id, powered_on) in &vms {
+        //the cd-roms are read again, the cached ones may be old
+        state.vm_details.remove(vm_id);
+        let result = vm_cdroms(api, state, vm_id).and_then(|cdroms| match &image {
+            Some(image) => API::mount_iso(api.clone(), vm_id.clone(), &cdroms, image, *powered_on),
+            None => API::eject_iso(api.clone(), vm_id.clone(), &cdroms),
+        });
+        if let Err(e) = result {
+            let verb = if image.is_some() { "Mounting" } else { "Ejecting" };
+            errors.insert(vm_id.clone(), format!("Error While {} iso: {}", verb, e));
+        }
+        state.vm_details.remove(vm_id);
+    }
+
+    finish_selected(state, &errors);
+    if !errors.is_empty() {
+        state.status_msg = format!("ISO changed on {} of {} VMs", vms.len() - errors.len(), vms.len());
+    }
+    load_vm_details(api, state);
+    state.mode = InputMode::ListVM;
+}
+
+//the selected vms where the action failed get its error, the others are unselected
+fn finish_selected(state: &mut State, errors: &HashMap<String, String>) {
+    state.hosts.iter_mut().flat_map(|host| host.vms.iter_mut()).filter(|vm| vm.selected).for_each(|vm| {
+        match errors.get(&vm.vm_id) {
+            Some(e) => {
+                vm.error = true;
+                vm.error_msg = e.clone();
+            }
+            None => {
+                vm.error = false;
+                vm.selected = false;
+            }
+        }
+    });
+}
+
+//disconnect every nic of the selected vms and keep them disconnected at power on
+//nics are disconnected first, cutting the vm off is what matters the most
+fn isolate_selected(api: &VcenterApi, state: &mut State) {
+    let vms = state.hosts.iter()
+        .flat_map(|host| host.vms.iter())
+        .filter(|vm| vm.selected)
+        .map(|vm| vm.vm_id.clone())
+        .collect::<Vec<String>>();
+    let mut errors = HashMap::new();
+    for vm_id in &vms {
+        state.vm_details.remove(vm_id);
+        let result = vm_nics(api, state, vm_id).and_then(|nics| {
+            //isolating twice keeps the nics as they were before the first time
+            state.isolated.entry(vm_id.clone()).or_insert_with(|| {
+                nics.iter().map(|nic| (nic.nic.clone(), nic.state == "CONNECTED", nic.start_connected)).collect()
+            });
+            nics.iter().filter(|nic| nic.state == "CONNECTED").try_for_each(|nic| API::disconnect_nic(api.clone(), vm_id.clone(), nic.nic.clone()))?;
+            nics.iter().filter(|nic| nic.start_connected).try_for_each(|nic| API::set_nic_start_connected(api.clone(), vm_id.clone(), nic.nic.clone(), false))
+        });
+        if let Err(e) = result {
+            errors.insert(vm_id.clone(), format!("Error While Isolating vm: {}", e));
+        }
+        state.vm_details.remove(vm_id);
+    }
+    finish_selected(state, &errors);
+    if !errors.is_empty() {
+        state.status_msg = format!("Isolated {} of {} VMs", vms.len() - errors.len(), vms.len());
+    }
+    save_isolated(api, state);
+    load_vm_details(api, state);
+}
+
+//give back to the selected isolated vms the nics they had, powered off vms only get start connected back
+fn undo_isolate(api: &VcenterApi, state: &mut State) {
+    let vms = state.hosts.iter()
+        .flat_map(|host| host.vms.iter())
+        .filter(|vm| vm.selected && state.isolated.contains_key(&vm.vm_id))
+        .map(|vm| (vm.vm_id.clone(), vm.state == "POWERED_ON"))
+        .collect::<Vec<(String, bool)>>();
+    let mut errors = HashMap::new();
+    for (vm_id, powered_on) in &vms {
+        let nics = state.isolated.get(vm_id).cloned().unwrap_or_default();
+        let result = nics.iter().try_for_each(|(nic, connected, start_connected)| -> Result<(), ApiError> {
+            if *start_connected {
+                API::set_nic_start_connected(api.clone(), vm_id.clone(), nic.clone(), true)?;
+            }
+            if *connected && *powered_on {
+                API::connect_nic(api.clone(), vm_id.clone(), nic.clone())?;
+            }
+            Ok(())
+        });
+        match result {
+            Ok(()) => {
+                state.isolated.remove(vm_id);
+            }
+            Err(e) => {
+                errors.insert(vm_id.clone(), format!("Error While Reconnecting vm: {}", e));
+            }
+        }
+        state.vm_details.remove(vm_id);
+    }
+    finish_selected(state, &errors);
+    if !errors.is_empty() {
+        state.status_msg = format!("Reconnected {} of {} VMs", vms.len() - errors.len(), vms.len());
+    }
+    save_isolated(api, state);
+    load_vm_details(api, state);
+}
+
+//isolated vms of every vcenter in the file, a missing or broken file means none
+fn read_isolated() -> HashMap<String, IsolatedNics> {
+    std::fs::read_to_string(ISOLATED_FILE)
+        .ok()
+        .and_then(|text| serde_json::from_str(&text).ok())
+        .unwrap_or_default()
+}
+
+fn load_isolated(hostname: &str) -> IsolatedNics {
+    read_isolated().remove(hostname).unwrap_or_default()
+}
+
+//the isolated vms of the other vcenters in the file are kept
+fn save_isolated(api: &VcenterApi, state: &mut State) {
+    let mut vcenters = read_isolated();
+    if state.isolated.is_empty() {
+        vcenters.remove(&api.hostname);
+    } else {
+        vcenters.insert(api.hostname.clone(), state.isolated.clone());
+    }
+    let result = serde_json::to_string_pretty(&vcenters)
+        .map_err(std::io::Error::from)
+        .and_then(|text| std::fs::write(ISOLATED_FILE, text));
+    if let Err(e) = result {
+        state.status_msg = format!("Error saving {}, the undo will not survive a restart: {}", ISOLATED_FILE, e);
+    }
+}
+
+//the network choice starts on the network of the first nic
+fn open_nic_form(api: &VcenterApi, state: &mut State) {
+    let Some(vm_id) = state.current_vm().map(|vm| vm.vm_id.clone()) else {
+        return;
+    };
+    let datacenter = state.datacenter.as_ref().map(|datacenter| datacenter.datacenter.clone());
+    let result = vm_nics(api, state, &vm_id).and_then(|nics| Ok((nics, API::get_networks(api.clone(), datacenter)?)));
+    match result {
+        Ok((nics, _)) if nics.is_empty() => {
+            if let Some(vm) = state.current_vm_mut() {
+                vm.error = true;
+                vm.error_msg = "The vm has no nic".to_string();
+            }
+        }
+        Ok((nics, mut networks)) => {
+            networks.sort_by(|a, b| a.name.cmp(&b.name));
+            let network = networks.iter().position(|network| nics[0].backing.network.as_ref() == Some(&network.network)).unwrap_or_default();
+            state.nic_form = NicForm { nics, networks, network, ..NicForm::default() };
+            state.mode = InputMode::MoveNic;
+        }
+        Err(e) => {
+            if let Some(vm) = state.current_vm_mut() {
+                vm.error = true;
+                vm.error_msg = format!("Error While Loading nics: {}", e);
+            }
+        }
+    }
+}
+
+fn nic_form_submit(api: &VcenterApi, state: &mut State) {
+    let Some(vm_id) = state.current_vm().map(|vm| vm.vm_id.clone()) else {
+        return;
+    };
+    let form = &mut state.nic_form;
+    let (Some(nic), Some(network)) = (form.nics.get(form.nic), form.networks.get(form.network)) else {
+        form.error = "There is no network to move the nic to".to_string();
+        return;
+    };
+    match API::move_nic(api.clone(), vm_id.clone(), nic.nic.clone(), network) {
+        Ok(()) => {
+            state.vm_details.remove(&vm_id);
+            load_vm_details(api, state);
+            state.mode = InputMode::ListVM;
+        }
+        Err(e) => form.error = format!("Error While Moving nic: {}", e),
+    }
+}
+
+//vms and nics attached to the network under the cursor, the nics of a vm are only fetched
+//when its info is already cached or when fetch is set, a network can have hundreds of vms
+fn load_network_nics(api: &VcenterApi, state: &mut State, fetch: bool) {
+    state.network_nics.clear();
+    let Some(network) = state.networks.get(state.network_cursor).cloned() else {
+        return;
+    };
+    let result = API::get_network_vms(api.clone(), network.network.clone()).and_then(|vms| {
+        let mut attached = Vec::new();
+        for vm_id in vms {
+            let name = state.hosts
+                .iter()
+                .flat_map(|host| host.cpy_vms.iter())
+                .find(|vm| vm.vm_id == vm_id)
+                .map(|vm| vm.name.clone())
+                .unwrap_or_else(|| vm_id.clone());
+            if !fetch && !state.vm_details.contains_key(&vm_id) {
+                attached.push((name, None));
+                continue;
+            }
+            vm_nics(api, state, &vm_id)?
+                .into_iter()
+                .filter(|nic| nic.backing.network.as_ref() == Some(&network.network))
+                .for_each(|nic| attached.push((name.clone(), Some(nic))));
+        }
+        Ok(attached)
+    });
+    match result {
+        Ok(mut attached) => {
+            attached.sort_by(|a, b| a.0.cmp(&b.0));
+            state.network_nics = attached;
+        }
+        Err(e) => {
+            state.status_msg = format!("Error loading the vms of {}: {}", network.name, e);
+        }
+    }
+}
+
+//name of the network a nic is attached to, the id when it is not known
+fn nic_network(state: &State, nic: &VapiNic) -> String {
+    nic.backing.network_name.clone()
+        .or_else(|| {
+            let id = nic.backing.network.as_ref()?;
+            state.networks.iter().find(|network| &network.network == id).map(|network| network.name.clone()).or(Some(id.clone()))
+        })
+        .unwrap_or_else(|| nic.backing.backing_type.clone())
+}
+
+//percent of the datastore in use
+fn datastore_usage(datastore: &VapiDatastore) -> u64 {
+    if datastore.capacity == 0 {
+        return 0;
+    }
+    (datastore.capacity - datastore.free_space.min(datastore.capacity)) * 100 / datastore.capacity
+}
+
+//bytes to GiB with one decimal
+fn gib(bytes: u64) -> String {
+    format!("{:.1} GiB", bytes as f64 / 1024f64.powi(3))
+}
+
+//color used to show if a nic is connected
+fn nic_state_color(state: &str) -> Color {
+    match state {
+        "CONNECTED" => Color::LightGreen,
+        _ => Color::LightRed,
+    }
+}
+
+//color used to show a power state
+fn state_color(state: &str) -> Color {
+    match state {
+        "POWERED_ON" => Color::LightGreen,
+        "SUSPENDED" | "SHUTTING_DOWN" => Color::LightYellow,
+        _ => Color::LightRed,
+    }
+}
+
+fn search(state : &mut State) {
+
+    state.hosts.iter_mut().for_each(|host| {
+        host.cpy_vms.iter_mut().for_each(|vm| {
+            //the tag filter applies on top of the name search
+            let tagged = match &state.tag_filter {
+                Some(tag) => state.vm_tags.get(&vm.vm_id).is_some_and(|tags| tags.contains(tag)),
+                None => true,
+            };
+            vm.delete = !(vm.name.to_lowercase().contains(&state.search_string.to_lowercase()) && tagged);
+        });
+    });
+
+
+
+}
+
+fn delete(state : &mut State) {
+    state.hosts.iter_mut().for_each(|host| {
+        //create a temporal copy of the vms
+        let mut vms = host.cpy_vms.clone();
+        //delete the vms that are marked as delete
+        vms.retain(|vm| !vm.delete);
+        //replace the vms with the new vms
+        if !vms.is_empty() {
+            host.vms = vms;
+        }
+    });
+    state.clamp_cursor();
+}
+
+
+fn load_crendetials() -> Credentials {
+    //open the file credentials.xml who is in the src folder
+    let file = File::open("src/credentials.json").unwrap();
+    let reader = BufReader::new(file);
+    let credentials: Credentials = serde_json::from_reader(reader).unwrap();
+
+    //return the credentials
+
+    credentials
+
+}
+
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let credentials = load_crendetials();
+    let mut config = credentials.connection_config();
+
+    //a certificate trusted before is only pinned when the configured verification fails,
+    //a changed one is shown again and replaces the saved one
+    if let CertificateCheck::Unknown { fingerprint } = API::check_certificate(&credentials.ip, &config)? {
+        let known = API::known_host(Path::new(KNOWN_HOSTS_FILE), &credentials.ip);
+        if !known.as_ref().is_some_and(|known| same_fingerprint(known, &fingerprint)) {
+            if !ask_trust_certificate(&credentials.ip, &fingerprint, known.as_deref())? {
+                return Err(format!("Certificate of {} is not trusted", credentials.ip).into());
+            }
+            API::trust_host(Path::new(KNOWN_HOSTS_FILE), &credentials.ip, &fingerprint)?;
+        }
+        config.tls = TlsMode::Pinned(fingerprint);
+    }
+
+    let api = API::new_api(credentials.ip.clone(), config)?;
+
+
+
+    //run API main func
+    API::authenticate(api.clone()
+                        ,credentials.user.clone()
+                        ,credentials.password.clone()
+    )?;
+
+    let mut state = State::new();
+    state.isolated = load_isolated(&credentials.ip);
+    if let Some(secs) = credentials.guest_shutdown_timeout {
+        state.shutdown_timeout = Duration::from_secs(secs);
+    }
+    if let Some(percent) = credentials.datastore_threshold {
+        state.datastore_threshold = percent;
+    }
+
+    //an empty datacenter in credentials.json shows the hosts of every datacenter
+    if !credentials.datacenter.is_empty() {
+        match API::get_datacenter(api.clone(), credentials.datacenter.clone()) {
+            Ok(datacenter) => state.datacenter = Some(datacenter),
+            Err(e) => {
+                let _ = API::logout(api);
+                return Err(e.into());
+            }
+        }
+    }
+
+    if let Err(e) = load_inventory(&api, &mut state) {
+        let _ = API::logout(api);
+        return Err(e.into());
+    }
+
+    enable_raw_mode()?;
+    execute!(std::io::stdout(), EnableMouseCapture, EnterAlternateScreen)?;
+
+
+
+
+    let backend = tui::backend::CrosstermBackend::new(std::io::stdout());
+    let mut terminal = Terminal::new(backend)?;
+    let result = run_app(&mut terminal,api.clone(),&mut state, Duration::from_millis(100));
+
+    disable_raw_mode()?;
+
+    execute!(terminal.backend_mut(), DisableMouseCapture,LeaveAlternateScreen)?;
+
+    if let Err(e) = result {
+        println!("{}", e);
+    }
+
+    //close the session so it does not stay open on vcenter
+    if let Err(e) = API::logout(api) {
+        println!("Error closing the session: {}", e);
+    }
+
+    Ok(())
+
+
+}
+
+//small terminal session before the login asking if an unknown certificate should be trusted
+fn ask_trust_certificate(hostname: &str, fingerprint: &str, known: Option<&str>) -> Result<bool, Box<dyn Error>> {
+    enable_raw_mode()?;
+    execute!(std::io::stdout(), EnterAlternateScreen)?;
+    let backend = tui::backend::CrosstermBackend::new(std::io::stdout());
+    let mut terminal = Terminal::new(backend)?;
+
+    let answer = loop {
+        terminal.draw(|f| trust_popup(f, hostname, fingerprint, known))?;
+        if let Event::Key(key) = event::read()? {
+            match key.code {
+                KeyCode::Char('y') => break true,
+                KeyCode::Char('n') | KeyCode::Esc => break false,
+                _ => {}
+            }
+        }
+    };
+
+    disable_raw_mode()?;
+    execute!(terminal.backend_mut(), LeaveAlternateScreen)?;
+    Ok(answer)
+}
+
+fn run_app<B: Backend>(
+    terminal: &mut Terminal<B>,
+    api: VcenterApi,
+    state: &mut State,
+    tick_rate: Duration,
+)
+    -> Result<(), std::io::Error> {
+
+    let mut last_tick = Instant::now();
+    let (updates, finished) = mpsc::channel();
+    let (deploys, deployed) = mpsc::channel();
+    let (clones, cloned) = mpsc::channel();
+    let (host_tasks, host_done) = mpsc::channel();
+    let (snapshots, snapshotted) = mpsc::channel();
+    let (disk_tasks, disks_done) = mpsc::channel();
+
+    loop {
+        while let Ok(update) = finished.try_recv() {
+            apply_power_update(state, update);
+        }
+        while let Ok(update) = deployed.try_recv() {
+            apply_provision_update(&api, state, update);
+        }
+        while let Ok(update) = cloned.try_recv() {
+            apply_clone_update(&api, state, update);
+        }
+        while let Ok(update) = host_done.try_recv() {
+            apply_host_update(state, update);
+        }
+        while let Ok(update) = snapshotted.try_recv() {
+            apply_snapshot_update(&api, state, update);
+        }
+        while let Ok(update) = disks_done.try_recv() {
+            apply_disk_update(&api, state, update);
+        }
+        terminal.draw(|f| ui(f, state))?;
+
+        let timeout = tick_rate
+            .checked_sub(last_tick.elapsed())
+            .unwrap_or_else(|| Duration::from_secs(0));
+
+        if poll(timeout)? {
+            if let Event::Key(key) = event::read()? {
+                match state.mode {
+                    InputMode::Normal => {
+                        match key.code {
+                            KeyCode::Esc => {
+                                disable_raw_mode()?;
+                                return Ok(());
+                            }
+                            KeyCode::Down => {
+                                    state.move_cursor(true, false);
+                            }
+                            KeyCode::Up => {
+                                    state.move_cursor(false, false);
+                            }
+                            KeyCode::Right => {
+                                    let node = state.node();
+                                    state.set_expanded(node, true);
+                            }
+                            //on a vm its host (or group) is collapsed and the cursor goes back to it
+                            KeyCode::Left => {
+                                    match (state.node(), state.parent_node()) {
+                                        (TreeNode::Vm(..), Some(parent)) => {
+                                            state.set_expanded(parent, false);
+                                            state.select_node(parent);
+                                        }
+                                        (node, _) => state.set_expanded(node, false),
+                                    }
+                            }
+                            //a host or group opens on its first vm, the other nodes are expanded or collapsed
+                            KeyCode::Enter => {
+                                match state.node() {
+                                    node @ (TreeNode::Host(_) | TreeNode::Group(_)) => {
+                                        state.set_expanded(node, true);
+                                        if let Some((TreeNode::Vm(..), _)) = state.tree().get(state.cursor + 1) {
+                                            state.cursor += 1;
+                                            state.mode = InputMode::ListVM;
+                                            load_vm_details(&api, state);
+                                        }
+                                    }
+                                    TreeNode::Vm(..) => {
+                                        state.mode = InputMode::ListVM;
+                                        load_vm_details(&api, state);
+                                    }
+                                    node => {
+                                        let expanded = state.expanded(node);
+                                        state.set_expanded(node, !expanded);
+                                    }
+                                }
+                            }
+
+                            KeyCode::Char('s') => {
+                                state.mode = InputMode::Search;
+                            }
+
+                            KeyCode::Char('f') => {
+                                state.mode = InputMode::File;
+                            }
+                            KeyCode::Char('n') => {
+                                match open_create_form(&api, state) {
+                                    Ok(()) => state.mode = InputMode::CreateVm,
+                                    Err(e) => state.status_msg = format!("Error loading the new vm choices: {}", e),
+                                }
+                            }
+                            KeyCode::Char('d') => {
+                                match API::get_datacenters(api.clone()) {
+                                    Ok(datacenters) => {
+                                        let current = state.datacenter.as_ref().map(|datacenter| datacenter.datacenter.clone());
+                                        state.datacenter_cursor = datacenters
+                                            .iter()
+                                            .position(|datacenter| Some(&datacenter.datacenter) == current.as_ref())
+                                            .unwrap_or(0);
+                                        state.datacenters = datacenters;
+                                        state.mode = InputMode::Datacenters;
+                                    }
+                                    Err(e) => {
+                                        state.status_msg = format!("Error loading the datacenters: {}", e);
+                                    }
+                                }
+                            }
+                            KeyCode::Char('g') => {
+                                let datacenter = state.datacenter.as_ref().map(|datacenter| datacenter.datacenter.clone());
+                                match API::get_datastores(api.clone(), datacenter) {
+                                    Ok(mut datastores) => {
+                                        datastores.sort_by(|a, b| a.name.cmp(&b.name));
+                                        state.datastores = datastores;
+                                        state.datastore_cursor = 0;
+                                        state.datastore_vms.clear();
+                                        state.mode = InputMode::Datastores;
+                                    }
+                                    Err(e) => {
+                                        state.status_msg = format!("Error loading the datastores: {}", e);
+                                    }
+                                }
+                            }
+                            KeyCode::Char('w') => {
+                                let datacenter = state.datacenter.as_ref().map(|datacenter| datacenter.datacenter.clone());
+                                match API::get_networks(api.clone(), datacenter) {
+                                    Ok(mut networks) => {
+                                        networks.sort_by(|a, b| a.name.cmp(&b.name));
+                                        state.networks = networks;
+                                        state.network_cursor = 0;
+                                        state.network_nics.clear();
+                                        state.mode = InputMode::Networks;
+                                    }
+                                    Err(e) => {
+                                        state.status_msg = format!("Error loading the networks: {}", e);
+                                    }
+                                }
+                            }
+                            KeyCode::Char('v') => {
+                                state.grouping = match state.grouping {
+                                    Grouping::Host => Grouping::Folder,
+                                    Grouping::Folder => Grouping::ResourcePool,
+                                    Grouping::ResourcePool => Grouping::Host,
+                                };
+                                state.cursor = 0;
+                                if let Err(e) = load_groups(&api, state) {
+                                    state.status_msg = format!("Error loading the groups: {}", e);
+                                    state.grouping = Grouping::Host;
+                                }
+                            }
+                            KeyCode::Char('l') => {
+                                    state.tag_confirm = None;
+                                    state.tag_return = InputMode::Normal;
+                                    state.mode = InputMode::Tags;
+                            }
+                            KeyCode::Char('c') => {
+                                match load_library_items(&api, state) {
+                                    Ok(()) => state.mode = InputMode::Library,
+                                    Err(e) => state.status_msg = format!("Error loading the content libraries: {}", e),
+                                }
+                            }
+                            KeyCode::Char('a') if state.host_index().is_some() => {
+                                state.host_action = None;
+                                state.mode = InputMode::HostActions;
+                            }
+                            KeyCode::F(5) => {
+                                state.status_msg = "".to_string();
+                                if let Err(e) = load_inventory(&api, state) {
+                                    state.status_msg = format!("Error reloading the inventory: {}", e);
+                                }
+                                search(state);
+                                delete(state);
+                            }
+                            _ => {
+                            }
+                        }
+                    }
+                    InputMode::ListVM => {
+                        match key.code {
+
+                            KeyCode::Char('h') => {
+                                state.mode = InputMode::Normal;
+                            }
+                            //up and down jump over the hosts and clusters to the next vm of the tree
+                            KeyCode::Up => {
+                                    state.move_cursor(false, true);
+                                    load_vm_details(&api, state);
+                            }
+                            KeyCode::Down => {
+                                    state.move_cursor(true, true);
+                                    load_vm_details(&api, state);
+                            }
+                            KeyCode::Char(' ') => {
+                                    if let Some(vm) = state.current_vm_mut() {
+                                        //edit selected
+                                        vm.selected = !vm.selected;
+                                    }
+                            }
+                            KeyCode::Char('p') => {
+                                    power_selected(state, |vm| poweron_vm(api.clone(), vm.vm_id.clone()), "POWERED_ON", "Error While Powering on vm");
+                            }
+                            KeyCode::Char('o') => {
+                                    guest_power_selected(&api, state, 'o', updates.clone());
+                            }
+                            KeyCode::Char('O') => {
+                                    power_selected(state, |vm| shutdown_vm(api.clone(), vm.vm_id.clone()), "POWERED_OFF", "Error While Powering off vm");
+                            }
+                            KeyCode::Char('r') => {
+                                    guest_power_selected(&api, state, 'r', updates.clone());
+                            }
+                            KeyCode::Char('R') => {
+                                    power_selected(state, |vm| reboot_vm(api.clone(), vm.vm_id.clone()), "POWERED_ON", "Error While Resetting vm");
+                            }
+                            KeyCode::Char('u') => {
+                                    power_selected(state, |vm| suspend_vm(api.clone(), vm.vm_id.clone()), "SUSPENDED", "Error While Suspending vm");
+                            }
+                            KeyCode::Char('t') => {
+                                    state.snapshot_cursor = 0;
+                                    state.snapshot_confirm = None;
+                                    load_snapshots(&api, state);
+                                    state.mode = InputMode::Snapshots;
+                            }
+                            KeyCode::Char('n') if state.snapshot_results.iter().all(|row| row.result.is_some()) => {
+                                    state.snapshot_form = SnapshotForm::default();
+                                    state.mode = InputMode::NewSnapshot;
+                            }
+                            KeyCode::Char('m') if state.hosts.iter().flat_map(|host| host.vms.iter()).any(|vm| vm.selected) => {
+                                    open_iso_form(&api, state);
+                            }
+                            KeyCode::Char('z') if state.hosts.iter().flat_map(|host| host.vms.iter()).any(|vm| vm.selected) => {
+                                    isolate_selected(&api, state);
+                            }
+                            KeyCode::Char('Z') => {
+                                    undo_isolate(&api, state);
+                            }
+                            KeyCode::Char('w') => {
+                                    open_nic_form(&api, state);
+                            }
+                            KeyCode::Char('k') => {
+                                    state.disk_cursor = 0;
+                                    state.disk_form = None;
+                                    state.disk_remove = None;
+                                    load_disks(&api, state);
+                                    state.mode = InputMode::Disks;
+                            }
+                            KeyCode::Char('i') => {
+                                    state.show_details = !state.show_details;
+                                    if state.show_details {
+                                        load_vm_details(&api, state);
+                                    }
+                            }
+                            KeyCode::Char('l') => {
+                                    state.tag_confirm = None;
+                                    state.tag_return = InputMode::ListVM;
+                                    state.mode = InputMode::Tags;
+                            }
+                            //a new clone action waits for the copies of the last one
+                            KeyCode::Char('c') if state.current_vm().is_some() && state.clone_results.iter().all(|row| row.result.is_some()) => {
+                                    let name = state.current_vm().map(|vm| vm.name.clone()).unwrap_or_default();
+                                    state.clone_form = CloneForm { pattern: format!("{}-{{01..02}}", name), ..CloneForm::default() };
+                                    state.mode = InputMode::Clone;
+                            }
+                            KeyCode::Char('e') if state.hosts.iter().flat_map(|host| host.vms.iter()).any(|vm| vm.selected) => {
+                                    open_resize_form(&api, state);
+                            }
+                            KeyCode::Char('x') => {
+                                    let vms = state.hosts.iter()
+                                        .flat_map(|host| host.vms.iter())
+                                        .filter(|vm| vm.selected)
+                                        .map(|vm| (vm.vm_id.clone(), vm.name.clone(), vm.state.clone()))
+                                        .collect::<Vec<(String, String, String)>>();
+                                    if !vms.is_empty() {
+                                        state.delete_form = DeleteForm { vms, ..DeleteForm::default() };
+                                        state.mode = InputMode::DeleteVm;
+                                    }
+                            }
+                            _ => {
+                            }
+                        }
+                    }
+                    InputMode::DeleteVm => {
+                        let form = &mut state.delete_form;
+                        //nothing is deleted until the phrase is typed and the powered on vms may be powered off
+                        let ready = form.typed == form.phrase() && (form.powered_on() == 0 || form.power_off);
+                        match key.code {
+                            KeyCode::Esc => {
+                                    state.mode = InputMode::ListVM;
+                            }
+                            KeyCode::Tab if form.powered_on() > 0 => {
+                                    form.power_off = !form.power_off;
+                            }
+                            KeyCode::Enter if ready => {
+                                    delete_selected(&api, state);
+                            }
+                            KeyCode::Char(c) => {
+                                    form.typed.push(c);
+                            }
+                            KeyCode::Backspace => {
+                                    form.typed.pop();
+                            }
+                            _ => {
+                            }
+                        }
+                    }
+                    InputMode::Resize => {
+                        let form = &mut state.resize_form;
+                        let text = match form.field {
+                            0 => Some(&mut form.cpu),
+                            1 => Some(&mut form.cores_per_socket),
+                            2 => Some(&mut form.memory_mib),
+                            _ => None,
+                        };
+                        match key.code {
+                            KeyCode::Esc => {
+                                    state.mode = InputMode::ListVM;
+                            }
+                            KeyCode::Enter => {
+                                match resize_updates(form) {
+                                    Ok((cpu, memory)) => {
+                                        resize_selected(&api, state, &cpu, &memory);
+                                        state.mode = InputMode::ListVM;
+                                    }
+                                    Err(e) => form.error = e,
+                                }
+                            }
+                            KeyCode::Up => {
+                                    form.field = form.field.saturating_sub(1);
+                            }
+                            KeyCode::Down | KeyCode::Tab if form.field < 5 => {
+                                    form.field += 1;
+                            }
+                            //the hot add flags go from Keep to On to Off
+                            KeyCode::Char(' ') if form.field >= 3 => {
+                                    let flag = match form.field {
+                                        3 => &mut form.cpu_hot_add,
+                                        4 => &mut form.cpu_hot_remove,
+                                        _ => &mut form.memory_hot_add,
+                                    };
+                                    *flag = match *flag {
+                                        None => Some(true),
+                                        Some(true) => Some(false),
+                                        Some(false) => None,
+                                    };
+                            }
+                            KeyCode::Char(c) if c.is_ascii_digit() => {
+                                    if let Some(text) = text {
+                                        text.push(c);
+                                        form.error = "".to_string();
+                                    }
+                            }
+                            KeyCode::Backspace => {
+                                    if let Some(text) = text {
+                                        text.pop();
+                                        form.error = "".to_string();
+                                    }
+                            }
+                            _ => {
+                            }
+                        }
+                    }
+                    InputMode::Disks => {
+                        let label = state.disks.get(state.disk_cursor).map(|disk| disk.label.clone()).unwrap_or_default();
+                        if let Some(typed) = state.disk_remove.as_mut() {
+                            //the disk goes only once its label is typed
+                            match key.code {
+                                KeyCode::Esc => {
+                                        state.disk_remove = None;
+                                }
+                                KeyCode::Enter if *typed == label => {
+                                        state.disk_remove = None;
+                                        remove_disk(&api, state, disk_tasks.clone());
+                                }
+                                KeyCode::Char(c) => {
+                                        typed.push(c);
+                                }
+                                KeyCode::Backspace => {
+                                        typed.pop();
+                                }
+                                _ => {
+                                }
+                            }
+                        } else if let Some(form) = state.disk_form.as_mut() {
+                            let fields = if form.grow { 1 } else { 3 };
+                            match key.code {
+                                KeyCode::Esc => {
+                                        state.disk_form = None;
+                                }
+                                KeyCode::Enter => {
+                                        disk_form_submit(&api, state, disk_tasks.clone());
+                                }
+                                KeyCode::Up => {
+                                        form.field = form.field.saturating_sub(1);
+                                }
+                                KeyCode::Down | KeyCode::Tab if form.field + 1 < fields => {
+                                        form.field += 1;
+                                }
+                                KeyCode::Right if form.field == 1 && !form.datastores.is_empty() => {
+                                        form.datastore = (form.datastore + 1) % form.datastores.len();
+                                }
+                                KeyCode::Left if form.field == 1 && !form.datastores.is_empty() => {
+                                        form.datastore = (form.datastore + form.datastores.len() - 1) % form.datastores.len();
+                                }
+                                KeyCode::Right if form.field == 2 => {
+                                        form.provisioning = (form.provisioning + 1) % DISK_PROVISIONING.len();
+                                }
+                                KeyCode::Left if form.field == 2 => {
+                                        form.provisioning = (form.provisioning + DISK_PROVISIONING.len() - 1) % DISK_PROVISIONING.len();
+                                }
+                                KeyCode::Char(c) if form.field == 0 && c.is_ascii_digit() => {
+                                        form.size_gib.push(c);
+                                }
+                                KeyCode::Backspace if form.field == 0 => {
+                                        form.size_gib.pop();
+                                }
+                                _ => {
+                                }
+                            }
+                        } else {
+                            match key.code {
+                                KeyCode::Esc => {
+                                        state.mode = InputMode::ListVM;
+                                }
+                                KeyCode::Up if state.disk_cursor > 0 => {
+                                        state.disk_cursor -= 1;
+                                }
+                                KeyCode::Down if state.disk_cursor + 1 < state.disks.len() => {
+                                        state.disk_cursor += 1;
+                                }
+                                //one disk task at a time, the disks are reloaded once it ended
+                                KeyCode::Char('a') if state.disk_task.is_none() => {
+                                        open_disk_form(&api, state, false);
+                                }
+                                KeyCode::Char('g') if !state.disks.is_empty() && state.disk_task.is_none() => {
+                                        open_disk_form(&api, state, true);
+                                }
+                                KeyCode::Char('d') if !state.disks.is_empty() && state.disk_task.is_none() => {
+                                        state.disk_remove = Some("".to_string());
+                                }
+                                _ => {
+                                }
+                            }
+                        }
+                    }
+                    InputMode::Iso => {
+                        let form = &mut state.iso_form;
+                        //the datastore iso has a datastore and a path, the library iso an item, the eject nothing
+                        let fields = [3, 2, 1][form.action];
+                        match key.code {
+                            KeyCode::Esc => {
+                                    state.mode = InputMode::ListVM;
+                            }
+                            KeyCode::Enter => {
+                                    iso_selected(&api, state);
+                            }
+                            KeyCode::Up => {
+                                    form.field = form.field.saturating_sub(1);
+                            }
+                            KeyCode::Down | KeyCode::Tab if form.field + 1 < fields => {
+                                    form.field += 1;
+                            }
+                            KeyCode::Right | KeyCode::Left if form.field == 0 => {
+                                    form.action = if key.code == KeyCode::Right { (form.action + 1) % 3 } else { (form.action + 2) % 3 };
+                                    form.error = "".to_string();
+                            }
+                            KeyCode::Right if form.field == 1 && form.action == 0 && !form.datastores.is_empty() => {
+                                    form.datastore = (form.datastore + 1) % form.datastores.len();
+                            }
+                            KeyCode::Left if form.field == 1 && form.action == 0 && !form.datastores.is_empty() => {
+                                    form.datastore = (form.datastore + form.datastores.len() - 1) % form.datastores.len();
+                            }
+                            KeyCode::Right if form.field == 1 && form.action == 1 && !form.items.is_empty() => {
+                                    form.item = (form.item + 1) % form.items.len();
+                            }
+                            KeyCode::Left if form.field == 1 && form.action == 1 && !form.items.is_empty() => {
+                                    form.item = (form.item + form.items.len() - 1) % form.items.len();
+                            }
+                            KeyCode::Char(c) if form.field == 2 => {
+                                    form.path.push(c);
+                            }
+                            KeyCode::Backspace if form.field == 2 => {
+                                    form.path.pop();
+                            }
+                            _ => {
+                            }
+                        }
+                    }
+                    InputMode::MoveNic => {
+                        let form = &mut state.nic_form;
+                        //(choice of the field, number of choices)
+                        let (choice, choices) = if form.field == 0 {
+                            (&mut form.nic, form.nics.len())
+                        } else {
+                            (&mut form.network, form.networks.len())
+                        };
+                        match key.code {
+                            KeyCode::Esc => {
+                                    state.mode = InputMode::ListVM;
+                            }
+                            KeyCode::Enter => {
+                                    nic_form_submit(&api, state);
+                            }
+                            KeyCode::Up => {
+                                    form.field = 0;
+                            }
+                            KeyCode::Down | KeyCode::Tab => {
+                                    form.field = 1;
+                            }
+                            KeyCode::Right if choices > 0 => {
+                                    *choice = (*choice + 1) % choices;
+                            }
+                            KeyCode::Left if choices > 0 => {
+                                    *choice = (*choice + choices - 1) % choices;
+                            }
+                            _ => {
+                            }
+                        }
+                    }
+                    InputMode::Snapshots => {
+                        //a revert or delete waits for 'y', any other key cancels it
+                        if let Some(action) = state.snapshot_confirm.take() {
+                            if key.code == KeyCode::Char('y') {
+                                snapshot_action(&api, state, action, snapshots.clone());
+                            }
+                        } else {
+                            match key.code {
+                                KeyCode::Esc => {
+                                        state.mode = InputMode::ListVM;
+                                }
+                                KeyCode::Up if state.snapshot_cursor > 0 => {
+                                        state.snapshot_cursor -= 1;
+                                }
+                                KeyCode::Down if state.snapshot_cursor + 1 < state.snapshots.len() => {
+                                        state.snapshot_cursor += 1;
+                                }
+                                KeyCode::Char(c @ ('v' | 'd')) if !state.snapshots.is_empty() && state.snapshot_task.is_none() => {
+                                        state.snapshot_confirm = Some(c);
+                                }
+                                _ => {
+                                }
+                            }
+                        }
+                    }
+                    InputMode::NewSnapshot => {
+                        let form = &mut state.snapshot_form;
+                        match key.code {
+                            KeyCode::Esc => {
+                                    state.mode = InputMode::ListVM;
+                            }
+                            KeyCode::Enter if !form.name.is_empty() => {
+                                    snapshot_selected(&api, state, snapshots.clone());
+                            }
+                            KeyCode::Up => {
+                                    form.field = form.field.saturating_sub(1);
+                            }
+                            KeyCode::Down | KeyCode::Tab if form.field < 3 => {
+                                    form.field += 1;
+                            }
+                            //fields 2 and 3 are the memory and quiesce flags
+                            KeyCode::Char(' ') if form.field == 2 => {
+                                    form.memory = !form.memory;
+                            }
+                            KeyCode::Char(' ') if form.field == 3 => {
+                                    form.quiesce = !form.quiesce;
+                            }
+                            KeyCode::Char(c) if form.field == 0 => {
+                                    form.name.push(c);
+                            }
+                            KeyCode::Char(c) if form.field == 1 => {
+                                    form.description.push(c);
+                            }
+                            KeyCode::Backspace if form.field == 0 => {
+                                    form.name.pop();
+                            }
+                            KeyCode::Backspace if form.field == 1 => {
+                                    form.description.pop();
+                            }
+                            _ => {
+                            }
+                        }
+                    }
+                    InputMode::Datacenters => {
+                        match key.code {
+                            KeyCode::Esc => {
+                                    state.mode = InputMode::Normal;
+                            }
+                            KeyCode::Up if state.datacenter_cursor > 0 => {
+                                    state.datacenter_cursor -= 1;
+                            }
+                            KeyCode::Down if state.datacenter_cursor + 1 < state.datacenters.len() => {
+                                    state.datacenter_cursor += 1;
+                            }
+                            KeyCode::Enter if !state.datacenters.is_empty() => {
+                                    state.datacenter = Some(state.datacenters[state.datacenter_cursor].clone());
+                                    state.cursor = 0;
+                                    state.status_msg = "".to_string();
+                                    if let Err(e) = load_inventory(&api, state) {
+                                        state.status_msg = format!("Error loading the datacenter: {}", e);
+                                    }
+                                    search(state);
+                                    delete(state);
+                                    state.mode = InputMode::Normal;
+                            }
+                            _ => {
+                            }
+                        }
+                    }
+                    InputMode::HostActions => {
+                        //the chosen action waits for 'y', any other key cancels it
+                        if let Some(action) = state.host_action.take() {
+                            if key.code == KeyCode::Char('y') {
+                                state.status_msg = "".to_string();
+                                host_action(&api, state, action, host_tasks.clone());
+                            }
+                            state.mode = InputMode::Normal;
+                        } else {
+                            match key.code {
+                                KeyCode::Esc => {
+                                        state.mode = InputMode::Normal;
+                                }
+                                KeyCode::Char(c @ ('m' | 'b' | 'x' | 'c')) => {
+                                        state.host_action = Some(c);
+                                }
+                                _ => {
+                                }
+                            }
+                        }
+                    }
+                    InputMode::Tags => {
+                        //an attach or detach waits for 'y', any other key cancels it
+                        if let Some(action) = state.tag_confirm.take() {
+                            if key.code == KeyCode::Char('y') {
+                                state.status_msg = "".to_string();
+                                tag_selected(&api, state, action);
+                                search(state);
+                                delete(state);
+                            }
+                        } else {
+                            let selected = state.hosts.iter().flat_map(|host| host.vms.iter()).any(|vm| vm.selected);
+                            match key.code {
+                                KeyCode::Esc => {
+                                        state.mode = state.tag_return.clone();
+                                }
+                                KeyCode::Up if state.tag_cursor > 0 => {
+                                        state.tag_cursor -= 1;
+                                }
+                                KeyCode::Down if state.tag_cursor + 1 < state.tags.len() => {
+                                        state.tag_cursor += 1;
+                                }
+                                KeyCode::Enter if !state.tags.is_empty() => {
+                                        state.tag_filter = Some(state.tags[state.tag_cursor].id.clone());
+                                        search(state);
+                                        delete(state);
+                                        state.mode = InputMode::Normal;
+                                }
+                                KeyCode::Char('c') => {
+                                        state.tag_filter = None;
+                                        search(state);
+                                        delete(state);
+                                }
+                                KeyCode::Char(c @ ('a' | 'd')) if selected && !state.tags.is_empty() => {
+                                        state.tag_confirm = Some(c);
+                                }
+                                _ => {
+                                }
+                            }
+                        }
+                    }
+                    InputMode::Clone => {
+                        let form = &mut state.clone_form;
+                        match key.code {
+                            KeyCode::Esc => {
+                                    state.mode = InputMode::ListVM;
+                            }
+                            KeyCode::Enter if !form.pattern.is_empty() => {
+                                    clone_form_submit(&api, state, clones.clone());
+                            }
+                            KeyCode::Up => {
+                                    form.field = 0;
+                            }
+                            KeyCode::Down | KeyCode::Tab => {
+                                    form.field = 1;
+                            }
+                            //field 1 is the power on flag
+                            KeyCode::Char(' ') if form.field == 1 => {
+                                    form.power_on = !form.power_on;
+                            }
+                            KeyCode::Char(c) if form.field == 0 => {
+                                    form.pattern.push(c);
+                            }
+                            KeyCode::Backspace if form.field == 0 => {
+                                    form.pattern.pop();
+                            }
+                            _ => {
+                            }
+                        }
+                    }
+                    //the clones keep running in the background once the popup is closed
+                    InputMode::CloneResults => {
+                        if key.code == KeyCode::Esc || key.code == KeyCode::Enter {
+                            state.mode = InputMode::ListVM;
+                        }
+                    }
+                    //the snapshots keep being created in the background once the popup is closed
+                    InputMode::SnapshotResults => {
+                        if key.code == KeyCode::Esc || key.code == KeyCode::Enter {
+                            state.mode = InputMode::ListVM;
+                        }
+                    }
+                    InputMode::CreateVm => {
+                        let form = &mut state.create_form;
+                        //(fields of the step, index of the field cycled with Left/Right and its number of choices)
+                        let (fields, cycle) = match form.step {
+                            0 => (2, Some((1, GUEST_OS.len()))),
+                            1 => (3, None),
+                            2 => (4, Some((form.field, [form.networks.len() + 1, form.datastores.len(), form.folders.len(), form.hosts.len()][form.field.min(3)]))),
+                            _ => (0, None),
+                        };
+                        let choice = match (form.step, form.field) {
+                            (0, _) => &mut form.guest_os,
+                            (_, 0) => &mut form.network,
+                            (_, 1) => &mut form.datastore,
+                            (_, 2) => &mut form.folder,
+                            _ => &mut form.host,
+                        };
+                        let text = match (form.step, form.field) {
+                            (0, 0) => Some(&mut form.name),
+                            (1, 0) => Some(&mut form.cpu),
+                            (1, 1) => Some(&mut form.memory_mib),
+                            (1, 2) => Some(&mut form.disk_gib),
+                            _ => None,
+                        };
+                        match key.code {
+                            KeyCode::Esc if form.step == 0 => {
+                                    state.mode = InputMode::Normal;
+                            }
+                            KeyCode::Esc => {
+                                    form.step -= 1;
+                                    form.field = 0;
+                                    form.error = "".to_string();
+                            }
+                            KeyCode::Enter => {
+                                    create_form_next(&api, state);
+                            }
+                            KeyCode::Up => {
+                                    form.field = form.field.saturating_sub(1);
+                            }
+                            KeyCode::Down | KeyCode::Tab if form.field + 1 < fields => {
+                                    form.field += 1;
+                            }
+                            KeyCode::Right => {
+                                if let Some((_, choices)) = cycle.filter(|&(field, choices)| field == form.field && choices > 0) {
+                                    *choice = (*choice + 1) % choices;
+                                }
+                            }
+                            KeyCode::Left => {
+                                if let Some((_, choices)) = cycle.filter(|&(field, choices)| field == form.field && choices > 0) {
+                                    *choice = (*choice + choices - 1) % choices;
+                                }
+                            }
+                            //the sizes only take digits
+                            KeyCode::Char(c) if text.is_some() && (form.step == 0 || c.is_ascii_digit()) => {
+                                    if let Some(text) = text {
+                                        text.push(c);
+                                    }
+                            }
+                            KeyCode::Backspace => {
+                                    if let Some(text) = text {
+                                        text.pop();
+                                    }
+                            }
+                            _ => {
+                            }
+                        }
+                    }
+                    InputMode::Library => {
+                        match key.code {
+                            KeyCode::Esc => {
+                                    state.mode = InputMode::Normal;
+                            }
+                            KeyCode::Up if state.library_cursor > 0 => {
+                                    state.library_cursor -= 1;
+                            }
+                            KeyCode::Down if state.library_cursor + 1 < state.library_items.len() => {
+                                    state.library_cursor += 1;
+                            }
+                            KeyCode::Enter if !state.library_items.is_empty() => {
+                                match open_deploy_form(&api, state) {
+                                    Ok(()) => state.mode = InputMode::Deploy,
+                                    Err(e) => state.status_msg = format!("Error loading the deploy choices: {}", e),
+                                }
+                            }
+                            _ => {
+                            }
+                        }
+                    }
+                    InputMode::Deploy => {
+                        let form = &mut state.deploy_form;
+                        //field 0 is the name, 1 to 3 are the target, folder and datastore lists
+                        let choices = match form.field {
+                            1 => form.targets.len(),
+                            2 => form.folders.len(),
+                            3 => form.datastores.len(),
+                            _ => 0,
+                        };
+                        let choice = match form.field {
+                            1 => &mut form.target,
+                            2 => &mut form.folder,
+                            _ => &mut form.datastore,
+                        };
+                        match key.code {
+                            KeyCode::Esc => {
+                                    state.mode = InputMode::Library;
+                            }
+                            KeyCode::Enter if !form.name.is_empty() => {
+                                    deploy_form_submit(&api, state, deploys.clone());
+                                    state.mode = InputMode::Normal;
+                            }
+                            KeyCode::Up => {
+                                    form.field = form.field.saturating_sub(1);
+                            }
+                            KeyCode::Down | KeyCode::Tab if form.field < 3 => {
+                                    form.field += 1;
+                            }
+                            KeyCode::Right if choices > 0 => {
+                                    *choice = (*choice + 1) % choices;
+                            }
+                            KeyCode::Left if choices > 0 => {
+                                    *choice = (*choice + choices - 1) % choices;
+                            }
+                            KeyCode::Char(c) if form.field == 0 => {
+                                    form.name.push(c);
+                            }
+                            KeyCode::Backspace if form.field == 0 => {
+                                    form.name.pop();
+                            }
+                            _ => {
+                            }
+                        }
+                    }
+                    InputMode::Networks => {
+                        match key.code {
+                            KeyCode::Esc => {
+                                    state.mode = InputMode::Normal;
+                            }
+                            KeyCode::Up if state.network_cursor > 0 => {
+                                    state.network_cursor -= 1;
+                                    state.network_nics.clear();
+                            }
+                            KeyCode::Down if state.network_cursor + 1 < state.networks.len() => {
+                                    state.network_cursor += 1;
+                                    state.network_nics.clear();
+                            }
+                            KeyCode::Enter => {
+                                    load_network_nics(&api, state, false);
+                            }
+                            KeyCode::Char('l') if state.network_nics.iter().any(|(_, nic)| nic.is_none()) => {
+                                    load_network_nics(&api, state, true);
+                            }
+                            _ => {
+                            }
+                        }
+                    }
+                    InputMode::Datastores => {
+                        match key.code {
+                            KeyCode::Esc => {
+                                    state.mode = InputMode::Normal;
+                            }
+                            KeyCode::Up if state.datastore_cursor > 0 => {
+                                    state.datastore_cursor -= 1;
+                                    state.datastore_vms.clear();
+                            }
+                            KeyCode::Down if state.datastore_cursor + 1 < state.datastores.len() => {
+                                    state.datastore_cursor += 1;
+                                    state.datastore_vms.clear();
+                            }
+                            KeyCode::Enter => {
+                                    load_datastore_vms(&api, state);
+                            }
+                            _ => {
+                            }
+                        }
+                    }
+                    InputMode::Search => {
+                        match key.code {
+                             KeyCode::Esc => {
+                                    state.mode = InputMode::Normal;
+                            }
+                            KeyCode::Char(c) => {
+                                    state.search_string.push(c);
+                                    search(state);
+                                    delete(state);
+                            }
+
+                            KeyCode::Backspace => {
+                                    state.search_string.pop();
+                                    search(state);
+                                    delete(state);
+                            }
+                            _ => {
+
+                            }
+                        }
+                    }
+                    InputMode::File =>{
+                        match key.code {
+                            KeyCode::Char(c) => {
+                                    state.file_path.push(c);
+                            }
+
+                            //a yaml file with a create: list creates vms, any other file selects them
+                            KeyCode::Enter => {
+                                    state.mode = InputMode::Normal;
+                                    if !create_from_file(&api, state) {
+                                        select_by_file(state);
+                                    }
+                                    state.file_path = "".to_string();
+                            }
+
+                            KeyCode::Backspace => {
+                                    state.file_path.pop();
+                            }
+
+                            _ => {
+
+                            }
+                        }
+                    }
+                }
+            }
+        }
+        if last_tick.elapsed() >= tick_rate {
+            last_tick = Instant::now();
+        }
+    }
+}
+
+fn ui<B: Backend>(f: &mut Frame<B>, state: &mut State) {
+    f.size();
+
+    let parent_layout = Layout::default()
+        .direction(tui::layout::Direction::Horizontal)
+        .constraints(
+            [
+                Constraint::Percentage(30),
+                Constraint::Percentage(70)
+            ].as_ref()
+        )
+        .split(f.size());
+
+    let new_section_block = Block::default()
+        .title("Info")
+        .borders(Borders::ALL)
+        .border_type(BorderType::Rounded);
+    f.render_widget(new_section_block, parent_layout[0]);
+    show_info(f,state, parent_layout[0]);
+
+
+    let new_list_section = Block::default()
+        .title("Datacenter")
+        .borders(Borders::ALL)
+        .border_type(BorderType::Rounded);
+    f.render_widget(new_list_section, parent_layout[1]);
+    match state.mode {
+        InputMode::Datastores => datastore_section(f, state, parent_layout[1]),
+        InputMode::Networks => network_section(f, state, parent_layout[1]),
+        _ => list_section(f,state, parent_layout[1]),
+    }
+
+    file_popup(f, state);
+    snapshot_popup(f, state);
+    disks_popup(f, state);
+    iso_popup(f, state);
+    move_nic_popup(f, state);
+    new_snapshot_popup(f, state);
+    datacenter_popup(f, state);
+    host_action_popup(f, state);
+    tag_popup(f, state);
+    library_popup(f, state);
+    deploy_popup(f, state);
+    clone_popup(f, state);
+    clone_results_popup(f, state);
+    snapshot_results_popup(f, state);
+    create_vm_popup(f, state);
+    delete_vm_popup(f, state);
+    resize_popup(f, state);
+
+
+}
+
+fn list_section<B: Backend>(f: &mut Frame<B>, state: &mut State, rect: Rect) {
+    let cursor_host = state.host_index();
+    let items: Vec<ListItem> = state
+        .tree()
+        .into_iter()
+        .enumerate()
+        .map(|(index, (node, depth))| {
+            let indent = "   ".repeat(depth);
+            let arrow = if state.expanded(node) { "▼" } else { "▶" };
+            let color = if index == state.cursor { Color::Green } else { Color::Gray };
+
+            match node {
+                TreeNode::Datacenter => {
+                    let name = state.datacenter.as_ref().map(|datacenter| datacenter.name.clone()).unwrap_or_else(|| "All Datacenters".to_string());
+                    ListItem::new(format!("{}{} {}", indent, arrow, name)).style(Style::default().fg(color))
+                }
+                TreeNode::Cluster(cluster) => {
+                    let cluster = &state.clusters[cluster];
+                    let hosts = state.hosts.iter().filter(|host| host.cluster.as_ref() == Some(&cluster.id)).count();
+                    ListItem::new(format!("{}{} {} ({} hosts)", indent, arrow, cluster.name, hosts)).style(Style::default().fg(color))
+                }
+                TreeNode::Group(group) => {
+                    let group = &state.groups[group];
+                    ListItem::new(format!("{}{} {} ({} vms)", indent, arrow, group.name, group.vms.len())).style(Style::default().fg(color))
+                }
+                TreeNode::Host(host) => {
+                    let host = &state.hosts[host];
+                    let connection_color = if host.connection_state == "CONNECTED" { Color::LightGreen } else { Color::LightRed };
+                    let mut spans = vec![
+                        Span::styled(format!("{}{} {} - ", indent, arrow, host.name), Style::default().fg(color)),
+                        Span::styled(host.connection_state.clone(), Style::default().fg(connection_color)),
+                    ];
+                    if host.maintenance == Some(true) {
+                        spans.push(Span::styled(" MAINTENANCE", Style::default().fg(Color::LightYellow)));
+                    }
+                    if let Some(running) = state.host_tasks.get(&host.ip) {
+                        spans.push(Span::styled(format!(" {}...", running), Style::default().fg(Color::Gray)));
+                    }
+                    ListItem::new(Spans::from(spans))
+                }
+                TreeNode::Vm(host, vm) => {
+                    let vm = &state.hosts[host].vms[vm];
+                    let color = if index == state.cursor {
+                        Color::Green
+                    } else if cursor_host == Some(host) {
+                        Color::White
+                    } else {
+                        Color::Gray
+                    };
+                    let check = if vm.selected { "[x]" } else { "[ ]" };
+
+                    //vm name in the cursor color and the state in the color of the state
+                    ListItem::new(Spans::from(vec![
+                        Span::styled(format!("{}{}   {} - ", indent, check, vm.name), Style::default().fg(color)),
+                        Span::styled(vm.state.clone(), Style::default().fg(state_color(&vm.state))),
+                    ]))
+                }
+            }
+        })
+        .collect();
+
+
+    let title = match &state.datacenter {
+        Some(datacenter) => format!("Datacenter {}", datacenter.name),
+        None => "Datacenter".to_string(),
+    };
+    let title = match state.grouping {
+        Grouping::Host => title,
+        Grouping::Folder => format!("{} - by folder", title),
+        Grouping::ResourcePool => format!("{} - by resource pool", title),
+    };
+    let title = match state.tags.iter().find(|tag| Some(&tag.id) == state.tag_filter.as_ref()) {
+        Some(tag) => format!("{} - tag {}", title, tag_label(state, tag)),
+        None => title,
+    };
+    let list = List::new(items)
+        .block(Block::default().borders(Borders::ALL).title(title))
+        .highlight_style(Style::default().add_modifier(Modifier::BOLD))
+        .highlight_symbol(">> ")
+        .start_corner(Corner::TopLeft);
+
+    //the list state scrolls the tree so the cursor stays visible
+    let mut list_state = ListState::default();
+    list_state.select(Some(state.cursor));
+    f.render_stateful_widget(list, rect, &mut list_state);
+}
+
+//datastores with a usage bar, the vms of the one under the cursor are listed under them
+fn datastore_section<B: Backend>(f: &mut Frame<B>, state: &mut State, rect: Rect) {
+    let chunk = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints(
+            [
+                Constraint::Percentage(60),
+                Constraint::Percentage(40),
+            ].as_ref()
+        )
+        .split(rect);
+
+    let items: Vec<ListItem> = state
+        .datastores
+        .iter()
+        .enumerate()
+        .map(|(index, datastore)| {
+            let usage = datastore_usage(datastore);
+            let color = if index == state.datastore_cursor { Color::Green } else { Color::White };
+            let usage_color = if usage >= state.datastore_threshold { Color::LightRed } else { Color::LightGreen };
+            let filled = (usage as usize * 20 / 100).min(20);
+            ListItem::new(Spans::from(vec![
+                Span::styled(format!("{:<30} ", datastore.name), Style::default().fg(color)),
+                Span::styled(format!("[{}{}] {:>3}%", "█".repeat(filled), "░".repeat(20 - filled), usage), Style::default().fg(usage_color)),
+                Span::styled(format!("  {} free of {}  {}", gib(datastore.free_space), gib(datastore.capacity), datastore.datastore_type),
+                             Style::default().fg(Color::Gray)),
+            ]))
+        })
+        .collect();
+    let items = if items.is_empty() {
+        vec![ListItem::new("No datastores").style(Style::default().fg(Color::Gray))]
+    } else {
+        items
+    };
+
+    let list = List::new(items)
+        .block(Block::default().borders(Borders::ALL).title(format!("Datastores (red above {}% used)", state.datastore_threshold)))
+        .highlight_style(Style::default().add_modifier(Modifier::BOLD))
+        .highlight_symbol(">> ");
+    let mut list_state = ListState::default();
+    list_state.select(Some(state.datastore_cursor));
+    f.render_stateful_widget(list, chunk[0], &mut list_state);
+
+    let title = match state.datastores.get(state.datastore_cursor) {
+        Some(datastore) => format!("VMs on {} (Intro: Load)", datastore.name),
+        None => "VMs".to_string(),
+    };
+    let vms: Vec<ListItem> = state.datastore_vms
+        .iter()
+        .map(|vm| ListItem::new(format!("• {}", vm)).style(Style::default().fg(Color::White)))
+        .collect();
+    f.render_widget(List::new(vms).block(Block::default().borders(Borders::ALL).title(title)), chunk[1]);
+}
+
+//networks and port groups, the vms and nics of the one under the cursor are listed under them
+fn network_section<B: Backend>(f: &mut Frame<B>, state: &mut State, rect: Rect) {
+    let chunk = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints(
+            [
+                Constraint::Percentage(60),
+                Constraint::Percentage(40),
+            ].as_ref()
+        )
+        .split(rect);
+
+    let items: Vec<ListItem> = state
+        .networks
+        .iter()
+        .enumerate()
+        .map(|(index, network)| {
+            let color = if index == state.network_cursor { Color::Green } else { Color::White };
+            ListItem::new(Spans::from(vec![
+                Span::styled(format!("{:<40} ", network.name), Style::default().fg(color)),
+                Span::styled(network.network_type.to_string(), Style::default().fg(Color::Gray)),
+            ]))
+        })
+        .collect();
+    let items = if items.is_empty() {
+        vec![ListItem::new("No networks").style(Style::default().fg(Color::Gray))]
+    } else {
+        items
+    };
+
+    let list = List::new(items)
+        .block(Block::default().borders(Borders::ALL).title("Networks"))
+        .highlight_style(Style::default().add_modifier(Modifier::BOLD))
+        .highlight_symbol(">> ");
+    let mut list_state = ListState::default();
+    list_state.select(Some(state.network_cursor));
+    f.render_stateful_widget(list, chunk[0], &mut list_state);
+
+    let title = match state.networks.get(state.network_cursor) {
+        Some(network) => format!("VMs on {} (Intro: Load  L: Load every NIC)", network.name),
+        None => "VMs".to_string(),
+    };
+    let nics: Vec<ListItem> = state.network_nics
+        .iter()
+        .map(|(vm, nic)| match nic {
+            Some(nic) => ListItem::new(Spans::from(vec![
+                Span::styled(format!("• {:<30} {} {} ", vm, nic.label, nic.mac_address.clone().unwrap_or_default()), Style::default().fg(Color::White)),
+                Span::styled(nic.state.to_string(), Style::default().fg(nic_state_color(&nic.state))),
+            ])),
+            None => ListItem::new(Spans::from(vec![
+                Span::styled(format!("• {:<30} ", vm), Style::default().fg(Color::White)),
+                Span::styled("NICs not loaded", Style::default().fg(Color::Gray)),
+            ])),
+        })
+        .collect();
+    f.render_widget(List::new(nics).block(Block::default().borders(Borders::ALL).title(title)), chunk[1]);
+}
+
+//four boxes describing the node under the cursor: datacenter, cluster or host
+fn display_node_info<B: Backend>(f : &mut Frame<B>, state: &mut State, new_section_chunk: Vec<Rect>){
+    let count = |hosts: &[&Host]| {
+        let vms = hosts.iter().map(|host| host.vms.len()).sum::<usize>();
+        let on = hosts.iter().flat_map(|host| host.vms.iter()).filter(|vm| vm.state == "POWERED_ON").count();
+        (vms, on)
+    };
+    let boxes: Vec<(&str, String, Color)> = match (state.node(), state.host_index()) {
+        (_, Some(host)) => {
+            let host = &state.hosts[host];
+            let (vms, on) = count(&[host]);
+            let color = if host.state == "POWERED_ON" && host.connection_state == "CONNECTED" { Color::LightGreen } else { Color::LightRed };
+            let maintenance = if host.maintenance == Some(true) { " (maintenance)" } else { "" };
+            vec![
+                ("Host Name", host.name.to_string(), Color::White),
+                ("Host State", format!("{} - {}{}", host.state, host.connection_state, maintenance), color),
+                ("VM number", vms.to_string(), Color::White),
+                ("Powered ON VMs", on.to_string(), Color::White),
+            ]
+        }
+        (TreeNode::Group(group), _) => {
+            let group = &state.groups[group];
+            let vms = state.hosts
+                .iter()
+                .flat_map(|host| host.vms.iter())
+                .filter(|vm| group.vms.contains(&vm.vm_id))
+                .collect::<Vec<&Vm>>();
+            let on = vms.iter().filter(|vm| vm.state == "POWERED_ON").count();
+            let title = if state.grouping == Grouping::Folder { "Folder" } else { "Resource Pool" };
+            vec![
+                (title, group.name.to_string(), Color::White),
+                ("Id", group.id.to_string(), Color::White),
+                ("VM number", vms.len().to_string(), Color::White),
+                ("Powered ON VMs", on.to_string(), Color::White),
+            ]
+        }
+        (TreeNode::Cluster(cluster), _) => {
+            let cluster = &state.clusters[cluster];
+            let hosts = state.hosts.iter().filter(|host| host.cluster.as_ref() == Some(&cluster.id)).collect::<Vec<&Host>>();
+            let (vms, on) = count(&hosts);
+            vec![
+                ("Cluster Name", cluster.name.to_string(), Color::White),
+                ("Hosts", hosts.len().to_string(), Color::White),
+                ("VM number", vms.to_string(), Color::White),
+                ("Powered ON VMs", on.to_string(), Color::White),
+            ]
+        }
+        _ => {
+            let name = state.datacenter.as_ref().map(|datacenter| datacenter.name.clone()).unwrap_or_else(|| "All Datacenters".to_string());
+            let hosts = state.hosts.iter().collect::<Vec<&Host>>();
+            let (vms, on) = count(&hosts);
+            vec![
+                ("Datacenter", name, Color::White),
+                ("Hosts", hosts.len().to_string(), Color::White),
+                ("VM number", vms.to_string(), Color::White),
+                ("Powered ON VMs", on.to_string(), Color::White),
+            ]
+        }
+    };
+    boxes.into_iter().enumerate().for_each(|(index, (title, value, color))| {
+        let text = vec![Spans::from(vec![Span::styled(value, Style::default().fg(color))])];
+        f.render_widget(Paragraph::new(text).block(Block::default().borders(Borders::ALL).border_type(BorderType::Rounded).title(title)), new_section_chunk[index + 1]);
+    });
+}
+
+fn show_info<B: Backend>(f: &mut Frame<B>, state: &mut State, rect: Rect) {
+    let new_section_chunk = Layout::default()
+        .margin(2)
+        .direction(Direction::Vertical)
+        .constraints(
+            [
+                Constraint::Length(15),
+                Constraint::Length(3),
+                Constraint::Length(3),
+                Constraint::Length(3),
+                Constraint::Length(3),
+                Constraint::Length(3),
+                Constraint::Length(3),
+                Constraint::Length(3),
+            ].as_ref()
+        )
+        .split(rect);
+
+    let desc = match state.mode {
+        InputMode::Normal | InputMode::Search | InputMode::File | InputMode::HostActions | InputMode::Library | InputMode::Deploy | InputMode::CreateVm => Paragraph::new(APP_KEYS_DESC),
+        InputMode::Tags if matches!(state.tag_return, InputMode::Normal) => Paragraph::new(APP_KEYS_DESC),
+        InputMode::Datastores => Paragraph::new(DATASTORE_KEYS_DESC),
+        InputMode::Networks => Paragraph::new(NETWORK_KEYS_DESC),
+        _ => Paragraph::new(VM_KEYS_DESC),
+    };
+    let details = match state.mode {
+        InputMode::ListVM if state.show_details => {
+            state.current_vm().and_then(|vm| state.vm_details.get(&vm.vm_id))
+        }
+        _ => None,
+    };
+    //the details take the place of the keys and the vm boxes, the error is still shown under them
+    if let Some(info) = details {
+        let details = Paragraph::new(vm_details_text(info))
+            .block(Block::default().title("VM Details (I: Hide)").borders(Borders::ALL).border_type(BorderType::Rounded))
+            .wrap(Wrap { trim: false });
+        f.render_widget(details, new_section_chunk[0].union(new_section_chunk[6]));
+        if let Some(vm) = state.current_vm().filter(|vm| vm.error) {
+            let error = Paragraph::new(vm.error_msg.to_owned())
+                .block(Block::default().title("Error").borders(Borders::ALL).border_type(BorderType::Rounded))
+                .style(Style::default().fg(Color::LightRed))
+                .wrap(Wrap { trim: true });
+            f.render_widget(error, new_section_chunk[7]);
+        }
+        return;
+    }
+    f.render_widget(desc, new_section_chunk[0]);
+
+    match state.mode {
+        InputMode::Normal | InputMode::HostActions => {
+            display_node_info(f, state, new_section_chunk.clone());
+            let username_input = Paragraph::new(state.search_string.to_owned())
+                .block(Block::default().title("Search VM by Name").borders(Borders::ALL).border_type(BorderType::Rounded))
+                .style(Style::default().fg(Color::Gray),
+                );
+            f.render_widget(username_input, new_section_chunk[6]);
+        }
+        InputMode::ListVM => {
+            if let Some(vm) = state.current_vm() {
+                let text = vec![
+
+                    //span with the vm name
+                    Spans::from(vec![Span::styled(vm.name.to_string(),
+                                                  Style::default().fg(Color::White),
+                    )]),];
+
+
+                let text2 = vec![
+                    Spans::from(vec![
+                        Span::styled(vm.state.to_string(), Style::default().fg(state_color(&vm.state))),
+                        Span::styled(format!(" - {} vCPU - {} MiB", vm.cpu_count, vm.memory_mib), Style::default().fg(Color::White)),
+                    ]),
+                ];
+
+                let text3 = vec![
+                    if vm.error {
+                        Spans::from(vec![Span::styled(vm.error_msg.to_string(),
+                                                      Style::default().fg(Color::LightRed),
+                        )])
+                    } else {
+                        Spans::from(vec![Span::styled("\n\nNo errors".to_string(),
+                                                      Style::default().fg(Color::LightGreen),
+                        )])
+                    }
+                ];
+                //render the pra
+                f.render_widget(Paragraph::new(text).block(Block::default().borders(Borders::ALL).border_type(BorderType::Rounded).title("VM Name")), new_section_chunk[1]);
+
+                f.render_widget(Paragraph::new(text2).block(Block::default().borders(Borders::ALL).border_type(BorderType::Rounded).title("VM State")), new_section_chunk[2]);
+                f.render_widget(Paragraph::new(text3).block(Block::default().borders(Borders::ALL).border_type(BorderType::Rounded).title("Error")), new_section_chunk[3]);
+
+                //tags and cd-roms share a row
+                let row = Layout::default()
+                    .direction(Direction::Horizontal)
+                    .constraints([Constraint::Percentage(50), Constraint::Percentage(50)].as_ref())
+                    .split(new_section_chunk[4]);
+                let tags = vm_tag_labels(state, &vm.vm_id);
+                let text4 = if tags.is_empty() {
+                    vec![Spans::from(vec![Span::styled("No tags", Style::default().fg(Color::Gray))])]
+                } else {
+                    vec![Spans::from(vec![Span::styled(tags.join(", "), Style::default().fg(Color::LightCyan))])]
+                };
+                f.render_widget(Paragraph::new(text4).block(Block::default().borders(Borders::ALL).border_type(BorderType::Rounded).title("Tags")), row[0]);
+
+                let cdroms = state.vm_details.get(&vm.vm_id).map(|info| {
+                    info.cdroms.values().map(|cdrom| {
+                        let color = if cdrom.backing.backing_type == "ISO_FILE" { Color::LightCyan } else { Color::Gray };
+                        Span::styled(format!("{} ", cdrom_backing(cdrom)), Style::default().fg(color))
+                    }).collect::<Vec<Span>>()
+                }).unwrap_or_default();
+                let text5 = if cdroms.is_empty() {
+                    Spans::from(vec![Span::styled("No CD-ROM", Style::default().fg(Color::Gray))])
+                } else {
+                    Spans::from(cdroms)
+                };
+                f.render_widget(Paragraph::new(text5).block(Block::default().borders(Borders::ALL).border_type(BorderType::Rounded).title("CD-ROM")), row[1]);
+
+                //one line per nic: label, mac, network and connection state
+                let nics: Vec<Spans> = state.vm_details.get(&vm.vm_id).map(|info| {
+                    info.nics.values().map(|nic| {
+                        Spans::from(vec![
+                            Span::styled(format!("{} {} {} ", nic.label, nic.mac_address.clone().unwrap_or_default(), nic_network(state, nic)),
+                                         Style::default().fg(Color::White)),
+                            Span::styled(nic.state.to_string(), Style::default().fg(nic_state_color(&nic.state))),
+                        ])
+                    }).collect()
+                }).unwrap_or_default();
+                let title = if state.isolated.contains_key(&vm.vm_id) { "NICs (Isolated, Shift+Z: Undo)" } else { "NICs" };
+                f.render_widget(Paragraph::new(nics)
+                    .block(Block::default().borders(Borders::ALL).border_type(BorderType::Rounded).title(title))
+                    .wrap(Wrap { trim: true }), new_section_chunk[5].union(new_section_chunk[6]));
+            }
+        }
+        InputMode::Search => {
+
+
+
+            let username_input = Paragraph::new(state.search_string.to_owned())
+                .block(Block::default().title("Search VM by Name").borders(Borders::ALL).border_type(BorderType::Rounded))
+                .style(Style::default().fg(Color::Yellow),
+                );
+            f.render_widget(username_input, new_section_chunk[6]);
+
+            display_node_info(f, state, new_section_chunk.clone());
+        }
+        InputMode::Networks => {
+            if let Some(network) = state.networks.get(state.network_cursor) {
+                let boxes = vec![
+                    ("Network", network.name.to_string()),
+                    ("Type", network.network_type.to_string()),
+                    ("Id", network.network.to_string()),
+                    ("Attached NICs", state.network_nics.iter().filter(|(_, nic)| nic.is_some()).count().to_string()),
+                ];
+                boxes.into_iter().enumerate().for_each(|(index, (title, value))| {
+                    let text = vec![Spans::from(vec![Span::styled(value, Style::default().fg(Color::White))])];
+                    f.render_widget(Paragraph::new(text).block(Block::default().borders(Borders::ALL).border_type(BorderType::Rounded).title(title)), new_section_chunk[index + 1]);
+                });
+            }
+        }
+        InputMode::Datastores => {
+            if let Some(datastore) = state.datastores.get(state.datastore_cursor) {
+                let usage = datastore_usage(datastore);
+                let usage_color = if usage >= state.datastore_threshold { Color::LightRed } else { Color::LightGreen };
+                let boxes = vec![
+                    ("Datastore", datastore.name.to_string(), Color::White),
+                    ("Type", datastore.datastore_type.to_string(), Color::White),
+                    ("Capacity", gib(datastore.capacity), Color::White),
+                    ("Free", format!("{} ({}% used)", gib(datastore.free_space), usage), usage_color),
+                ];
+                boxes.into_iter().enumerate().for_each(|(index, (title, value, color))| {
+                    let text = vec![Spans::from(vec![Span::styled(value, Style::default().fg(color))])];
+                    f.render_widget(Paragraph::new(text).block(Block::default().borders(Borders::ALL).border_type(BorderType::Rounded).title(title)), new_section_chunk[index + 1]);
+                });
+            }
+        }
+        _ => {}
+    }
+
+    //last error reported by the api that does not belong to a single vm
+    if !state.status_msg.is_empty() {
+        let status = Paragraph::new(state.status_msg.to_owned())
+            .block(Block::default().title("Status").borders(Borders::ALL).border_type(BorderType::Rounded))
+            .style(Style::default().fg(Color::LightRed))
+            .wrap(Wrap { trim: true });
+        f.render_widget(status, new_section_chunk[7]);
+    }
+
+}
+
+//lines of the details view, one section per kind of device
+fn vm_details_text(info: &VapiVmInfo) -> Vec<Spans<'static>> {
+    let title = |text: &str| Spans::from(vec![Span::styled(text.to_string(), Style::default().fg(Color::Yellow))]);
+    let line = |text: String| Spans::from(vec![Span::styled(text, Style::default().fg(Color::White))]);
+    let flag = |enabled: bool| if enabled { "on" } else { "off" };
+
+    let mut text = vec![
+        line(format!("Hardware: {}", info.hardware.version)),
+        line(format!("Guest OS: {}", info.guest_os)),
+        line(format!("CPU: {} ({} cores/socket, hot add {}, hot remove {})", info.cpu.count, info.cpu.cores_per_socket,
+                     flag(info.cpu.hot_add_enabled), flag(info.cpu.hot_remove_enabled))),
+        line(format!("Memory: {} MiB (hot add {})", info.memory.size_mib, flag(info.memory.hot_add_enabled))),
+        line(format!("Boot: {} delay {} ms{}", info.boot.boot_type, info.boot.delay,
+                     if info.boot.enter_setup_mode { ", enter setup" } else { "" })),
+    ];
+    if !info.boot_devices.is_empty() {
+        let order = info.boot_devices.iter().map(|device| device.device_type.clone()).collect::<Vec<String>>().join(", ");
+        text.push(line(format!("Boot order: {}", order)));
+    }
+
+    text.push(title("Disks (K: Manage)"));
+    info.disks.values().for_each(|disk| {
+        let file = disk.backing.vmdk_file.clone().unwrap_or_else(|| disk.backing.backing_type.clone());
+        text.push(line(format!("  {}: {} {}", disk.label, gib(disk.capacity), file)));
+    });
+
+    text.push(title("NICs"));
+    info.nics.values().for_each(|nic| {
+        let network = nic.backing.network_name.clone().or_else(|| nic.backing.network.clone()).unwrap_or_default();
+        text.push(line(format!("  {}: {} {} {}", nic.label, nic.mac_address.clone().unwrap_or_default(), network, nic.state)));
+    });
+
+    text.push(title("CD-ROMs"));
+    info.cdroms.values().for_each(|cdrom| {
+        text.push(line(format!("  {}: {} {}", cdrom.label, cdrom_backing(cdrom), cdrom.state)));
+    });
+
+    text
+}
+
+fn file_popup<B: Backend>(f: &mut Frame<B>, state: &mut State) {
+    if let InputMode::File = state.mode {
+        let block = Block::default()
+            .title("Find File")
+            .title_alignment(Alignment::Center)
+            .borders(Borders::ALL)
+            .border_type(BorderType::Rounded);
+        let area = centered_rect(60, 25, f.size());
+        f.render_widget(Clear, area); //this clears out the background
+        f.render_widget(block, area);
+
+        let chunk = Layout::default()
+            .margin(2)
+            .constraints(
+                [
+                    Constraint::Length(2),
+                    Constraint::Length(3),
+                    Constraint::Length(2),
+                ].as_ref()
+            )
+            .split(area);
+
+        let text = Paragraph::new("Enter the path to the file with the vms you like to select, or to a YAML file with a create: list of vms to create")
+            .style(Style::default().fg(Color::White))
+            .alignment(Alignment::Center);
+        f.render_widget(text, chunk[0]);
+
+        let path_input = Paragraph::new(state.file_path.to_owned())
+            .block(Block::default().title("Search VM by Name").borders(Borders::ALL).border_type(BorderType::Rounded))
+            .style(Style::default().fg(Color::White)    ,
+            );
+        f.render_widget(path_input, chunk[1]);
+    }
+}
+
+fn snapshot_popup<B: Backend>(f: &mut Frame<B>, state: &mut State) {
+    if let InputMode::Snapshots = state.mode {
+        let Some(vm) = state.current_vm() else {
+            return;
+        };
+        let block = Block::default()
+            .title(format!("Snapshots of {}", vm.name))
+            .title_alignment(Alignment::Center)
+            .borders(Borders::ALL)
+            .border_type(BorderType::Rounded);
+        let area = centered_rect(70, 60, f.size());
+        f.render_widget(Clear, area);
+        f.render_widget(block, area);
+
+        let chunk = Layout::default()
+            .margin(2)
+            .constraints(
+                [
+                    Constraint::Min(3),
+                    Constraint::Length(2),
+                ].as_ref()
+            )
+            .split(area);
+
+        let items: Vec<ListItem> = if state.snapshots.is_empty() {
+            vec![ListItem::new("No snapshots").style(Style::default().fg(Color::Gray))]
+        } else {
+            state.snapshots
+                .iter()
+                .enumerate()
+                .map(|(index, snapshot)| {
+                    let color = if index == state.snapshot_cursor { Color::Green } else { Color::White };
+                    let marker = if snapshot.current { "▶" } else { "•" };
+                    let mut spans = vec![
+                        Span::styled(format!("{}{} {}", "   ".repeat(snapshot.depth), marker, snapshot.name), Style::default().fg(color)),
+                        Span::styled(format!("  {}", snapshot.created), Style::default().fg(Color::Gray)),
+                        Span::styled(format!("  {}", snapshot.description), Style::default().fg(Color::Gray)),
+                    ];
+                    if snapshot.current {
+                        spans.push(Span::styled("  (current)", Style::default().fg(Color::LightYellow)));
+                    }
+                    ListItem::new(Spans::from(spans))
+                })
+                .collect()
+        };
+        f.render_widget(List::new(items), chunk[0]);
+
+        let footer = match state.snapshot_confirm {
+            Some(action) => {
+                let verb = if action == 'v' { "Revert to" } else { "Delete" };
+                Spans::from(vec![Span::styled(format!("{} {}? Y: Yes  Other key: No", verb, state.snapshots[state.snapshot_cursor].name),
+                                              Style::default().fg(Color::LightYellow),
+                )])
+            }
+            None if state.snapshot_task.is_some() => {
+                let verb = if state.snapshot_task == Some('v') { "Reverting" } else { "Deleting" };
+                Spans::from(vec![Span::styled(format!("{} snapshot...  Esc: Back", verb), Style::default().fg(Color::LightYellow))])
+            }
+            None if vm.error => Spans::from(vec![Span::styled(vm.error_msg.to_string(), Style::default().fg(Color::LightRed))]),
+            None => Spans::from(vec![Span::styled("V: Revert  D: Delete  Esc: Back", Style::default().fg(Color::White))]),
+        };
+        f.render_widget(Paragraph::new(footer).wrap(Wrap { trim: true }), chunk[1]);
+    }
+}
+
+fn disks_popup<B: Backend>(f: &mut Frame<B>, state: &mut State) {
+    if let InputMode::Disks = state.mode {
+        let Some(vm) = state.current_vm() else {
+            return;
+        };
+        let block = Block::default()
+            .title(format!("Disks of {}", vm.name))
+            .title_alignment(Alignment::Center)
+            .borders(Borders::ALL)
+            .border_type(BorderType::Rounded);
+        let area = centered_rect(70, 60, f.size());
+        f.render_widget(Clear, area);
+        f.render_widget(block, area);
+
+        //the add form needs three fields, grow and remove one
+        let form_height = match (&state.disk_form, &state.disk_remove) {
+            (Some(form), _) if !form.grow => 9,
+            (Some(_), _) | (_, Some(_)) => 3,
+            _ => 0,
+        };
+        let chunk = Layout::default()
+            .margin(2)
+            .constraints(
+                [
+                    Constraint::Min(3),
+                    Constraint::Length(form_height),
+                    Constraint::Length(2),
+                ].as_ref()
+            )
+            .split(area);
+
+        let items: Vec<ListItem> = if state.disks.is_empty() {
+            vec![ListItem::new("No disks").style(Style::default().fg(Color::Gray))]
+        } else {
+            state.disks
+                .iter()
+                .enumerate()
+                .map(|(index, disk)| {
+                    let color = if index == state.disk_cursor { Color::Green } else { Color::White };
+                    let file = disk.backing.vmdk_file.clone().unwrap_or_else(|| disk.backing.backing_type.clone());
+                    ListItem::new(Spans::from(vec![
+                        Span::styled(format!("• {:<14}", disk.label), Style::default().fg(color)),
+                        Span::styled(format!("{:>12}  ", gib(disk.capacity)), Style::default().fg(color)),
+                        Span::styled(file, Style::default().fg(Color::Gray)),
+                    ]))
+                })
+                .collect()
+        };
+        f.render_widget(List::new(items), chunk[0]);
+
+        let label = state.disks.get(state.disk_cursor).map(|disk| disk.label.clone()).unwrap_or_default();
+        let footer = if let Some(typed) = &state.disk_remove {
+            f.render_widget(Paragraph::new(typed.to_owned())
+                .block(Block::default().title(format!("Type '{}' to remove it", label)).borders(Borders::ALL).border_type(BorderType::Rounded))
+                .style(Style::default().fg(Color::Yellow)), chunk[1]);
+            Span::styled("The VMDK file is kept on the datastore. Intro: Remove  Esc: Cancel", Style::default().fg(Color::LightYellow))
+        } else if let Some(form) = &state.disk_form {
+            //the field being edited is shown in yellow
+            let field_style = |field: usize| {
+                if form.field == field { Style::default().fg(Color::Yellow) } else { Style::default().fg(Color::White) }
+            };
+            let rows = Layout::default()
+                .constraints([Constraint::Length(3), Constraint::Length(3), Constraint::Length(3)].as_ref())
+                .split(chunk[1]);
+            let title = if form.grow { format!("New Size of {} (GiB)", label) } else { "Size (GiB)".to_string() };
+            f.render_widget(Paragraph::new(form.size_gib.to_owned())
+                .block(Block::default().title(title).borders(Borders::ALL).border_type(BorderType::Rounded))
+                .style(field_style(0)), rows[0]);
+            if !form.grow {
+                let datastore = form.datastores.get(form.datastore)
+                    .map(|datastore| format!("◀ {} ({} free) ▶", datastore.name, gib(datastore.free_space)))
+                    .unwrap_or_else(|| "None available".to_string());
+                f.render_widget(Paragraph::new(datastore)
+                    .block(Block::default().title("Datastore").borders(Borders::ALL).border_type(BorderType::Rounded))
+                    .style(field_style(1)), rows[1]);
+                f.render_widget(Paragraph::new(format!("◀ {} ▶", DISK_PROVISIONING[form.provisioning].1))
+                    .block(Block::default().title("Provisioning").borders(Borders::ALL).border_type(BorderType::Rounded))
+                    .style(field_style(2)), rows[2]);
+            }
+            if form.error.is_empty() {
+                Span::styled("Up/Down: Field  Left/Right: Change  Intro: Apply  Esc: Cancel", Style::default().fg(Color::Gray))
+            } else {
+                Span::styled(form.error.to_owned(), Style::default().fg(Color::LightRed))
+            }
+        } else if let Some(action) = state.disk_task {
+            Span::styled(format!("{} disk...  Esc: Back", action), Style::default().fg(Color::LightYellow))
+        } else if vm.error {
+            Span::styled(vm.error_msg.to_string(), Style::default().fg(Color::LightRed))
+        } else {
+            Span::styled("A: Add  G: Grow  D: Remove  Esc: Back", Style::default().fg(Color::White))
+        };
+        f.render_widget(Paragraph::new(Spans::from(footer)).wrap(Wrap { trim: true }), chunk[2]);
+    }
+}
+
+fn iso_popup<B: Backend>(f: &mut Frame<B>, state: &mut State) {
+    if let InputMode::Iso = state.mode {
+        let form = &state.iso_form;
+        let selected = state.hosts.iter().flat_map(|host| host.vms.iter()).filter(|vm| vm.selected).count();
+        let block = Block::default()
+            .title(format!("ISO of {} VMs", selected))
+            .title_alignment(Alignment::Center)
+            .borders(Borders::ALL)
+            .border_type(BorderType::Rounded);
+        let area = centered_rect(60, 50, f.size());
+        f.render_widget(Clear, area);
+        f.render_widget(block, area);
+
+        let chunk = Layout::default()
+            .margin(2)
+            .constraints(
+                [
+                    Constraint::Length(3),
+                    Constraint::Length(3),
+                    Constraint::Length(3),
+                    Constraint::Min(2),
+                    Constraint::Length(1),
+                ].as_ref()
+            )
+            .split(area);
+
+        //the field being edited is shown in yellow
+        let field_style = |field: usize| {
+            if form.field == field { Style::default().fg(Color::Yellow) } else { Style::default().fg(Color::White) }
+        };
+        let choice = |value: Option<String>| value.map(|value| format!("◀ {} ▶", value)).unwrap_or_else(|| "None available".to_string());
+        let action = ["Mount Datastore ISO", "Mount Content Library ISO", "Eject ISO"][form.action];
+        f.render_widget(Paragraph::new(choice(Some(action.to_string())))
+            .block(Block::default().title("Action").borders(Borders::ALL).border_type(BorderType::Rounded))
+            .style(field_style(0)), chunk[0]);
+        match form.action {
+            0 => {
+                let datastore = form.datastores.get(form.datastore).map(|datastore| datastore.name.clone());
+                f.render_widget(Paragraph::new(choice(datastore))
+                    .block(Block::default().title("Datastore").borders(Borders::ALL).border_type(BorderType::Rounded))
+                    .style(field_style(1)), chunk[1]);
+                f.render_widget(Paragraph::new(form.path.to_owned())
+                    .block(Block::default().title("Path (like iso/ubuntu.iso)").borders(Borders::ALL).border_type(BorderType::Rounded))
+                    .style(field_style(2)), chunk[2]);
+            }
+            1 => {
+                let item = form.items.get(form.item).map(|(library, item)| format!("{} / {}", library, item.name));
+                f.render_widget(Paragraph::new(choice(item))
+                    .block(Block::default().title("ISO").borders(Borders::ALL).border_type(BorderType::Rounded))
+                    .style(field_style(1)), chunk[1]);
+                f.render_widget(Paragraph::new("The ISO is mounted on a new CD-ROM of each VM").style(Style::default().fg(Color::Gray)), chunk[2]);
+            }
+            _ => {
+                f.render_widget(Paragraph::new("Every ISO of the VMs is ejected, the CD-ROMs of content library ISOs are removed")
+                    .style(Style::default().fg(Color::Gray))
+                    .wrap(Wrap { trim: true }), chunk[1]);
+            }
+        }
+
+        f.render_widget(Paragraph::new(form.error.to_owned()).style(Style::default().fg(Color::LightRed)).wrap(Wrap { trim: true }), chunk[3]);
+        f.render_widget(Paragraph::new("Up/Down: Field  Left/Right: Change  Intro: Apply  Esc: Cancel")
+            .style(Style::default().fg(Color::Gray)), chunk[4]);
+    }
+}
+
+fn move_nic_popup<B: Backend>(f: &mut Frame<B>, state: &mut State) {
+    if let InputMode::MoveNic = state.mode {
+        let Some(vm) = state.current_vm() else {
+            return;
+        };
+        let form = &state.nic_form;
+        let block = Block::default()
+            .title(format!("Move NIC of {}", vm.name))
+            .title_alignment(Alignment::Center)
+            .borders(Borders::ALL)
+            .border_type(BorderType::Rounded);
+        let area = centered_rect(60, 40, f.size());
+        f.render_widget(Clear, area);
+        f.render_widget(block, area);
+
+        let chunk = Layout::default()
+            .margin(2)
+            .constraints(
+                [
+                    Constraint::Length(3),
+                    Constraint::Length(3),
+                    Constraint::Min(2),
+                    Constraint::Length(1),
+                ].as_ref()
+            )
+            .split(area);
+
+        //the field being edited is shown in yellow
+        let field_style = |field: usize| {
+            if form.field == field { Style::default().fg(Color::Yellow) } else { Style::default().fg(Color::White) }
+        };
+        let choice = |value: Option<String>| value.map(|value| format!("◀ {} ▶", value)).unwrap_or_else(|| "None available".to_string());
+        let nic = form.nics.get(form.nic).map(|nic| format!("{} ({})", nic.label, nic_network(state, nic)));
+        let network = form.networks.get(form.network).map(|network| format!("{} ({})", network.name, network.network_type));
+        f.render_widget(Paragraph::new(choice(nic))
+            .block(Block::default().title("NIC").borders(Borders::ALL).border_type(BorderType::Rounded))
+            .style(field_style(0)), chunk[0]);
+        f.render_widget(Paragraph::new(choice(network))
+            .block(Block::default().title("New Network").borders(Borders::ALL).border_type(BorderType::Rounded))
+            .style(field_style(1)), chunk[1]);
+        f.render_widget(Paragraph::new(form.error.to_owned()).style(Style::default().fg(Color::LightRed)).wrap(Wrap { trim: true }), chunk[2]);
+        f.render_widget(Paragraph::new("Up/Down: Field  Left/Right: Change  Intro: Move  Esc: Cancel")
+            .style(Style::default().fg(Color::Gray)), chunk[3]);
+    }
+}
+
+fn new_snapshot_popup<B: Backend>(f: &mut Frame<B>, state: &mut State) {
+    if let InputMode::NewSnapshot = state.mode {
+        let selected = state.hosts.iter().flat_map(|host| host.vms.iter()).filter(|vm| vm.selected).count();
+        let form = &state.snapshot_form;
+        let block = Block::default()
+            .title(format!("New Snapshot of {} VMs", selected))
+            .title_alignment(Alignment::Center)
+            .borders(Borders::ALL)
+            .border_type(BorderType::Rounded);
+        let area = centered_rect(60, 40, f.size());
+        f.render_widget(Clear, area);
+        f.render_widget(block, area);
+
+        let chunk = Layout::default()
+            .margin(2)
+            .constraints(
+                [
+                    Constraint::Length(3),
+                    Constraint::Length(3),
+                    Constraint::Length(1),
+                    Constraint::Length(1),
+                    Constraint::Length(2),
+                ].as_ref()
+            )
+            .split(area);
+
+        //the field being edited is shown in yellow
+        let field_style = |field: usize| {
+            if form.field == field { Style::default().fg(Color::Yellow) } else { Style::default().fg(Color::White) }
+        };
+        let check = |flag: bool| if flag { "[x]" } else { "[ ]" };
+
+        f.render_widget(Paragraph::new(form.name.to_owned())
+            .block(Block::default().title("Name").borders(Borders::ALL).border_type(BorderType::Rounded))
+            .style(field_style(0)), chunk[0]);
+        f.render_widget(Paragraph::new(form.description.to_owned())
+            .block(Block::default().title("Description").borders(Borders::ALL).border_type(BorderType::Rounded))
+            .style(field_style(1)), chunk[1]);
+        f.render_widget(Paragraph::new(format!("{} Include memory", check(form.memory))).style(field_style(2)), chunk[2]);
+        f.render_widget(Paragraph::new(format!("{} Quiesce guest file system", check(form.quiesce))).style(field_style(3)), chunk[3]);
+        f.render_widget(Paragraph::new("Up/Down: Field  Space: Toggle  Intro: Create  Esc: Cancel")
+            .style(Style::default().fg(Color::Gray)), chunk[4]);
+    }
+}
+
+fn datacenter_popup<B: Backend>(f: &mut Frame<B>, state: &mut State) {
+    if let InputMode::Datacenters = state.mode {
+        let block = Block::default()
+            .title("Select Datacenter")
+            .title_alignment(Alignment::Center)
+            .borders(Borders::ALL)
+            .border_type(BorderType::Rounded);
+        let area = centered_rect(40, 40, f.size());
+        f.render_widget(Clear, area);
+        f.render_widget(block, area);
+
+        let chunk = Layout::default()
+            .margin(2)
+            .constraints(
+                [
+                    Constraint::Min(3),
+                    Constraint::Length(1),
+                ].as_ref()
+            )
+            .split(area);
+
+        let current = state.datacenter.as_ref().map(|datacenter| datacenter.datacenter.clone());
+        let items: Vec<ListItem> = if state.datacenters.is_empty() {
+            vec![ListItem::new("No datacenters").style(Style::default().fg(Color::Gray))]
+        } else {
+            state.datacenters
+                .iter()
+                .enumerate()
+                .map(|(index, datacenter)| {
+                    let color = if index == state.datacenter_cursor { Color::Green } else { Color::White };
+                    let marker = if Some(&datacenter.datacenter) == current.as_ref() { "▶" } else { "•" };
+                    ListItem::new(format!("{} {}", marker, datacenter.name)).style(Style::default().fg(color))
+                })
+                .collect()
+        };
+        f.render_widget(List::new(items), chunk[0]);
+        f.render_widget(Paragraph::new("Intro: Select  Esc: Cancel").style(Style::default().fg(Color::Gray)), chunk[1]);
+    }
+}
+
+fn host_action_popup<B: Backend>(f: &mut Frame<B>, state: &mut State) {
+    if let InputMode::HostActions = state.mode {
+        let Some(host) = state.host_index().map(|host| &state.hosts[host]) else {
+            return;
+        };
+        let block = Block::default()
+            .title(format!("Host {}", host.name))
+            .title_alignment(Alignment::Center)
+            .borders(Borders::ALL)
+            .border_type(BorderType::Rounded);
+        let area = centered_rect(40, 30, f.size());
+        f.render_widget(Clear, area);
+        f.render_widget(block, area);
+
+        let chunk = Layout::default()
+            .margin(2)
+            .constraints(
+                [
+                    Constraint::Min(4),
+                    Constraint::Length(2),
+                ].as_ref()
+            )
+            .split(area);
+
+        let items: Vec<ListItem> = ['m', 'b', 'x', 'c']
+            .iter()
+            .map(|&action| {
+                ListItem::new(format!("{}: {}", action.to_ascii_uppercase(), host_action_label(host, action)))
+                    .style(Style::default().fg(Color::White))
+            })
+            .collect();
+        f.render_widget(List::new(items), chunk[0]);
+
+        let footer = match state.host_action {
+            Some(action) => Spans::from(vec![Span::styled(format!("{} {}? Y: Yes  Other key: No", host_action_label(host, action), host.name),
+                                                          Style::default().fg(Color::LightYellow),
+            )]),
+            None => Spans::from(vec![Span::styled("Esc: Back", Style::default().fg(Color::Gray))]),
+        };
+        f.render_widget(Paragraph::new(footer).wrap(Wrap { trim: true }), chunk[1]);
+    }
+}
+
+fn tag_popup<B: Backend>(f: &mut Frame<B>, state: &mut State) {
+    if let InputMode::Tags = state.mode {
+        let selected = state.hosts.iter().flat_map(|host| host.vms.iter()).filter(|vm| vm.selected).count();
+        let block = Block::default()
+            .title(format!("Tags ({} VMs selected)", selected))
+            .title_alignment(Alignment::Center)
+            .borders(Borders::ALL)
+            .border_type(BorderType::Rounded);
+        let area = centered_rect(50, 60, f.size());
+        f.render_widget(Clear, area);
+        f.render_widget(block, area);
+
+        let chunk = Layout::default()
+            .margin(2)
+            .constraints(
+                [
+                    Constraint::Min(3),
+                    Constraint::Length(2),
+                ].as_ref()
+            )
+            .split(area);
 
-            let vm_items: Vec<ListItem> = host
-                .vms
+        let items: Vec<ListItem> = if state.tags.is_empty() {
+            vec![ListItem::new("No tags").style(Style::default().fg(Color::Gray))]
+        } else {
+            state.tags
                 .iter()
                 .enumerate()
-                .map(|(index,vm)| {
-                    return if selected_host && state.vm_cursor == Some(index) {
-                        if vm.selected {
-                            ListItem::new(format!(" [x]   {} - {}", vm.name, vm.state))
-                                .style(Style::default().fg(Color::Green))
-                        } else {
-                            ListItem::new(format!(" [ ]   {} - {}", vm.name, vm.state))
-                                .style(Style::default().fg(Color::Green))
-                        }
-                    } else if selected_host {
-                        if vm.selected {
-                            //create a list item with the vm.name in color white and the vm.sate in color green
-
-                            ListItem::new(format!(" [x]   {} - {}", vm.name, vm.state))
-                                .style(Style::default().fg(Color::White))
-                        } else {
-                            ListItem::new(format!(" [ ]   {} - {}", vm.name, vm.state))
-                                .style(Style::default().fg(Color::White))
-                        }
-                    }else{
-                        if vm.selected {
-                            ListItem::new(format!(" [x]   {} - {}", vm.name, vm.state))
-                                .style(Style::default().fg(Color::Gray))
-                        } else {
-                            ListItem::new(format!(" [ ]   {} - {}", vm.name, vm.state))
-                                .style(Style::default().fg(Color::Gray))
-                        }
-                    }
+                .map(|(index, tag)| {
+                    let color = if index == state.tag_cursor { Color::Green } else { Color::White };
+                    let marker = if Some(&tag.id) == state.tag_filter.as_ref() { "▶" } else { "•" };
+                    let vms = state.vm_tags.values().filter(|tags| tags.contains(&tag.id)).count();
+                    ListItem::new(Spans::from(vec![
+                        Span::styled(format!("{} {}", marker, tag_label(state, tag)), Style::default().fg(color)),
+                        Span::styled(format!("  ({} vms)  {}", vms, tag.description), Style::default().fg(Color::Gray)),
+                    ]))
                 })
-                .collect();
-
-            if vm_items.len() > 0 {
-                vec![host_item, ListItem::new("\n")]
-                .into_iter()
-                .chain(vm_items.into_iter())
                 .collect()
-            } else {
-                vec![host_item]
+        };
+        let list = List::new(items)
+            .highlight_style(Style::default().add_modifier(Modifier::BOLD))
+            .highlight_symbol(">> ");
+        let mut list_state = ListState::default();
+        list_state.select(Some(state.tag_cursor));
+        f.render_stateful_widget(list, chunk[0], &mut list_state);
+
+        let footer = match state.tag_confirm {
+            Some(action) => {
+                let (verb, preposition) = if action == 'a' { ("Attach", "to") } else { ("Detach", "from") };
+                Spans::from(vec![Span::styled(format!("{} {} {} {} VMs? Y: Yes  Other key: No", verb, tag_label(state, &state.tags[state.tag_cursor]), preposition, selected),
+                                              Style::default().fg(Color::LightYellow),
+                )])
             }
+            None => Spans::from(vec![Span::styled("Intro: Filter  C: Clear Filter  A: Attach to Selected  D: Detach from Selected  Esc: Back",
+                                                  Style::default().fg(Color::Gray))]),
+        };
+        f.render_widget(Paragraph::new(footer).wrap(Wrap { trim: true }), chunk[1]);
+    }
+}
 
-        })
-        .flatten()
-        .collect();
+fn library_popup<B: Backend>(f: &mut Frame<B>, state: &mut State) {
+    if let InputMode::Library = state.mode {
+        let block = Block::default()
+            .title("Content Libraries")
+            .title_alignment(Alignment::Center)
+            .borders(Borders::ALL)
+            .border_type(BorderType::Rounded);
+        let area = centered_rect(60, 60, f.size());
+        f.render_widget(Clear, area);
+        f.render_widget(block, area);
 
+        let chunk = Layout::default()
+            .margin(2)
+            .constraints(
+                [
+                    Constraint::Min(3),
+                    Constraint::Length(1),
+                ].as_ref()
+            )
+            .split(area);
 
-    let list = List::new(items)
-        .block(Block::default().borders(Borders::ALL).title("Datacenter"))
-        .highlight_style(Style::default().add_modifier(Modifier::BOLD))
-        .highlight_symbol(">> ")
-        .start_corner(Corner::TopLeft);
+        let items: Vec<ListItem> = if state.library_items.is_empty() {
+            vec![ListItem::new("No OVF or VM templates").style(Style::default().fg(Color::Gray))]
+        } else {
+            state.library_items
+                .iter()
+                .enumerate()
+                .map(|(index, (library, item))| {
+                    let color = if index == state.library_cursor { Color::Green } else { Color::White };
+                    ListItem::new(Spans::from(vec![
+                        Span::styled(format!("{} / {}", library, item.name), Style::default().fg(color)),
+                        Span::styled(format!("  {}  {}  {}", item.item_type, gib(item.size), item.description), Style::default().fg(Color::Gray)),
+                    ]))
+                })
+                .collect()
+        };
+        let list = List::new(items)
+            .highlight_style(Style::default().add_modifier(Modifier::BOLD))
+            .highlight_symbol(">> ");
+        let mut list_state = ListState::default();
+        list_state.select(Some(state.library_cursor));
+        f.render_stateful_widget(list, chunk[0], &mut list_state);
+        f.render_widget(Paragraph::new("Intro: Deploy  Esc: Cancel").style(Style::default().fg(Color::Gray)), chunk[1]);
+    }
+}
 
+fn deploy_popup<B: Backend>(f: &mut Frame<B>, state: &mut State) {
+    if let InputMode::Deploy = state.mode {
+        let Some((_, item)) = state.library_items.get(state.library_cursor) else {
+            return;
+        };
+        let form = &state.deploy_form;
+        let block = Block::default()
+            .title(format!("Deploy {}", item.name))
+            .title_alignment(Alignment::Center)
+            .borders(Borders::ALL)
+            .border_type(BorderType::Rounded);
+        let area = centered_rect(60, 50, f.size());
+        f.render_widget(Clear, area);
+        f.render_widget(block, area);
 
+        let chunk = Layout::default()
+            .margin(2)
+            .constraints(
+                [
+                    Constraint::Length(3),
+                    Constraint::Length(3),
+                    Constraint::Length(3),
+                    Constraint::Length(3),
+                    Constraint::Length(2),
+                ].as_ref()
+            )
+            .split(area);
 
-    f.render_widget(list, rect);
+        //the field being edited is shown in yellow
+        let field_style = |field: usize| {
+            if form.field == field { Style::default().fg(Color::Yellow) } else { Style::default().fg(Color::White) }
+        };
+        let choice = |value: Option<String>| value.map(|value| format!("◀ {} ▶", value)).unwrap_or_else(|| "None available".to_string());
+
+        let fields = [
+            ("VM Name", form.name.to_owned()),
+            ("Host or Cluster", choice(form.targets.get(form.target).map(|target| target.2.clone()))),
+            ("Folder", choice(form.folders.get(form.folder).map(|folder| folder.name.clone()))),
+            ("Datastore", choice(form.datastores.get(form.datastore).map(|datastore| {
+                format!("{} ({} free)", datastore.name, gib(datastore.free_space))
+            }))),
+        ];
+        fields.into_iter().enumerate().for_each(|(index, (title, value))| {
+            f.render_widget(Paragraph::new(value)
+                .block(Block::default().title(title).borders(Borders::ALL).border_type(BorderType::Rounded))
+                .style(field_style(index)), chunk[index]);
+        });
+        f.render_widget(Paragraph::new("Up/Down: Field  Left/Right: Change  Intro: Deploy  Esc: Back")
+            .style(Style::default().fg(Color::Gray)), chunk[4]);
+    }
 }
 
-fn display_host_info<B: Backend>(f : &mut Frame<B>, state: &mut State, new_section_chunk: Vec<Rect>){
-    let text = vec![
-        Spans::from(vec![Span::styled(format!("{}", state.hosts[state.host_cursor.unwrap_or(0)].name),
-                                      Style::default().fg(Color::White),
-        )]),
-    ];
-    let text2 = vec![
-        if state.hosts[state.host_cursor.unwrap_or(0)].state == "POWERED_ON" {
-            Spans::from(vec![Span::styled(format!("{}", state.hosts[state.host_cursor.unwrap_or(0)].state),
-                                          Style::default().fg(Color::LightGreen),
-            )])
-        } else {
-            Spans::from(vec![Span::styled(format!("{}", state.hosts[state.host_cursor.unwrap_or(0)].state),
-                                          Style::default().fg(Color::LightRed),
-            )])
-        },
-    ];
-    let text3 = vec![Spans::from(vec![Span::styled(format!("{}", state.hosts[state.host_cursor.unwrap_or(0)].vms.len()),
-                                                   Style::default().fg(Color::White),
-    )]),];
+fn clone_popup<B: Backend>(f: &mut Frame<B>, state: &mut State) {
+    if let InputMode::Clone = state.mode {
+        let Some(vm) = state.current_vm() else {
+            return;
+        };
+        let form = &state.clone_form;
+        let block = Block::default()
+            .title(format!("Clone {}", vm.name))
+            .title_alignment(Alignment::Center)
+            .borders(Borders::ALL)
+            .border_type(BorderType::Rounded);
+        let area = centered_rect(60, 35, f.size());
+        f.render_widget(Clear, area);
+        f.render_widget(block, area);
 
-    let text4 = vec![Spans::from(vec![Span::styled(format!("{}", state.hosts[state.host_cursor.unwrap_or(0)].vms.iter().filter(|vm| vm.state == "POWERED_ON").count()),
-                                                   Style::default().fg(Color::White),
-    )]),];
-    //clear the screen
-    f.render_widget(Paragraph::new(text).block(Block::default().borders(Borders::ALL).border_type(BorderType::Rounded).title("Host Name")), new_section_chunk[1]);
-    f.render_widget(Paragraph::new(text2).block(Block::default().borders(Borders::ALL).border_type(BorderType::Rounded).title("Host State")), new_section_chunk[2]);
-    f.render_widget(Paragraph::new(text3).block(Block::default().borders(Borders::ALL).border_type(BorderType::Rounded).title("VM number")), new_section_chunk[3]);
-    f.render_widget(Paragraph::new(text4).block(Block::default().borders(Borders::ALL).border_type(BorderType::Rounded).title("Powered ON VMs")), new_section_chunk[4]);
-}
+        let chunk = Layout::default()
+            .margin(2)
+            .constraints(
+                [
+                    Constraint::Length(3),
+                    Constraint::Length(1),
+                    Constraint::Length(2),
+                    Constraint::Length(2),
+                ].as_ref()
+            )
+            .split(area);
 
-fn show_info<B: Backend>(f: &mut Frame<B>, state: &mut State, rect: Rect) {
-    let new_section_chunk = Layout::default()
-        .margin(2)
-        .direction(Direction::Vertical)
-        .constraints(
-            [
-                Constraint::Length(15),
-                Constraint::Length(3),
-                Constraint::Length(3),
-                Constraint::Length(3),
-                Constraint::Length(3),
-                Constraint::Length(3),
-                Constraint::Length(3),
-                Constraint::Length(3),
-            ].as_ref()
-        )
-        .split(rect);
+        //the field being edited is shown in yellow
+        let field_style = |field: usize| {
+            if form.field == field { Style::default().fg(Color::Yellow) } else { Style::default().fg(Color::White) }
+        };
+        let check = if form.power_on { "[x]" } else { "[ ]" };
+
+        f.render_widget(Paragraph::new(form.pattern.to_owned())
+            .block(Block::default().title("Name Pattern (web-{01..05})").borders(Borders::ALL).border_type(BorderType::Rounded))
+            .style(field_style(0)), chunk[0]);
+        f.render_widget(Paragraph::new(format!("{} Power on the clones", check)).style(field_style(1)), chunk[1]);
+
+        //names the pattern gives, or why it is not valid
+        let preview = match expand_pattern(&form.pattern) {
+            Ok(names) if names.len() > 3 => Span::styled(format!("{} clones: {}, {} ... {}", names.len(), names[0], names[1], names[names.len() - 1]),
+                                                          Style::default().fg(Color::LightGreen)),
+            Ok(names) => Span::styled(format!("{} clones: {}", names.len(), names.join(", ")), Style::default().fg(Color::LightGreen)),
+            Err(e) => Span::styled(e, Style::default().fg(Color::LightRed)),
+        };
+        f.render_widget(Paragraph::new(Spans::from(vec![preview])).wrap(Wrap { trim: true }), chunk[2]);
+        f.render_widget(Paragraph::new("Up/Down: Field  Space: Toggle  Intro: Clone  Esc: Cancel")
+            .style(Style::default().fg(Color::Gray)), chunk[3]);
+    }
+}
 
-    let desc = Paragraph::new(APP_KEYS_DESC);
-    f.render_widget(desc, new_section_chunk[0]);
+fn clone_results_popup<B: Backend>(f: &mut Frame<B>, state: &mut State) {
+    if let InputMode::CloneResults = state.mode {
+        let done = state.clone_results.iter().filter(|row| row.result.is_some()).count();
+        let block = Block::default()
+            .title(format!("Clones {}/{}", done, state.clone_results.len()))
+            .title_alignment(Alignment::Center)
+            .borders(Borders::ALL)
+            .border_type(BorderType::Rounded);
+        let area = centered_rect(60, 60, f.size());
+        f.render_widget(Clear, area);
+        f.render_widget(block, area);
 
-    match state.mode {
-        InputMode::Normal => {
-            let text = vec![
-                Spans::from(vec![Span::styled(format!("{}", state.hosts[state.host_cursor.unwrap_or(0)].name),
-                                              Style::default().fg(Color::White),
-                )]),
-            ];
-            let text2 = vec![
-                if (state.hosts[state.host_cursor.unwrap_or(0)].state == "POWERED_ON") {
-                    Spans::from(vec![Span::styled(format!("{}", state.hosts[state.host_cursor.unwrap_or(0)].state),
-                                                  Style::default().fg(Color::LightGreen),
-                    )])
-                } else {
-                    Spans::from(vec![Span::styled(format!("{}", state.hosts[state.host_cursor.unwrap_or(0)].state),
-                                                  Style::default().fg(Color::LightRed),
-                    )])
-                },
-            ];
-            let text3 = vec![Spans::from(vec![Span::styled(format!("{}", state.hosts[state.host_cursor.unwrap_or(0)].vms.len()),
-                                                           Style::default().fg(Color::White),
-            )]),];
-
-            let text4 = vec![Spans::from(vec![Span::styled(format!("{}", state.hosts[state.host_cursor.unwrap_or(0)].vms.iter().filter(|vm| vm.state == "POWERED_ON").count()),
-                                                           Style::default().fg(Color::White),
-            )]),];
-            //clear the screen
-            f.render_widget(Paragraph::new(text).block(Block::default().borders(Borders::ALL).border_type(BorderType::Rounded).title("Host Name")), new_section_chunk[1]);
-            f.render_widget(Paragraph::new(text2).block(Block::default().borders(Borders::ALL).border_type(BorderType::Rounded).title("Host State")), new_section_chunk[2]);
-            f.render_widget(Paragraph::new(text3).block(Block::default().borders(Borders::ALL).border_type(BorderType::Rounded).title("VM number")), new_section_chunk[3]);
-            f.render_widget(Paragraph::new(text4).block(Block::default().borders(Borders::ALL).border_type(BorderType::Rounded).title("Powered ON VMs")), new_section_chunk[4]);
-            let username_input = Paragraph::new(state.search_string.to_owned())
-                .block(Block::default().title("Search VM by Name").borders(Borders::ALL).border_type(BorderType::Rounded))
-                .style(Style::default().fg(Color::Gray),
-                );
-            f.render_widget(username_input, new_section_chunk[6]);
-        }
-        InputMode::ListVM => {
-            let text = vec![
+        let chunk = Layout::default()
+            .margin(2)
+            .constraints(
+                [
+                    Constraint::Min(3),
+                    Constraint::Length(1),
+                ].as_ref()
+            )
+            .split(area);
 
-                //span with the vm name
-                Spans::from(vec![Span::styled(format!("{}", state.hosts[state.host_cursor.unwrap_or(0)].vms[state.vm_cursor.unwrap_or(0)].name),
-                                              Style::default().fg(Color::White),
-                )]),];
+        //the clones run one after the other, the first one without a result is the one in progress
+        let running = state.clone_results.iter().position(|row| row.result.is_none());
+        let items: Vec<ListItem> = state.clone_results
+            .iter()
+            .enumerate()
+            .map(|(index, row)| {
+                let (status, color) = match &row.result {
+                    Some(Ok(vm_id)) => (format!("Done ({})", vm_id), Color::LightGreen),
+                    Some(Err(e)) => (e.clone(), Color::LightRed),
+                    None if running == Some(index) => ("Cloning...".to_string(), Color::LightYellow),
+                    None => ("Waiting".to_string(), Color::Gray),
+                };
+                ListItem::new(Spans::from(vec![
+                    Span::styled(format!("{:<30} ", row.name), Style::default().fg(Color::White)),
+                    Span::styled(status, Style::default().fg(color)),
+                ]))
+            })
+            .collect();
+        f.render_widget(List::new(items), chunk[0]);
+        f.render_widget(Paragraph::new("Esc: Close (the clones keep running)").style(Style::default().fg(Color::Gray)), chunk[1]);
+    }
+}
 
+fn snapshot_results_popup<B: Backend>(f: &mut Frame<B>, state: &mut State) {
+    if let InputMode::SnapshotResults = state.mode {
+        let done = state.snapshot_results.iter().filter(|row| row.result.is_some()).count();
+        let block = Block::default()
+            .title(format!("Snapshots {}/{}", done, state.snapshot_results.len()))
+            .title_alignment(Alignment::Center)
+            .borders(Borders::ALL)
+            .border_type(BorderType::Rounded);
+        let area = centered_rect(60, 60, f.size());
+        f.render_widget(Clear, area);
+        f.render_widget(block, area);
 
-            let text2 = vec![
-                if state.hosts[state.host_cursor.unwrap_or(0)].vms[state.vm_cursor.unwrap_or(0)].state == "POWERED_ON" {
-                    Spans::from(vec![Span::styled(format!("{}", state.hosts[state.host_cursor.unwrap_or(0)].vms[state.vm_cursor.unwrap_or(0)].state),
-                                                  Style::default().fg(Color::LightGreen), )])
-                } else {
-                    Spans::from(vec![Span::styled(format!("{}", state.hosts[state.host_cursor.unwrap_or(0)].vms[state.vm_cursor.unwrap_or(0)].state),
-                                                  Style::default().fg(Color::LightRed), )])
-                },
-            ];
+        let chunk = Layout::default()
+            .margin(2)
+            .constraints(
+                [
+                    Constraint::Min(3),
+                    Constraint::Length(1),
+                ].as_ref()
+            )
+            .split(area);
 
-             let text3 = vec![
-                 if state.hosts[state.host_cursor.unwrap_or(0)].vms[state.vm_cursor.unwrap_or(0)].error == true {
-                 Spans::from(vec![Span::styled(format!("{}", state.hosts[state.host_cursor.unwrap_or(0)].vms[state.vm_cursor.unwrap_or(0)].error_msg),
-                                               Style::default().fg(Color::LightRed),
-                 )])
-             } else {
-                 Spans::from(vec![Span::styled(format!("\n\nNo errors"),
-                                               Style::default().fg(Color::LightGreen),
-                 )])
-             }
-             ];
-            //render the pra
-            f.render_widget(Paragraph::new(text).block(Block::default().borders(Borders::ALL).border_type(BorderType::Rounded).title("VM Name")), new_section_chunk[1]);
+        //the vms are done one after the other, the first one without a result is the one in progress
+        let running = state.snapshot_results.iter().position(|row| row.result.is_none());
+        let items: Vec<ListItem> = state.snapshot_results
+            .iter()
+            .enumerate()
+            .map(|(index, row)| {
+                let (status, color) = match &row.result {
+                    Some(Ok(())) => ("Done".to_string(), Color::LightGreen),
+                    Some(Err(e)) => (e.clone(), Color::LightRed),
+                    None if running == Some(index) => ("Creating snapshot...".to_string(), Color::LightYellow),
+                    None => ("Waiting".to_string(), Color::Gray),
+                };
+                ListItem::new(Spans::from(vec![
+                    Span::styled(format!("{:<30} ", row.name), Style::default().fg(Color::White)),
+                    Span::styled(status, Style::default().fg(color)),
+                ]))
+            })
+            .collect();
+        f.render_widget(List::new(items), chunk[0]);
+        f.render_widget(Paragraph::new("Esc: Close (the snapshots keep running)").style(Style::default().fg(Color::Gray)), chunk[1]);
+    }
+}
 
-            f.render_widget(Paragraph::new(text2).block(Block::default().borders(Borders::ALL).border_type(BorderType::Rounded).title("VM State")), new_section_chunk[2]);
-            f.render_widget(Paragraph::new(text3).block(Block::default().borders(Borders::ALL).border_type(BorderType::Rounded).title("Error")), new_section_chunk[3]);
+fn create_vm_popup<B: Backend>(f: &mut Frame<B>, state: &mut State) {
+    if let InputMode::CreateVm = state.mode {
+        let form = &state.create_form;
+        let block = Block::default()
+            .title(format!("New VM - Step {}/{}: {}", form.step + 1, CREATE_STEPS.len(), CREATE_STEPS[form.step]))
+            .title_alignment(Alignment::Center)
+            .borders(Borders::ALL)
+            .border_type(BorderType::Rounded);
+        let area = centered_rect(60, 60, f.size());
+        f.render_widget(Clear, area);
+        f.render_widget(block, area);
 
+        let chunk = Layout::default()
+            .margin(2)
+            .constraints(
+                [
+                    Constraint::Min(12),
+                    Constraint::Length(2),
+                    Constraint::Length(1),
+                ].as_ref()
+            )
+            .split(area);
 
+        //the field being edited is shown in yellow
+        let field_style = |field: usize| {
+            if form.field == field { Style::default().fg(Color::Yellow) } else { Style::default().fg(Color::White) }
+        };
+        let choice = |value: Option<String>| value.map(|value| format!("◀ {} ▶", value)).unwrap_or_else(|| "None available".to_string());
+        let network = if form.network == form.networks.len() {
+            Some("No NIC".to_string())
+        } else {
+            form.networks.get(form.network).map(|network| network.name.clone())
+        };
+        let datastore = form.datastores.get(form.datastore).map(|datastore| format!("{} ({} free)", datastore.name, gib(datastore.free_space)));
+        let folder = form.folders.get(form.folder).map(|folder| folder.name.clone());
+        let host = form.hosts.get(form.host).map(|host| host.1.clone());
+
+        let fields: Vec<(&str, String)> = match form.step {
+            0 => vec![
+                ("VM Name", form.name.to_owned()),
+                ("Guest OS", choice(Some(GUEST_OS[form.guest_os].to_string()))),
+            ],
+            1 => vec![
+                ("CPU Count", form.cpu.to_owned()),
+                ("Memory (MiB)", form.memory_mib.to_owned()),
+                ("Disk (GiB)", form.disk_gib.to_owned()),
+            ],
+            2 => vec![
+                ("Network", choice(network.clone())),
+                ("Datastore", choice(datastore.clone())),
+                ("Folder", choice(folder.clone())),
+                ("Host", choice(host.clone())),
+            ],
+            _ => Vec::new(),
+        };
+        if fields.is_empty() {
+            let line = |title: &str, value: String| Spans::from(vec![
+                Span::styled(format!("{:<12}", title), Style::default().fg(Color::Gray)),
+                Span::styled(value, Style::default().fg(Color::White)),
+            ]);
+            let review = vec![
+                line("Name", form.name.to_owned()),
+                line("Guest OS", GUEST_OS[form.guest_os].to_string()),
+                line("CPU", form.cpu.to_owned()),
+                line("Memory", format!("{} MiB", form.memory_mib)),
+                line("Disk", format!("{} GiB", form.disk_gib)),
+                line("Network", network.unwrap_or_default()),
+                line("Datastore", datastore.unwrap_or_default()),
+                line("Folder", folder.unwrap_or_default()),
+                line("Host", host.unwrap_or_default()),
+            ];
+            f.render_widget(Paragraph::new(review), chunk[0]);
+        } else {
+            let rows = Layout::default()
+                .constraints(fields.iter().map(|_| Constraint::Length(3)).collect::<Vec<Constraint>>())
+                .split(chunk[0]);
+            fields.into_iter().enumerate().for_each(|(index, (title, value))| {
+                f.render_widget(Paragraph::new(value)
+                    .block(Block::default().title(title).borders(Borders::ALL).border_type(BorderType::Rounded))
+                    .style(field_style(index)), rows[index]);
+            });
         }
-        InputMode::Search => {
 
+        f.render_widget(Paragraph::new(form.error.to_owned()).style(Style::default().fg(Color::LightRed)).wrap(Wrap { trim: true }), chunk[1]);
+        let help = if form.step + 1 == CREATE_STEPS.len() {
+            "Intro: Create  Esc: Back"
+        } else {
+            "Up/Down: Field  Left/Right: Change  Intro: Next  Esc: Back"
+        };
+        f.render_widget(Paragraph::new(help).style(Style::default().fg(Color::Gray)), chunk[2]);
+    }
+}
 
+fn delete_vm_popup<B: Backend>(f: &mut Frame<B>, state: &mut State) {
+    if let InputMode::DeleteVm = state.mode {
+        let form = &state.delete_form;
+        let block = Block::default()
+            .title(format!("Delete {} VMs", form.vms.len()))
+            .title_alignment(Alignment::Center)
+            .borders(Borders::ALL)
+            .border_type(BorderType::Rounded);
+        let area = centered_rect(60, 60, f.size());
+        f.render_widget(Clear, area);
+        f.render_widget(block, area);
 
-            let username_input = Paragraph::new(state.search_string.to_owned())
-                .block(Block::default().title("Search VM by Name").borders(Borders::ALL).border_type(BorderType::Rounded))
-                .style(Style::default().fg(Color::Yellow),
-                );
-            f.render_widget(username_input, new_section_chunk[6]);
+        let chunk = Layout::default()
+            .margin(2)
+            .constraints(
+                [
+                    Constraint::Min(3),
+                    Constraint::Length(3),
+                    Constraint::Length(1),
+                    Constraint::Length(2),
+                ].as_ref()
+            )
+            .split(area);
 
-            display_host_info(f, state, new_section_chunk);
+        //powered on vms are shown in red, they can only go once powered off
+        let vms = form.vms.iter().map(|(_, name, power_state)| {
+            let color = if power_state == "POWERED_ON" { Color::LightRed } else { Color::White };
+            Spans::from(vec![
+                Span::styled(format!("{:<40}", name), Style::default().fg(Color::White)),
+                Span::styled(power_state.to_owned(), Style::default().fg(color)),
+            ])
+        }).collect::<Vec<Spans>>();
+        f.render_widget(Paragraph::new(vms), chunk[0]);
+
+        let phrase = form.phrase();
+        let typed_color = if form.typed == phrase { Color::LightRed } else { Color::Yellow };
+        f.render_widget(Paragraph::new(form.typed.to_owned())
+            .block(Block::default().title(format!("Type '{}' to confirm", phrase)).borders(Borders::ALL).border_type(BorderType::Rounded))
+            .style(Style::default().fg(typed_color)), chunk[1]);
+
+        let powered_on = form.powered_on();
+        if powered_on > 0 {
+            let check = if form.power_off { "[x]" } else { "[ ]" };
+            f.render_widget(Paragraph::new(format!("{} Power off the {} powered on VMs first", check, powered_on)), chunk[2]);
         }
-        _ => {}
+        let footer = if powered_on > 0 && !form.power_off {
+            Span::styled("Powered on VMs can not be deleted. Tab: Power Off First  Esc: Cancel", Style::default().fg(Color::LightRed))
+        } else {
+            Span::styled("Intro: Delete  Esc: Cancel", Style::default().fg(Color::Gray))
+        };
+        f.render_widget(Paragraph::new(Spans::from(footer)).wrap(Wrap { trim: true }), chunk[3]);
     }
-
 }
 
-fn file_popup<B: Backend>(f: &mut Frame<B>, state: &mut State) {
-    if let InputMode::File = state.mode {
+fn resize_popup<B: Backend>(f: &mut Frame<B>, state: &mut State) {
+    if let InputMode::Resize = state.mode {
+        let form = &state.resize_form;
+        let vms = state.hosts.iter().flat_map(|host| host.vms.iter()).filter(|vm| vm.selected).collect::<Vec<&Vm>>();
         let block = Block::default()
-            .title("Find File")
+            .title(format!("Resize {} VMs", vms.len()))
             .title_alignment(Alignment::Center)
             .borders(Borders::ALL)
             .border_type(BorderType::Rounded);
-        let area = centered_rect(60, 25, f.size());
-        f.render_widget(Clear, area); //this clears out the background
+        let area = centered_rect(80, 70, f.size());
+        f.render_widget(Clear, area);
         f.render_widget(block, area);
 
         let chunk = Layout::default()
             .margin(2)
             .constraints(
                 [
-                    Constraint::Length(2),
                     Constraint::Length(3),
+                    Constraint::Length(3),
+                    Constraint::Min(3),
                     Constraint::Length(2),
+                    Constraint::Length(1),
                 ].as_ref()
             )
             .split(area);
 
-        let text = Paragraph::new("Enter the path to the file with the vms you like to select (File should be a YAML)")
-            .style(Style::default().fg(Color::White))
-            .alignment(Alignment::Center);
-        f.render_widget(text, chunk[0]);
+        //the field being edited is shown in yellow
+        let field_style = |field: usize| {
+            if form.field == field { Style::default().fg(Color::Yellow) } else { Style::default().fg(Color::White) }
+        };
+        let sizes = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Ratio(1, 3), Constraint::Ratio(1, 3), Constraint::Ratio(1, 3)].as_ref())
+            .split(chunk[0]);
+        [("CPU Count", &form.cpu), ("Cores per Socket", &form.cores_per_socket), ("Memory (MiB)", &form.memory_mib)]
+            .into_iter()
+            .enumerate()
+            .for_each(|(index, (title, value))| {
+                let value = if value.is_empty() { "Keep".to_string() } else { value.to_owned() };
+                f.render_widget(Paragraph::new(value)
+                    .block(Block::default().title(title).borders(Borders::ALL).border_type(BorderType::Rounded))
+                    .style(field_style(index)), sizes[index]);
+            });
+        let flag = |value: Option<bool>| match value {
+            None => "Keep",
+            Some(true) => "On",
+            Some(false) => "Off",
+        };
+        let flags = vec![
+            Spans::from(Span::styled(format!("CPU hot add:      {}", flag(form.cpu_hot_add)), field_style(3))),
+            Spans::from(Span::styled(format!("CPU hot remove:   {}", flag(form.cpu_hot_remove)), field_style(4))),
+            Spans::from(Span::styled(format!("Memory hot add:   {}", flag(form.memory_hot_add)), field_style(5))),
+        ];
+        f.render_widget(Paragraph::new(flags), chunk[1]);
+
+        //before and after of every vm, the ones that have to be powered off first are in red
+        let updates = resize_updates(form).ok();
+        let mut power_off = 0;
+        let mut table = vec![Spans::from(Span::styled(
+            format!("{:<30} {:>14} {:>22}  {:<12} {}", "VM", "CPU", "Memory (MiB)", "State", "Needs Power Off"),
+            Style::default().fg(Color::Gray),
+        ))];
+        vms.iter().for_each(|vm| {
+            let (cpu_count, memory_mib, reason) = match &updates {
+                Some((cpu, memory)) => (
+                    cpu.count.unwrap_or(vm.cpu_count),
+                    memory.size_mib.unwrap_or(vm.memory_mib as u64),
+                    resize_needs_power_off(vm, state.vm_details.get(&vm.vm_id), cpu, memory),
+                ),
+                None => (vm.cpu_count, vm.memory_mib as u64, None),
+            };
+            if reason.is_some() {
+                power_off += 1;
+            }
+            let color = if reason.is_some() { Color::LightRed } else { Color::White };
+            table.push(Spans::from(Span::styled(
+                format!("{:<30} {:>14} {:>22}  {:<12} {}",
+                        vm.name,
+                        format!("{} → {}", vm.cpu_count, cpu_count),
+                        format!("{} → {}", vm.memory_mib, memory_mib),
+                        vm.state,
+                        reason.unwrap_or("")),
+                Style::default().fg(color),
+            )));
+        });
+        f.render_widget(Paragraph::new(table), chunk[2]);
 
-        let path_input = Paragraph::new(state.file_path.to_owned())
-            .block(Block::default().title("Search VM by Name").borders(Borders::ALL).border_type(BorderType::Rounded))
-            .style(Style::default().fg(Color::White)    ,
-            );
-        f.render_widget(path_input, chunk[1]);
+        let warning = if !form.error.is_empty() {
+            form.error.to_owned()
+        } else if power_off > 0 {
+            format!("{} VMs have to be powered off for this change, vCenter refuses it while they run", power_off)
+        } else {
+            "".to_string()
+        };
+        f.render_widget(Paragraph::new(warning).style(Style::default().fg(Color::LightRed)).wrap(Wrap { trim: true }), chunk[3]);
+        f.render_widget(Paragraph::new("Up/Down: Field  Space: Keep/On/Off  Intro: Apply  Esc: Cancel")
+            .style(Style::default().fg(Color::Gray)), chunk[4]);
+    }
+}
+
+//known is the fingerprint saved before, the certificate changed since then
+fn trust_popup<B: Backend>(f: &mut Frame<B>, hostname: &str, fingerprint: &str, known: Option<&str>) {
+    let block = Block::default()
+        .title(if known.is_some() { "Changed Certificate" } else { "Unknown Certificate" })
+        .title_alignment(Alignment::Center)
+        .borders(Borders::ALL)
+        .border_type(BorderType::Rounded);
+    let area = centered_rect(60, 40, f.size());
+    f.render_widget(Clear, area);
+    f.render_widget(block, area);
+
+    let mut text = vec![
+        Spans::from(vec![Span::styled(format!("The certificate of {} is not trusted.", hostname),
+                                      Style::default().fg(Color::White),
+        )]),
+        Spans::from(""),
+        Spans::from(vec![Span::styled("SHA-256 fingerprint:", Style::default().fg(Color::White))]),
+        Spans::from(vec![Span::styled(fingerprint, Style::default().fg(Color::Yellow))]),
+        Spans::from(""),
+    ];
+    if let Some(known) = known {
+        text.push(Spans::from(vec![Span::styled("It is not the one trusted before:", Style::default().fg(Color::LightRed))]));
+        text.push(Spans::from(vec![Span::styled(known, Style::default().fg(Color::Gray))]));
+        text.push(Spans::from(""));
     }
+    text.push(Spans::from(vec![Span::styled(format!("Y: Trust it and save it in {}    N: Exit", KNOWN_HOSTS_FILE),
+                                            Style::default().fg(Color::White),
+    )]));
+    let paragraph = Paragraph::new(text)
+        .alignment(Alignment::Center)
+        .wrap(Wrap { trim: true });
+    f.render_widget(paragraph, Layout::default().margin(2).constraints([Constraint::Min(0)].as_ref()).split(area)[0]);
 }
 
 fn select_by_file(state : &mut State){
@@ -787,7 +5141,13 @@ fn select_by_file(state : &mut State){
     state.search_string = state.file_path.to_owned();
 
     //read the file
-    let file = File::open(state.file_path.to_owned()).unwrap();
+    let file = match File::open(&state.file_path) {
+        Ok(file) => file,
+        Err(e) => {
+            state.status_msg = format!("Error opening {}: {}", state.file_path, e);
+            return;
+        }
+    };
     let reader = BufReader::new(file);
     let parser = EventReader::new(reader);
 
@@ -796,18 +5156,16 @@ fn select_by_file(state : &mut State){
 
     for event in parser {
         match event {
-            Ok(XmlEvent::StartElement { name, .. }) => {
-                if name.local_name == "name" {
+            Ok(XmlEvent::StartElement { name, .. })
+                if name.local_name == "name" => {
                     current_name = Some(String::new());
                 }
-            }
-            Ok(XmlEvent::EndElement { name }) => {
-                if name.local_name == "name" {
+            Ok(XmlEvent::EndElement { name })
+                if name.local_name == "name" => {
                     if let Some(name) = current_name.take() {
                         names.push(name);
                     }
                 }
-            }
             Ok(XmlEvent::Characters(chars)) => {
                 if let Some(ref mut name) = current_name {
                     name.push_str(&chars);
@@ -860,6 +5218,100 @@ fn centered_rect(percent_x: u16, percent_y: u16, r: Rect) -> Rect {
         .split(popup_layout[1])[1]
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn expand_pattern_pads_with_leading_zeros() {
+        assert_eq!(expand_pattern("web-{01..05}").unwrap(), vec!["web-01", "web-02", "web-03", "web-04", "web-05"]);
+        assert_eq!(expand_pattern("web-{8..10}").unwrap(), vec!["web-8", "web-9", "web-10"]);
+    }
+
+    #[test]
+    fn expand_pattern_counts_down_on_reversed_range() {
+        assert_eq!(expand_pattern("db{3..1}").unwrap(), vec!["db3", "db2", "db1"]);
+    }
+
+    #[test]
+    fn expand_pattern_multiplies_ranges() {
+        assert_eq!(expand_pattern("r{1..2}-n{1..3}").unwrap(), vec!["r1-n1", "r1-n2", "r1-n3", "r2-n1", "r2-n2", "r2-n3"]);
+    }
+
+    #[test]
+    fn expand_pattern_without_range_is_the_name() {
+        assert_eq!(expand_pattern("web").unwrap(), vec!["web"]);
+    }
+
+    #[test]
+    fn expand_pattern_rejects_bad_patterns() {
+        assert!(expand_pattern("web-{01..05").unwrap_err().contains("Missing }"));
+        assert!(expand_pattern("web-{a..c}").unwrap_err().contains("not a range of numbers"));
+        assert!(expand_pattern("web-{1-5}").unwrap_err().contains("not a range like"));
+    }
+
+    #[test]
+    fn expand_pattern_stops_over_the_limit() {
+        assert_eq!(expand_pattern(&format!("vm-{{1..{}}}", CLONE_LIMIT)).unwrap().len(), CLONE_LIMIT);
+        assert!(expand_pattern(&format!("vm-{{1..{}}}", CLONE_LIMIT + 1)).is_err());
+        assert!(expand_pattern("vm-{0..4000000000}").is_err());
+        assert!(expand_pattern("vm-{1..20}-{1..20}").is_err());
+    }
+
+    fn resize_form(cpu: &str, cores: &str, memory: &str) -> ResizeForm {
+        ResizeForm { cpu: cpu.to_string(), cores_per_socket: cores.to_string(), memory_mib: memory.to_string(), ..ResizeForm::default() }
+    }
+
+    fn vm_info(cpu_hot_add: bool, memory_hot_add: bool) -> VapiVmInfo {
+        serde_json::from_value(serde_json::json!({
+            "name": "web", "power_state": "POWERED_ON", "guest_OS": "UBUNTU_64",
+            "hardware": { "version": "VMX_19" },
+            "cpu": { "count": 2, "hot_add_enabled": cpu_hot_add },
+            "memory": { "size_MiB": 4096, "hot_add_enabled": memory_hot_add },
+            "boot": { "type": "BIOS" },
+        })).unwrap()
+    }
+
+    #[test]
+    fn resize_updates_keeps_empty_fields() {
+        let (cpu, memory) = resize_updates(&resize_form("4", "", "8192")).unwrap();
+        assert_eq!((cpu.count, cpu.cores_per_socket, memory.size_mib), (Some(4), None, Some(8192)));
+        assert_eq!(resize_updates(&resize_form("", "", "")).unwrap_err(), "Nothing to change");
+    }
+
+    #[test]
+    fn resize_updates_rejects_bad_sizes() {
+        assert!(resize_updates(&resize_form("0", "", "")).is_err());
+        assert!(resize_updates(&resize_form(&(MAX_CPU_COUNT + 1).to_string(), "", "")).is_err());
+        assert!(resize_updates(&resize_form("99999999999", "", "")).is_err());
+        assert!(resize_updates(&resize_form("", "0", "")).is_err());
+        assert!(resize_updates(&resize_form("4", "8", "")).is_err());
+        assert!(resize_updates(&resize_form("6", "4", "")).is_err());
+        assert!(resize_updates(&resize_form("", "99999999999", "")).is_err());
+        assert!(resize_updates(&resize_form("", "", "4097")).is_err());
+        assert!(resize_updates(&resize_form("", "", &(MAX_MEMORY_MIB + 4).to_string())).is_err());
+        assert!(resize_updates(&resize_form("", "", &MAX_MEMORY_MIB.to_string())).is_ok());
+    }
+
+    #[test]
+    fn resize_needs_power_off_only_for_running_vms() {
+        let (cpu, memory) = resize_updates(&resize_form("", "", "2048")).unwrap();
+        assert_eq!(resize_needs_power_off(&Vm::new("web", "POWERED_OFF", "vm-1", 2, 4096), None, &cpu, &memory), None);
+        assert_eq!(resize_needs_power_off(&Vm::new("web", "POWERED_ON", "vm-1", 2, 4096), None, &cpu, &memory), Some("memory can not shrink live"));
+    }
 
+    #[test]
+    fn resize_needs_power_off_follows_hot_add() {
+        let vm = Vm::new("web", "POWERED_ON", "vm-1", 2, 4096);
+        let (cpu, memory) = resize_updates(&resize_form("4", "", "8192")).unwrap();
+        assert_eq!(resize_needs_power_off(&vm, None, &cpu, &memory), Some("CPU hot add is off"));
+        assert_eq!(resize_needs_power_off(&vm, Some(&vm_info(true, false)), &cpu, &memory), Some("memory hot add is off"));
+        assert_eq!(resize_needs_power_off(&vm, Some(&vm_info(true, true)), &cpu, &memory), None);
 
+        let (cpu, memory) = resize_updates(&resize_form("1", "", "")).unwrap();
+        assert_eq!(resize_needs_power_off(&vm, Some(&vm_info(true, true)), &cpu, &memory), Some("CPU hot remove is off"));
 
+        let (cpu, memory) = resize_updates(&resize_form("", "1", "")).unwrap();
+        assert_eq!(resize_needs_power_off(&vm, Some(&vm_info(true, true)), &cpu, &memory), Some("cores per socket"));
+    }
+}